@@ -0,0 +1,538 @@
+use crate::{Offset, Point, Radians, Rect, UndefinedUnit};
+
+/// The smallest tolerance that curves are flattened with. Smaller tolerances,
+/// including zero, negative and NaN ones, would take an unbounded number of
+/// segments, and are raised to this.
+const MIN_TOLERANCE: f32 = 1e-4;
+
+/// The most line segments a curve is flattened into, or cubic curves an arc is
+/// approximated with. Curves with huge or infinite control points would
+/// otherwise need more segments than fit in memory.
+const MAX_SEGMENTS: u32 = 1024;
+
+/// A quadratic Bézier curve.
+#[derive(Debug, PartialEq)]
+pub struct QuadraticBezier<Unit = UndefinedUnit> {
+    pub from: Point<f32, Unit>,
+    pub ctrl: Point<f32, Unit>,
+    pub to: Point<f32, Unit>,
+}
+
+impl<Unit> Clone for QuadraticBezier<Unit> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<Unit> Copy for QuadraticBezier<Unit> {}
+
+impl<Unit> QuadraticBezier<Unit> {
+    #[must_use]
+    pub fn new(from: Point<f32, Unit>, ctrl: Point<f32, Unit>, to: Point<f32, Unit>) -> Self {
+        Self { from, ctrl, to }
+    }
+
+    /// The point on the curve at `t`, where `t = 0` is `from` and `t = 1` is
+    /// `to`.
+    #[must_use]
+    pub fn sample(&self, t: f32) -> Point<f32, Unit> {
+        let mt = 1.0 - t;
+        let (a, b, c) = (mt * mt, 2.0 * mt * t, t * t);
+        Point::new(
+            a * self.from.x + b * self.ctrl.x + c * self.to.x,
+            a * self.from.y + b * self.ctrl.y + c * self.to.y,
+        )
+    }
+
+    /// Approximates the curve with line segments such that no part of the
+    /// curve is farther than `tolerance` from them. The end points of the
+    /// segments are appended to `out`, excluding `from`.
+    ///
+    /// Tolerances below a ten-thousandth of a unit, or that are NaN, are
+    /// raised to a ten-thousandth of a unit. At most 1024 segments are used,
+    /// so the tolerance is not met by curves that are too large for it.
+    pub fn flatten_into(&self, tolerance: f32, out: &mut Vec<Point<f32, Unit>>) {
+        let tolerance = tolerance.max(MIN_TOLERANCE);
+        // The distance between the curve and a chord spanning `h` of its
+        // parameter range is at most |B''| * h^2 / 8.
+        let dd = ((self.from - self.ctrl) - (self.ctrl - self.to)).length();
+        let n = (dd / (4.0 * tolerance)).sqrt().ceil().max(1.0);
+        push_samples(n, |t| self.sample(t), out);
+    }
+
+    /// The tightest axis-aligned rectangle that contains the curve.
+    #[must_use]
+    pub fn bounds(&self) -> Rect<f32, Unit> {
+        let mut bounds = Rect::new(self.from.min(&self.to), self.from.max(&self.to));
+
+        for (p0, p1, p2) in [
+            (self.from.x, self.ctrl.x, self.to.x),
+            (self.from.y, self.ctrl.y, self.to.y),
+        ] {
+            let denominator = p0 - 2.0 * p1 + p2;
+            if denominator != 0.0 {
+                let t = (p0 - p1) / denominator;
+                if t > 0.0 && t < 1.0 {
+                    include(&mut bounds, self.sample(t));
+                }
+            }
+        }
+
+        bounds
+    }
+}
+
+/// A cubic Bézier curve.
+#[derive(Debug, PartialEq)]
+pub struct CubicBezier<Unit = UndefinedUnit> {
+    pub from: Point<f32, Unit>,
+    pub ctrl1: Point<f32, Unit>,
+    pub ctrl2: Point<f32, Unit>,
+    pub to: Point<f32, Unit>,
+}
+
+impl<Unit> Clone for CubicBezier<Unit> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<Unit> Copy for CubicBezier<Unit> {}
+
+impl<Unit> CubicBezier<Unit> {
+    #[must_use]
+    pub fn new(
+        from: Point<f32, Unit>,
+        ctrl1: Point<f32, Unit>,
+        ctrl2: Point<f32, Unit>,
+        to: Point<f32, Unit>,
+    ) -> Self {
+        Self {
+            from,
+            ctrl1,
+            ctrl2,
+            to,
+        }
+    }
+
+    /// The point on the curve at `t`, where `t = 0` is `from` and `t = 1` is
+    /// `to`.
+    #[must_use]
+    pub fn sample(&self, t: f32) -> Point<f32, Unit> {
+        let mt = 1.0 - t;
+        let (a, b, c, d) = (mt * mt * mt, 3.0 * mt * mt * t, 3.0 * mt * t * t, t * t * t);
+        Point::new(
+            a * self.from.x + b * self.ctrl1.x + c * self.ctrl2.x + d * self.to.x,
+            a * self.from.y + b * self.ctrl1.y + c * self.ctrl2.y + d * self.to.y,
+        )
+    }
+
+    /// Approximates the curve with line segments such that no part of the
+    /// curve is farther than `tolerance` from them. The end points of the
+    /// segments are appended to `out`, excluding `from`.
+    ///
+    /// Tolerances below a ten-thousandth of a unit, or that are NaN, are
+    /// raised to a ten-thousandth of a unit. At most 1024 segments are used,
+    /// so the tolerance is not met by curves that are too large for it.
+    pub fn flatten_into(&self, tolerance: f32, out: &mut Vec<Point<f32, Unit>>) {
+        let tolerance = tolerance.max(MIN_TOLERANCE);
+        // |B''| is at most 6 times the largest second difference of the
+        // control points, and a chord spanning `h` of the parameter range
+        // deviates by at most |B''| * h^2 / 8.
+        let dd0 = ((self.from - self.ctrl1) - (self.ctrl1 - self.ctrl2)).length();
+        let dd1 = ((self.ctrl1 - self.ctrl2) - (self.ctrl2 - self.to)).length();
+        let n = (0.75 * dd0.max(dd1) / tolerance).sqrt().ceil().max(1.0);
+        push_samples(n, |t| self.sample(t), out);
+    }
+
+    /// The tightest axis-aligned rectangle that contains the curve.
+    #[must_use]
+    pub fn bounds(&self) -> Rect<f32, Unit> {
+        let mut bounds = Rect::new(self.from.min(&self.to), self.from.max(&self.to));
+
+        for (p0, p1, p2, p3) in [
+            (self.from.x, self.ctrl1.x, self.ctrl2.x, self.to.x),
+            (self.from.y, self.ctrl1.y, self.ctrl2.y, self.to.y),
+        ] {
+            // The roots of the derivative, divided by 3.
+            let a = p3 - 3.0 * p2 + 3.0 * p1 - p0;
+            let b = 2.0 * (p2 - 2.0 * p1 + p0);
+            let c = p1 - p0;
+
+            for t in solve_quadratic(a, b, c).into_iter().flatten() {
+                if t > 0.0 && t < 1.0 {
+                    include(&mut bounds, self.sample(t));
+                }
+            }
+        }
+
+        bounds
+    }
+}
+
+/// An elliptical arc, described by its center.
+#[derive(Debug, PartialEq)]
+pub struct Arc<Unit = UndefinedUnit> {
+    pub center: Point<f32, Unit>,
+    pub radii: Offset<f32, Unit>,
+    /// The rotation of the ellipse's X-axis.
+    pub x_rotation: Radians<f32>,
+    pub start_angle: Radians<f32>,
+    /// The angle covered by the arc. Positive angles are clockwise on screen
+    /// (with the Y-axis pointing down).
+    pub sweep_angle: Radians<f32>,
+}
+
+impl<Unit> Clone for Arc<Unit> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<Unit> Copy for Arc<Unit> {}
+
+impl<Unit> Arc<Unit> {
+    /// Converts an arc in SVG's endpoint parameterization to its center
+    /// parameterization, following the SVG 1.1 implementation notes (F.6.5).
+    ///
+    /// Radii that are too small to span the two points are scaled up. Returns
+    /// `None` if the two points are the same or either radius is zero, in
+    /// which case SVG treats the arc as a straight line (or omits it).
+    #[must_use]
+    #[allow(clippy::similar_names)]
+    pub fn from_svg(
+        from: Point<f32, Unit>,
+        to: Point<f32, Unit>,
+        radii: Offset<f32, Unit>,
+        x_rotation: Radians<f32>,
+        large_arc: bool,
+        sweep: bool,
+    ) -> Option<Self> {
+        let (mut rx, mut ry) = (radii.x.abs(), radii.y.abs());
+
+        if (from.x == to.x && from.y == to.y) || rx == 0.0 || ry == 0.0 {
+            return None;
+        }
+
+        let (sin, cos) = x_rotation.0.sin_cos();
+
+        // Step 1: move the origin to the midpoint and undo the rotation.
+        let dx = (from.x - to.x) / 2.0;
+        let dy = (from.y - to.y) / 2.0;
+        let x1 = cos * dx + sin * dy;
+        let y1 = -sin * dx + cos * dy;
+
+        // Correct out-of-range radii (F.6.6).
+        let lambda = (x1 * x1) / (rx * rx) + (y1 * y1) / (ry * ry);
+        if lambda > 1.0 {
+            rx *= lambda.sqrt();
+            ry *= lambda.sqrt();
+        }
+
+        // Step 2: find the center in the rotated frame.
+        let numerator = rx * rx * ry * ry - rx * rx * y1 * y1 - ry * ry * x1 * x1;
+        let denominator = rx * rx * y1 * y1 + ry * ry * x1 * x1;
+        let mut coefficient = (numerator / denominator).max(0.0).sqrt();
+        if large_arc == sweep {
+            coefficient = -coefficient;
+        }
+        let cx1 = coefficient * rx * y1 / ry;
+        let cy1 = -coefficient * ry * x1 / rx;
+
+        // Step 3: rotate the center back into place.
+        let center = Point::new(
+            cos * cx1 - sin * cy1 + (from.x + to.x) / 2.0,
+            sin * cx1 + cos * cy1 + (from.y + to.y) / 2.0,
+        );
+
+        // Step 4: compute the angles.
+        let u = Offset::<f32>::new((x1 - cx1) / rx, (y1 - cy1) / ry);
+        let v = Offset::<f32>::new((-x1 - cx1) / rx, (-y1 - cy1) / ry);
+
+        let start_angle = u.y.atan2(u.x);
+        let mut sweep_angle = u.cross(&v).atan2(u.dot(&v));
+
+        if !sweep && sweep_angle > 0.0 {
+            sweep_angle -= std::f32::consts::TAU;
+        } else if sweep && sweep_angle < 0.0 {
+            sweep_angle += std::f32::consts::TAU;
+        }
+
+        Some(Self {
+            center,
+            radii: Offset::new(rx, ry),
+            x_rotation,
+            start_angle: Radians(start_angle),
+            sweep_angle: Radians(sweep_angle),
+        })
+    }
+
+    /// The point on the ellipse at `angle`.
+    #[must_use]
+    pub fn sample(&self, angle: Radians<f32>) -> Point<f32, Unit> {
+        let (sin_r, cos_r) = self.x_rotation.0.sin_cos();
+        let (sin_a, cos_a) = angle.0.sin_cos();
+        let (x, y) = (self.radii.x * cos_a, self.radii.y * sin_a);
+
+        Point::new(
+            self.center.x + cos_r * x - sin_r * y,
+            self.center.y + sin_r * x + cos_r * y,
+        )
+    }
+
+    /// The point where the arc starts.
+    #[must_use]
+    pub fn from(&self) -> Point<f32, Unit> {
+        self.sample(self.start_angle)
+    }
+
+    /// The point where the arc ends.
+    #[must_use]
+    pub fn to(&self) -> Point<f32, Unit> {
+        self.sample(Radians(self.start_angle.0 + self.sweep_angle.0))
+    }
+
+    /// Approximates the arc with cubic Bézier curves, each spanning at most a
+    /// quarter turn. Arcs that sweep more than 256 full turns are split into
+    /// 1024 curves instead.
+    pub fn for_each_cubic(&self, mut callback: impl FnMut(CubicBezier<Unit>)) {
+        let quarter_turn = std::f32::consts::FRAC_PI_2;
+
+        #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+        let n = ((self.sweep_angle.0.abs() / quarter_turn).ceil() as u32).clamp(1, MAX_SEGMENTS);
+
+        let step = self.sweep_angle.0 / n as f32;
+        let k = 4.0 / 3.0 * (step / 4.0).tan();
+
+        let (sin_r, cos_r) = self.x_rotation.0.sin_cos();
+        let tangent = |angle: f32| {
+            let (sin_a, cos_a) = angle.sin_cos();
+            let (dx, dy) = (-self.radii.x * sin_a, self.radii.y * cos_a);
+            Offset::new(cos_r * dx - sin_r * dy, sin_r * dx + cos_r * dy)
+        };
+
+        let mut angle = self.start_angle.0;
+        let mut from = self.sample(Radians(angle));
+        for i in 0..n {
+            let next_angle = if i + 1 == n {
+                self.start_angle.0 + self.sweep_angle.0
+            } else {
+                angle + step
+            };
+            let to = self.sample(Radians(next_angle));
+
+            callback(CubicBezier::new(
+                from,
+                from + tangent(angle) * k,
+                to - tangent(next_angle) * k,
+                to,
+            ));
+
+            angle = next_angle;
+            from = to;
+        }
+    }
+}
+
+/// Appends `n` evenly spaced samples of a curve over `(0, 1]`, with `n`
+/// clamped to between 1 and `MAX_SEGMENTS`.
+fn push_samples<Unit>(
+    n: f32,
+    sample: impl Fn(f32) -> Point<f32, Unit>,
+    out: &mut Vec<Point<f32, Unit>>,
+) {
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    let n = (n as u32).clamp(1, MAX_SEGMENTS);
+
+    let step = 1.0 / n as f32;
+    for i in 1..n {
+        out.push(sample(i as f32 * step));
+    }
+    out.push(sample(1.0));
+}
+
+fn include<Unit>(bounds: &mut Rect<f32, Unit>, point: Point<f32, Unit>) {
+    bounds.p0 = bounds.p0.min(&point);
+    bounds.p1 = bounds.p1.max(&point);
+}
+
+/// The real roots of `a * t^2 + b * t + c`.
+fn solve_quadratic(a: f32, b: f32, c: f32) -> [Option<f32>; 2] {
+    if a.abs() < f32::EPSILON {
+        if b == 0.0 {
+            return [None, None];
+        }
+        return [Some(-c / b), None];
+    }
+
+    let discriminant = b * b - 4.0 * a * c;
+    if discriminant < 0.0 {
+        return [None, None];
+    }
+
+    let root = discriminant.sqrt();
+    [Some((-b + root) / (2.0 * a)), Some((-b - root) / (2.0 * a))]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Angle, Degrees};
+
+    #[test]
+    fn arc_from_svg() {
+        let arc = Arc::<()>::from_svg(
+            Point::new(0.0, 0.0),
+            Point::new(10.0, 0.0),
+            Offset::new(5.0, 5.0),
+            Radians(0.0),
+            false,
+            false,
+        )
+        .unwrap();
+
+        assert!((arc.center - Point::new(5.0, 0.0)).length() < 1e-4);
+        assert!((arc.sweep_angle.to_degrees().0 + 180.0).abs() < 1e-3);
+        assert!((arc.from() - Point::new(0.0, 0.0)).length() < 1e-4);
+        assert!((arc.to() - Point::new(10.0, 0.0)).length() < 1e-4);
+
+        // Radii that are too small are scaled up to fit.
+        let arc = Arc::<()>::from_svg(
+            Point::new(0.0, 0.0),
+            Point::new(10.0, 0.0),
+            Offset::new(1.0, 1.0),
+            Degrees(30.0).to_radians(),
+            true,
+            true,
+        )
+        .unwrap();
+        assert!((arc.radii.x - 5.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn arc_cubics_stay_on_the_ellipse() {
+        let arc = Arc::<()> {
+            center: Point::new(3.0, -2.0),
+            radii: Offset::new(10.0, 4.0),
+            x_rotation: Degrees(20.0).to_radians(),
+            start_angle: Radians(0.3),
+            sweep_angle: Radians(-4.0),
+        };
+
+        let mut curves = Vec::new();
+        arc.for_each_cubic(|curve| curves.push(curve));
+        assert_eq!(curves.len(), 3);
+        assert_eq!(curves[0].from, arc.from());
+        assert_eq!(curves[2].to, arc.to());
+
+        for curve in curves {
+            let mid = curve.sample(0.5);
+            // Undo the rotation and check that the midpoint is on the ellipse.
+            let (sin, cos) = arc.x_rotation.sin_cos();
+            let d = mid - arc.center;
+            let (x, y) = (cos * d.x + sin * d.y, -sin * d.x + cos * d.y);
+            let r = (x / arc.radii.x).powi(2) + (y / arc.radii.y).powi(2);
+            assert!((r - 1.0).abs() < 1e-3, "{r}");
+        }
+    }
+
+    #[test]
+    fn flatten_within_tolerance() {
+        let curve = CubicBezier::<()>::new(
+            Point::new(0.0, 0.0),
+            Point::new(0.0, 100.0),
+            Point::new(100.0, 100.0),
+            Point::new(100.0, 0.0),
+        );
+
+        for tolerance in [1.0, 0.1, 0.01] {
+            let mut points = vec![curve.from];
+            curve.flatten_into(tolerance, &mut points);
+            assert_eq!(points.last(), Some(&curve.to));
+
+            // The curve is sampled uniformly, so the middle of each chord
+            // corresponds to the middle of its parameter range.
+            let n = (points.len() - 1) as f32;
+            for (i, pair) in points.windows(2).enumerate() {
+                let mid = pair[0].lerp(&pair[1], 0.5);
+                let distance = (curve.sample((i as f32 + 0.5) / n) - mid).length();
+                assert!(distance <= tolerance, "{distance} > {tolerance}");
+            }
+        }
+    }
+
+    #[test]
+    fn flatten_degenerate_tolerance() {
+        let curve = QuadraticBezier::<()>::new(
+            Point::new(0.0, 0.0),
+            Point::new(50.0, 100.0),
+            Point::new(100.0, 0.0),
+        );
+
+        // Tolerances that would never be met are raised to the minimum.
+        let mut expected = Vec::new();
+        curve.flatten_into(MIN_TOLERANCE, &mut expected);
+        for tolerance in [0.0, -1.0, f32::NAN] {
+            let mut points = Vec::new();
+            curve.flatten_into(tolerance, &mut points);
+            assert_eq!(points, expected, "{tolerance}");
+        }
+    }
+
+    #[test]
+    fn flatten_huge_curves() {
+        // Huge or infinite control points are flattened into a bounded number
+        // of segments.
+        for ctrl in [1e20, f32::INFINITY] {
+            let mut points = Vec::new();
+            QuadraticBezier::<()>::new(Point::new(0.0, 0.0), Point::new(ctrl, 0.0), Point::zero())
+                .flatten_into(0.1, &mut points);
+            assert_eq!(points.len(), MAX_SEGMENTS as usize);
+
+            let mut points = Vec::new();
+            CubicBezier::<()>::new(
+                Point::new(0.0, 0.0),
+                Point::new(ctrl, 0.0),
+                Point::new(0.0, ctrl),
+                Point::zero(),
+            )
+            .flatten_into(0.1, &mut points);
+            assert_eq!(points.len(), MAX_SEGMENTS as usize);
+        }
+
+        let arc = Arc::<()> {
+            center: Point::zero(),
+            radii: Offset::new(1.0, 1.0),
+            x_rotation: Radians(0.0),
+            start_angle: Radians(0.0),
+            sweep_angle: Radians(1e20),
+        };
+        let mut num_curves = 0;
+        arc.for_each_cubic(|_| num_curves += 1);
+        assert_eq!(num_curves, MAX_SEGMENTS);
+    }
+
+    #[test]
+    fn curve_bounds() {
+        let curve = QuadraticBezier::<()>::new(
+            Point::new(0.0, 0.0),
+            Point::new(5.0, 10.0),
+            Point::new(10.0, 0.0),
+        );
+        assert_eq!(
+            curve.bounds(),
+            Rect::new(Point::new(0.0, 0.0), Point::new(10.0, 5.0))
+        );
+
+        let curve = CubicBezier::<()>::new(
+            Point::new(0.0, 0.0),
+            Point::new(0.0, 10.0),
+            Point::new(10.0, 10.0),
+            Point::new(10.0, 0.0),
+        );
+        assert_eq!(
+            curve.bounds(),
+            Rect::new(Point::new(0.0, 0.0), Point::new(10.0, 7.5))
+        );
+    }
+}
@@ -1,8 +1,16 @@
 use std::{
     marker::PhantomData,
-    ops::{Mul, Sub},
+    ops::{Add, AddAssign, Div, DivAssign, Mul, MulAssign, Neg, Sub, SubAssign},
 };
 
+mod bezier;
+mod path;
+mod svg_path;
+
+pub use bezier::{Arc, CubicBezier, QuadraticBezier};
+pub use path::{Iter as PathIter, Path, PathBuilder, PathEvent, Polyline};
+pub use svg_path::SvgPathError;
+
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub struct UndefinedUnit;
 
@@ -98,20 +106,20 @@ impl<T: num::Real> Angle<T> for Degrees<T> {
     }
 
     fn sin(&self) -> T {
-        self.0.sin()
+        self.to_radians().sin()
     }
 
     fn cos(&self) -> T {
-        self.0.cos()
+        self.to_radians().cos()
     }
 
     fn sin_cos(&self) -> (T, T) {
-        self.0.sin_cos()
+        self.to_radians().sin_cos()
     }
 }
 
 #[repr(C)]
-#[derive(Copy, Debug, PartialEq, Eq)]
+#[derive(Debug, PartialEq, Eq)]
 pub struct Point<T, Unit = UndefinedUnit> {
     pub x: T,
     pub y: T,
@@ -131,6 +139,10 @@ where
     }
 }
 
+// Implemented manually (as is `Clone`) so that `Unit` does not need to be
+// `Copy`.
+impl<T: Copy, Unit> Copy for Point<T, Unit> {}
+
 impl<T, Unit> Point<T, Unit> {
     #[must_use]
     pub fn new(x: T, y: T) -> Self {
@@ -158,6 +170,35 @@ impl<T, Unit> Point<T, Unit> {
     }
 }
 
+impl<T, Unit> Point<T, Unit>
+where
+    T: Copy + Add<Output = T> + Sub<Output = T> + Mul<Output = T>,
+{
+    /// Linearly interpolates between two points, where `t = 0` is `self` and
+    /// `t = 1` is `other`.
+    #[must_use]
+    pub fn lerp(&self, other: &Self, t: T) -> Self {
+        Self::new(
+            self.x + (other.x - self.x) * t,
+            self.y + (other.y - self.y) * t,
+        )
+    }
+}
+
+impl<T: PartialOrd + Copy, Unit> Point<T, Unit> {
+    /// The component-wise minimum of two points.
+    #[must_use]
+    pub fn min(&self, other: &Self) -> Self {
+        Self::new(min(self.x, other.x), min(self.y, other.y))
+    }
+
+    /// The component-wise maximum of two points.
+    #[must_use]
+    pub fn max(&self, other: &Self) -> Self {
+        Self::new(max(self.x, other.x), max(self.y, other.y))
+    }
+}
+
 impl<T: Default, Unit> Default for Point<T, Unit> {
     fn default() -> Self {
         Self::new(T::default(), T::default())
@@ -172,7 +213,65 @@ impl<T: Sub, Unit> Sub for Point<T, Unit> {
     }
 }
 
+impl<T: Add, Unit> Add<Offset<T, Unit>> for Point<T, Unit> {
+    type Output = Point<T::Output, Unit>;
+
+    fn add(self, rhs: Offset<T, Unit>) -> Self::Output {
+        Self::Output::new(self.x + rhs.x, self.y + rhs.y)
+    }
+}
+
+impl<T: Sub, Unit> Sub<Offset<T, Unit>> for Point<T, Unit> {
+    type Output = Point<T::Output, Unit>;
+
+    fn sub(self, rhs: Offset<T, Unit>) -> Self::Output {
+        Self::Output::new(self.x - rhs.x, self.y - rhs.y)
+    }
+}
+
+impl<T: AddAssign, Unit> AddAssign<Offset<T, Unit>> for Point<T, Unit> {
+    fn add_assign(&mut self, rhs: Offset<T, Unit>) {
+        self.x += rhs.x;
+        self.y += rhs.y;
+    }
+}
+
+impl<T: SubAssign, Unit> SubAssign<Offset<T, Unit>> for Point<T, Unit> {
+    fn sub_assign(&mut self, rhs: Offset<T, Unit>) {
+        self.x -= rhs.x;
+        self.y -= rhs.y;
+    }
+}
+
+impl<T: Mul, Unit> Mul<Scale<T, Unit>> for Point<T, Unit> {
+    type Output = Point<T::Output, Unit>;
+
+    fn mul(self, rhs: Scale<T, Unit>) -> Self::Output {
+        Self::Output::new(self.x * rhs.x, self.y * rhs.y)
+    }
+}
+
+impl<T: Div, Unit> Div<Scale<T, Unit>> for Point<T, Unit> {
+    type Output = Point<T::Output, Unit>;
+
+    fn div(self, rhs: Scale<T, Unit>) -> Self::Output {
+        Self::Output::new(self.x / rhs.x, self.y / rhs.y)
+    }
+}
+
+/// A displacement between two points.
+///
+/// Offsets can only be combined with points and offsets in the same unit:
+///
+/// ```compile_fail
+/// use geometry::{Offset, Point, ScreenSpace, WorldSpace};
+///
+/// let p = Point::<f32, WorldSpace>::new(1.0, 2.0);
+/// let o = Offset::<f32, ScreenSpace>::new(3.0, 4.0);
+/// let _ = p + o;
+/// ```
 #[repr(C)]
+#[derive(Debug, PartialEq, Eq)]
 pub struct Offset<T, Unit = UndefinedUnit> {
     pub x: T,
     pub y: T,
@@ -206,6 +305,74 @@ impl<T, Unit> Offset<T, Unit> {
     }
 }
 
+impl<T, Unit> Clone for Offset<T, Unit>
+where
+    T: Clone,
+{
+    fn clone(&self) -> Self {
+        Self {
+            x: self.x.clone(),
+            y: self.y.clone(),
+            _unit: PhantomData,
+        }
+    }
+}
+
+impl<T: Copy, Unit> Copy for Offset<T, Unit> {}
+
+impl<T, Unit> Offset<T, Unit>
+where
+    T: Copy + Add<Output = T> + Sub<Output = T> + Mul<Output = T>,
+{
+    /// Linearly interpolates between two offsets, where `t = 0` is `self` and
+    /// `t = 1` is `other`.
+    #[must_use]
+    pub fn lerp(&self, other: &Self, t: T) -> Self {
+        Self::new(
+            self.x + (other.x - self.x) * t,
+            self.y + (other.y - self.y) * t,
+        )
+    }
+
+    #[must_use]
+    pub fn dot(&self, other: &Self) -> T {
+        self.x * other.x + self.y * other.y
+    }
+
+    /// The Z component of the 3D cross product. Its sign is the direction of
+    /// rotation from `self` to `other`.
+    #[must_use]
+    pub fn cross(&self, other: &Self) -> T {
+        self.x * other.y - self.y * other.x
+    }
+
+    #[must_use]
+    pub fn square_length(&self) -> T {
+        self.dot(self)
+    }
+}
+
+impl<T: num::Real, Unit> Offset<T, Unit> {
+    #[must_use]
+    pub fn length(&self) -> T {
+        self.square_length().sqrt()
+    }
+}
+
+impl<T: PartialOrd + Copy, Unit> Offset<T, Unit> {
+    /// The component-wise minimum of two offsets.
+    #[must_use]
+    pub fn min(&self, other: &Self) -> Self {
+        Self::new(min(self.x, other.x), min(self.y, other.y))
+    }
+
+    /// The component-wise maximum of two offsets.
+    #[must_use]
+    pub fn max(&self, other: &Self) -> Self {
+        Self::new(max(self.x, other.x), max(self.y, other.y))
+    }
+}
+
 impl<T, Unit> From<Point<T, Unit>> for Offset<T, Unit> {
     fn from(point: Point<T, Unit>) -> Self {
         Self {
@@ -222,7 +389,92 @@ impl<T: Default, Unit> Default for Offset<T, Unit> {
     }
 }
 
+impl<T: Add, Unit> Add<Offset<T, Unit>> for Offset<T, Unit> {
+    type Output = Offset<T::Output, Unit>;
+
+    fn add(self, rhs: Offset<T, Unit>) -> Self::Output {
+        Self::Output::new(self.x + rhs.x, self.y + rhs.y)
+    }
+}
+
+impl<T: Sub, Unit> Sub<Offset<T, Unit>> for Offset<T, Unit> {
+    type Output = Offset<T::Output, Unit>;
+
+    fn sub(self, rhs: Offset<T, Unit>) -> Self::Output {
+        Self::Output::new(self.x - rhs.x, self.y - rhs.y)
+    }
+}
+
+impl<T: AddAssign, Unit> AddAssign<Offset<T, Unit>> for Offset<T, Unit> {
+    fn add_assign(&mut self, rhs: Offset<T, Unit>) {
+        self.x += rhs.x;
+        self.y += rhs.y;
+    }
+}
+
+impl<T: SubAssign, Unit> SubAssign<Offset<T, Unit>> for Offset<T, Unit> {
+    fn sub_assign(&mut self, rhs: Offset<T, Unit>) {
+        self.x -= rhs.x;
+        self.y -= rhs.y;
+    }
+}
+
+impl<T: Neg, Unit> Neg for Offset<T, Unit> {
+    type Output = Offset<T::Output, Unit>;
+
+    fn neg(self) -> Self::Output {
+        Self::Output::new(-self.x, -self.y)
+    }
+}
+
+impl<T: Mul + Copy, Unit> Mul<T> for Offset<T, Unit> {
+    type Output = Offset<T::Output, Unit>;
+
+    fn mul(self, rhs: T) -> Self::Output {
+        Self::Output::new(self.x * rhs, self.y * rhs)
+    }
+}
+
+impl<T: Div + Copy, Unit> Div<T> for Offset<T, Unit> {
+    type Output = Offset<T::Output, Unit>;
+
+    fn div(self, rhs: T) -> Self::Output {
+        Self::Output::new(self.x / rhs, self.y / rhs)
+    }
+}
+
+impl<T: MulAssign + Copy, Unit> MulAssign<T> for Offset<T, Unit> {
+    fn mul_assign(&mut self, rhs: T) {
+        self.x *= rhs;
+        self.y *= rhs;
+    }
+}
+
+impl<T: DivAssign + Copy, Unit> DivAssign<T> for Offset<T, Unit> {
+    fn div_assign(&mut self, rhs: T) {
+        self.x /= rhs;
+        self.y /= rhs;
+    }
+}
+
+impl<T: Mul, Unit> Mul<Scale<T, Unit>> for Offset<T, Unit> {
+    type Output = Offset<T::Output, Unit>;
+
+    fn mul(self, rhs: Scale<T, Unit>) -> Self::Output {
+        Self::Output::new(self.x * rhs.x, self.y * rhs.y)
+    }
+}
+
+impl<T: Div, Unit> Div<Scale<T, Unit>> for Offset<T, Unit> {
+    type Output = Offset<T::Output, Unit>;
+
+    fn div(self, rhs: Scale<T, Unit>) -> Self::Output {
+        Self::Output::new(self.x / rhs.x, self.y / rhs.y)
+    }
+}
+
 #[repr(C)]
+#[derive(Debug, PartialEq, Eq)]
 pub struct Scale<T, Unit = UndefinedUnit> {
     pub x: T,
     pub y: T,
@@ -256,13 +508,61 @@ impl<T, Unit> Scale<T, Unit> {
     }
 }
 
+impl<T, Unit> Clone for Scale<T, Unit>
+where
+    T: Clone,
+{
+    fn clone(&self) -> Self {
+        Self {
+            x: self.x.clone(),
+            y: self.y.clone(),
+            _unit: PhantomData,
+        }
+    }
+}
+
+impl<T: Copy, Unit> Copy for Scale<T, Unit> {}
+
 impl<T: Default, Unit> Default for Scale<T, Unit> {
     fn default() -> Self {
         Self::new(T::default(), T::default())
     }
 }
 
+impl<T: Mul, Unit> Mul<Scale<T, Unit>> for Scale<T, Unit> {
+    type Output = Scale<T::Output, Unit>;
+
+    fn mul(self, rhs: Scale<T, Unit>) -> Self::Output {
+        Self::Output::new(self.x * rhs.x, self.y * rhs.y)
+    }
+}
+
+impl<T: Div, Unit> Div<Scale<T, Unit>> for Scale<T, Unit> {
+    type Output = Scale<T::Output, Unit>;
+
+    fn div(self, rhs: Scale<T, Unit>) -> Self::Output {
+        Self::Output::new(self.x / rhs.x, self.y / rhs.y)
+    }
+}
+
+impl<T: Mul + Copy, Unit> Mul<T> for Scale<T, Unit> {
+    type Output = Scale<T::Output, Unit>;
+
+    fn mul(self, rhs: T) -> Self::Output {
+        Self::Output::new(self.x * rhs, self.y * rhs)
+    }
+}
+
+impl<T: Div + Copy, Unit> Div<T> for Scale<T, Unit> {
+    type Output = Scale<T::Output, Unit>;
+
+    fn div(self, rhs: T) -> Self::Output {
+        Self::Output::new(self.x / rhs, self.y / rhs)
+    }
+}
+
 #[repr(C)]
+#[derive(Debug)]
 pub struct Extent<T, Unit = UndefinedUnit> {
     pub width: T,
     pub height: T,
@@ -288,6 +588,35 @@ impl<T, Unit> Extent<T, Unit> {
     }
 }
 
+impl<T, Unit> Clone for Extent<T, Unit>
+where
+    T: Clone,
+{
+    fn clone(&self) -> Self {
+        Self {
+            width: self.width.clone(),
+            height: self.height.clone(),
+            _unit: PhantomData,
+        }
+    }
+}
+
+impl<T: Copy, Unit> Copy for Extent<T, Unit> {}
+
+impl<T: PartialOrd + Copy, Unit> Extent<T, Unit> {
+    /// The component-wise minimum of two extents.
+    #[must_use]
+    pub fn min(&self, other: &Self) -> Self {
+        Self::new(min(self.width, other.width), min(self.height, other.height))
+    }
+
+    /// The component-wise maximum of two extents.
+    #[must_use]
+    pub fn max(&self, other: &Self) -> Self {
+        Self::new(max(self.width, other.width), max(self.height, other.height))
+    }
+}
+
 impl<T: Default, Unit> Default for Extent<T, Unit> {
     fn default() -> Self {
         Self::new(T::default(), T::default())
@@ -300,18 +629,175 @@ impl<T: PartialEq, Unit> PartialEq for Extent<T, Unit> {
     }
 }
 
+impl<T: Add, Unit> Add<Extent<T, Unit>> for Extent<T, Unit> {
+    type Output = Extent<T::Output, Unit>;
+
+    fn add(self, rhs: Extent<T, Unit>) -> Self::Output {
+        Self::Output::new(self.width + rhs.width, self.height + rhs.height)
+    }
+}
+
+impl<T: Sub, Unit> Sub<Extent<T, Unit>> for Extent<T, Unit> {
+    type Output = Extent<T::Output, Unit>;
+
+    fn sub(self, rhs: Extent<T, Unit>) -> Self::Output {
+        Self::Output::new(self.width - rhs.width, self.height - rhs.height)
+    }
+}
+
+impl<T: Mul + Copy, Unit> Mul<T> for Extent<T, Unit> {
+    type Output = Extent<T::Output, Unit>;
+
+    fn mul(self, rhs: T) -> Self::Output {
+        Self::Output::new(self.width * rhs, self.height * rhs)
+    }
+}
+
+impl<T: Div + Copy, Unit> Div<T> for Extent<T, Unit> {
+    type Output = Extent<T::Output, Unit>;
+
+    fn div(self, rhs: T) -> Self::Output {
+        Self::Output::new(self.width / rhs, self.height / rhs)
+    }
+}
+
+impl<T: MulAssign + Copy, Unit> MulAssign<T> for Extent<T, Unit> {
+    fn mul_assign(&mut self, rhs: T) {
+        self.width *= rhs;
+        self.height *= rhs;
+    }
+}
+
+impl<T: DivAssign + Copy, Unit> DivAssign<T> for Extent<T, Unit> {
+    fn div_assign(&mut self, rhs: T) {
+        self.width /= rhs;
+        self.height /= rhs;
+    }
+}
+
+impl<T: Mul, Unit> Mul<Scale<T, Unit>> for Extent<T, Unit> {
+    type Output = Extent<T::Output, Unit>;
+
+    fn mul(self, rhs: Scale<T, Unit>) -> Self::Output {
+        Self::Output::new(self.width * rhs.x, self.height * rhs.y)
+    }
+}
+
+impl<T: Div, Unit> Div<Scale<T, Unit>> for Extent<T, Unit> {
+    type Output = Extent<T::Output, Unit>;
+
+    fn div(self, rhs: Scale<T, Unit>) -> Self::Output {
+        Self::Output::new(self.width / rhs.x, self.height / rhs.y)
+    }
+}
+
+/// Distances from each edge of a rectangle, used to grow or shrink it.
+#[repr(C)]
+#[derive(Debug, PartialEq, Eq)]
+pub struct Insets<T, Unit = UndefinedUnit> {
+    pub left: T,
+    pub top: T,
+    pub right: T,
+    pub bottom: T,
+    _unit: PhantomData<Unit>,
+}
+
+impl<T, Unit> Clone for Insets<T, Unit>
+where
+    T: Clone,
+{
+    fn clone(&self) -> Self {
+        Self {
+            left: self.left.clone(),
+            top: self.top.clone(),
+            right: self.right.clone(),
+            bottom: self.bottom.clone(),
+            _unit: PhantomData,
+        }
+    }
+}
+
+impl<T: Copy, Unit> Copy for Insets<T, Unit> {}
+
+impl<T, Unit> Insets<T, Unit> {
+    #[must_use]
+    pub fn new(left: T, top: T, right: T, bottom: T) -> Self {
+        Self {
+            left,
+            top,
+            right,
+            bottom,
+            _unit: PhantomData,
+        }
+    }
+
+    /// Insets with the same distance on every side.
+    #[must_use]
+    pub fn uniform(value: T) -> Self
+    where
+        T: Copy,
+    {
+        Self::new(value, value, value, value)
+    }
+
+    #[must_use]
+    pub fn zero() -> Self
+    where
+        T: num::Zero,
+    {
+        Self::new(T::zero(), T::zero(), T::zero(), T::zero())
+    }
+}
+
+impl<T: Default, Unit> Default for Insets<T, Unit> {
+    fn default() -> Self {
+        Self::new(T::default(), T::default(), T::default(), T::default())
+    }
+}
+
+/// An axis-aligned rectangle between two corners.
+///
+/// A rectangle is normalized when `p0` is its top-left corner and `p1` is its
+/// bottom-right corner. Rectangles are half-open: they contain points on their
+/// top and left edges, but not on their bottom and right edges. Unless
+/// otherwise noted, methods assume that the rectangle is normalized.
 #[repr(C)]
+#[derive(Debug, PartialEq, Eq)]
 pub struct Rect<T, Unit = UndefinedUnit> {
     pub p0: Point<T, Unit>,
     pub p1: Point<T, Unit>,
 }
 
+impl<T, Unit> Clone for Rect<T, Unit>
+where
+    T: Clone,
+{
+    fn clone(&self) -> Self {
+        Self {
+            p0: self.p0.clone(),
+            p1: self.p1.clone(),
+        }
+    }
+}
+
+impl<T: Copy, Unit> Copy for Rect<T, Unit> {}
+
 impl<T, Unit> Rect<T, Unit> {
     #[must_use]
     pub fn new(p0: Point<T, Unit>, p1: Point<T, Unit>) -> Self {
         Self { p0, p1 }
     }
 
+    #[must_use]
+    #[allow(clippy::needless_pass_by_value)]
+    pub fn from_origin_extent(origin: Point<T, Unit>, extent: Extent<T, Unit>) -> Self
+    where
+        T: Add<Output = T> + Copy,
+    {
+        let p1 = Point::new(origin.x + extent.width, origin.y + extent.height);
+        Self::new(origin, p1)
+    }
+
     #[must_use]
     pub fn zero() -> Self
     where
@@ -327,6 +813,150 @@ impl<T, Unit> Rect<T, Unit> {
     {
         Extent::new(self.p1.x - self.p0.x, self.p1.y - self.p0.y)
     }
+
+    /// Returns `true` if the rectangle has no area.
+    #[must_use]
+    pub fn is_empty(&self) -> bool
+    where
+        T: PartialOrd,
+    {
+        self.p1.x <= self.p0.x || self.p1.y <= self.p0.y
+    }
+
+    /// Swaps the corners' coordinates as needed so that `p0` is the top-left
+    /// corner and `p1` is the bottom-right corner.
+    #[must_use]
+    pub fn normalize(&self) -> Self
+    where
+        T: PartialOrd + Copy,
+    {
+        Self::new(
+            Point::new(min(self.p0.x, self.p1.x), min(self.p0.y, self.p1.y)),
+            Point::new(max(self.p0.x, self.p1.x), max(self.p0.y, self.p1.y)),
+        )
+    }
+
+    #[must_use]
+    pub fn center(&self) -> Point<T, Unit>
+    where
+        T: num::One + Add<Output = T> + Div<Output = T> + Copy,
+    {
+        let two = T::one() + T::one();
+        Point::new((self.p0.x + self.p1.x) / two, (self.p0.y + self.p1.y) / two)
+    }
+
+    #[must_use]
+    pub fn contains(&self, point: &Point<T, Unit>) -> bool
+    where
+        T: PartialOrd,
+    {
+        self.p0.x <= point.x && point.x < self.p1.x && self.p0.y <= point.y && point.y < self.p1.y
+    }
+
+    /// Returns `true` if the two rectangles overlap. Rectangles that only
+    /// share an edge do not overlap.
+    #[must_use]
+    pub fn intersects(&self, other: &Self) -> bool
+    where
+        T: PartialOrd,
+    {
+        self.p0.x < other.p1.x
+            && other.p0.x < self.p1.x
+            && self.p0.y < other.p1.y
+            && other.p0.y < self.p1.y
+    }
+
+    /// The area shared by both rectangles, if any.
+    #[must_use]
+    pub fn intersection(&self, other: &Self) -> Option<Self>
+    where
+        T: PartialOrd + Copy,
+    {
+        let rect = Self::new(
+            Point::new(max(self.p0.x, other.p0.x), max(self.p0.y, other.p0.y)),
+            Point::new(min(self.p1.x, other.p1.x), min(self.p1.y, other.p1.y)),
+        );
+
+        (!rect.is_empty()).then_some(rect)
+    }
+
+    /// The smallest rectangle that contains both rectangles. Empty rectangles
+    /// are ignored.
+    #[must_use]
+    pub fn union(&self, other: &Self) -> Self
+    where
+        T: PartialOrd + Copy,
+    {
+        if self.is_empty() {
+            *other
+        } else if other.is_empty() {
+            *self
+        } else {
+            Self::new(
+                Point::new(min(self.p0.x, other.p0.x), min(self.p0.y, other.p0.y)),
+                Point::new(max(self.p1.x, other.p1.x), max(self.p1.y, other.p1.y)),
+            )
+        }
+    }
+
+    /// Moves each edge outward by the matching inset.
+    #[must_use]
+    pub fn inflate(&self, insets: Insets<T, Unit>) -> Self
+    where
+        T: Add<Output = T> + Sub<Output = T> + Copy,
+    {
+        Self::new(
+            Point::new(self.p0.x - insets.left, self.p0.y - insets.top),
+            Point::new(self.p1.x + insets.right, self.p1.y + insets.bottom),
+        )
+    }
+
+    /// Moves each edge inward by the matching inset. The result may be empty
+    /// or inverted if the insets are larger than the rectangle.
+    #[must_use]
+    pub fn deflate(&self, insets: Insets<T, Unit>) -> Self
+    where
+        T: Add<Output = T> + Sub<Output = T> + Copy,
+    {
+        Self::new(
+            Point::new(self.p0.x + insets.left, self.p0.y + insets.top),
+            Point::new(self.p1.x - insets.right, self.p1.y - insets.bottom),
+        )
+    }
+
+    #[must_use]
+    #[allow(clippy::needless_pass_by_value)]
+    pub fn translate(&self, offset: Offset<T, Unit>) -> Self
+    where
+        T: Add<Output = T> + Copy,
+    {
+        Self::new(
+            Point::new(self.p0.x + offset.x, self.p0.y + offset.y),
+            Point::new(self.p1.x + offset.x, self.p1.y + offset.y),
+        )
+    }
+}
+
+impl<T: Default, Unit> Default for Rect<T, Unit> {
+    fn default() -> Self {
+        Self::new(Point::default(), Point::default())
+    }
+}
+
+fn min<T: PartialOrd>(a: T, b: T) -> T {
+    if b < a {
+        b
+    } else {
+        a
+    }
+}
+
+fn max<T: PartialOrd>(a: T, b: T) -> T {
+    if b > a {
+        b
+    } else {
+        a
+    }
 }
 
 /// A 2D transform stored as a 3x3 matrix in column-major order compressed into
@@ -351,6 +981,20 @@ pub struct Transform<T: num::Real, Src = UndefinedUnit, Dst = UndefinedUnit> {
 }
 
 impl<T: num::Real, Src, Dst> Transform<T, Src, Dst> {
+    #[must_use]
+    pub fn new(m11: T, m12: T, m21: T, m22: T, m31: T, m32: T) -> Self {
+        Self {
+            m11,
+            m12,
+            m21,
+            m22,
+            m31,
+            m32,
+            _src: PhantomData,
+            _dst: PhantomData,
+        }
+    }
+
     #[must_use]
     pub fn identity() -> Self {
         Self {
@@ -457,11 +1101,121 @@ impl<T: num::Real, Src, Dst> Transform<T, Src, Dst> {
 
     pub fn transform_point(&self, point: &Point<T, Src>) -> Point<T, Dst> {
         Point::new(
-            self.m11 * point.x + self.m12 * point.y + self.m31,
-            self.m21 * point.x + self.m22 * point.y + self.m32,
+            point.x * self.m11 + point.y * self.m21 + self.m31,
+            point.x * self.m12 + point.y * self.m22 + self.m32,
+        )
+    }
+
+    /// Transforms a vector, ignoring translation.
+    #[allow(clippy::needless_pass_by_value)]
+    pub fn transform_vector(&self, vector: Offset<T, Src>) -> Offset<T, Dst> {
+        Offset::new(
+            vector.x * self.m11 + vector.y * self.m21,
+            vector.x * self.m12 + vector.y * self.m22,
         )
     }
 
+    /// Transforms the corners of a rectangle and returns the smallest
+    /// axis-aligned rectangle that contains them.
+    pub fn transform_rect(&self, rect: &Rect<T, Src>) -> Rect<T, Dst> {
+        let corners = [
+            self.transform_point(&rect.p0),
+            self.transform_point(&Point::new(rect.p1.x, rect.p0.y)),
+            self.transform_point(&Point::new(rect.p0.x, rect.p1.y)),
+            self.transform_point(&rect.p1),
+        ];
+
+        let mut bounds = Rect::new(corners[0], corners[0]);
+        for corner in &corners[1..] {
+            bounds.p0 = Point::new(min(bounds.p0.x, corner.x), min(bounds.p0.y, corner.y));
+            bounds.p1 = Point::new(max(bounds.p1.x, corner.x), max(bounds.p1.y, corner.y));
+        }
+        bounds
+    }
+
+    #[must_use]
+    pub fn determinant(&self) -> T {
+        self.m11 * self.m22 - self.m12 * self.m21
+    }
+
+    /// The transform that maps points in `Dst` back to `Src`, or `None` if the
+    /// transform is not invertible (e.g. it scales an axis to zero).
+    #[must_use]
+    pub fn inverse(&self) -> Option<Transform<T, Dst, Src>> {
+        let det = self.determinant();
+
+        if det == T::zero() {
+            return None;
+        }
+
+        let inv_det = T::one() / det;
+        let m11 = self.m22 * inv_det;
+        let m12 = -self.m12 * inv_det;
+        let m21 = -self.m21 * inv_det;
+        let m22 = self.m11 * inv_det;
+
+        Some(Transform {
+            m11,
+            m12,
+            m21,
+            m22,
+            m31: -(self.m31 * m11 + self.m32 * m21),
+            m32: -(self.m31 * m12 + self.m32 * m22),
+            _src: PhantomData,
+            _dst: PhantomData,
+        })
+    }
+
+    /// Splits the transform into scale, skew, rotation and translation,
+    /// applied in that order.
+    ///
+    /// A reflection is represented as a negative vertical scale.
+    #[must_use]
+    pub fn decompose(&self) -> Decomposition<T, Dst> {
+        let scale_x = (self.m11 * self.m11 + self.m12 * self.m12).sqrt();
+
+        let rotation = if scale_x == T::zero() {
+            T::zero()
+        } else {
+            self.m12.atan2(self.m11)
+        };
+
+        let (s, c) = rotation.sin_cos();
+        let scale_y = self.m22 * c - self.m21 * s;
+
+        let skew = if scale_y == T::zero() {
+            T::zero()
+        } else {
+            (self.m21 * c + self.m22 * s) / scale_y
+        };
+
+        Decomposition {
+            translation: Offset::new(self.m31, self.m32),
+            rotation: Radians(rotation),
+            scale: Scale::new(scale_x, scale_y),
+            skew,
+        }
+    }
+
+    /// Rebuilds a transform from its decomposed parts. This is the inverse of
+    /// [`Transform::decompose`].
+    #[must_use]
+    pub fn recompose(parts: &Decomposition<T, Dst>) -> Self {
+        let (s, c) = parts.rotation.sin_cos();
+        let (sx, sy) = (parts.scale.x, parts.scale.y);
+
+        Self {
+            m11: sx * c,
+            m12: sx * s,
+            m21: sy * (parts.skew * c - s),
+            m22: sy * (parts.skew * s + c),
+            m31: parts.translation.x,
+            m32: parts.translation.y,
+            _src: PhantomData,
+            _dst: PhantomData,
+        }
+    }
+
     pub fn with_src<NewSrc>(&self) -> Transform<T, NewSrc, Dst> {
         Transform {
             m11: self.m11,
@@ -498,6 +1252,44 @@ impl<T: num::Real, Src, Dst> Transform<T, Src, Dst> {
     }
 }
 
+/// A transform split into components that can be interpolated independently.
+/// See [`Transform::decompose`].
+pub struct Decomposition<T: num::Real, Unit = UndefinedUnit> {
+    pub translation: Offset<T, Unit>,
+    pub rotation: Radians<T>,
+    pub scale: Scale<T, Unit>,
+    /// The horizontal shear factor, such that `x' = x + skew * y`.
+    pub skew: T,
+}
+
+impl<T: num::Real, Unit> Decomposition<T, Unit> {
+    /// Linearly interpolates between two decompositions. Rotations take the
+    /// shortest path.
+    #[must_use]
+    pub fn lerp(&self, other: &Self, t: T) -> Self {
+        let lerp = |a: T, b: T| a + (b - a) * t;
+
+        let pi = T::pi();
+        let two_pi = pi + pi;
+        let mut delta = other.rotation.0 - self.rotation.0;
+        if delta > pi {
+            delta = delta - two_pi;
+        } else if delta < -pi {
+            delta = delta + two_pi;
+        }
+
+        Self {
+            translation: self.translation.lerp(&other.translation, t),
+            rotation: Radians(self.rotation.0 + delta * t),
+            scale: Scale::new(
+                lerp(self.scale.x, other.scale.x),
+                lerp(self.scale.y, other.scale.y),
+            ),
+            skew: lerp(self.skew, other.skew),
+        }
+    }
+}
+
 impl<T: num::Real, Src, Dst> Clone for Transform<T, Src, Dst> {
     fn clone(&self) -> Self {
         Self {
@@ -530,7 +1322,7 @@ impl<T: num::Real, Src, Dst> std::fmt::Debug for Transform<T, Src, Dst> {
 }
 
 pub mod num {
-    use std::ops::{Add, Mul, Neg};
+    use std::ops::{Add, Div, Mul, Neg, Sub};
 
     pub trait Zero {
         fn zero() -> Self;
@@ -548,7 +1340,9 @@ pub mod num {
         + Default
         + Neg<Output = Self>
         + Add<Output = Self>
+        + Sub<Output = Self>
         + Mul<Output = Self>
+        + Div<Output = Self>
         + PartialOrd
         + std::fmt::Debug
         + std::fmt::Display
@@ -563,6 +1357,16 @@ pub mod num {
         #[must_use]
         fn sin_cos(self) -> (Self, Self);
 
+        /// The four-quadrant arctangent of `self` (y) and `x`, in radians.
+        #[must_use]
+        fn atan2(self, x: Self) -> Self;
+
+        #[must_use]
+        fn sqrt(self) -> Self;
+
+        #[must_use]
+        fn pi() -> Self;
+
         #[must_use]
         fn radians_to_degrees(self) -> Self;
 
@@ -607,6 +1411,18 @@ pub mod num {
             self.sin_cos()
         }
 
+        fn atan2(self, x: Self) -> Self {
+            self.atan2(x)
+        }
+
+        fn sqrt(self) -> Self {
+            self.sqrt()
+        }
+
+        fn pi() -> Self {
+            std::f32::consts::PI
+        }
+
         fn radians_to_degrees(self) -> Self {
             self * 180.0 / std::f32::consts::PI
         }
@@ -616,3 +1432,214 @@ pub mod num {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rect(x0: i32, y0: i32, x1: i32, y1: i32) -> Rect<i32> {
+        Rect::new(Point::new(x0, y0), Point::new(x1, y1))
+    }
+
+    #[test]
+    fn rect_contains() {
+        let r = rect(0, 0, 10, 10);
+        assert!(r.contains(&Point::new(0, 0)));
+        assert!(r.contains(&Point::new(9, 9)));
+        assert!(!r.contains(&Point::new(10, 5)));
+        assert!(!r.contains(&Point::new(5, -1)));
+    }
+
+    #[test]
+    fn rect_intersection() {
+        let a = rect(0, 0, 10, 10);
+        let b = rect(5, 5, 15, 15);
+        assert!(a.intersects(&b));
+        assert_eq!(a.intersection(&b), Some(rect(5, 5, 10, 10)));
+
+        // Touching edges do not intersect.
+        let c = rect(10, 0, 20, 10);
+        assert!(!a.intersects(&c));
+        assert_eq!(a.intersection(&c), None);
+    }
+
+    #[test]
+    fn rect_union() {
+        let a = rect(0, 0, 10, 10);
+        assert_eq!(a.union(&rect(5, -5, 20, 5)), rect(0, -5, 20, 10));
+        assert_eq!(a.union(&rect(50, 50, 50, 60)), a);
+        assert_eq!(rect(50, 50, 50, 60).union(&a), a);
+    }
+
+    fn assert_near(a: f32, b: f32) {
+        assert!((a - b).abs() < 1e-5, "{a} != {b}");
+    }
+
+    fn assert_transform_near(a: &Transform<f32>, b: &Transform<f32>) {
+        assert_near(a.m11, b.m11);
+        assert_near(a.m12, b.m12);
+        assert_near(a.m21, b.m21);
+        assert_near(a.m22, b.m22);
+        assert_near(a.m31, b.m31);
+        assert_near(a.m32, b.m32);
+    }
+
+    fn sample_transform() -> Transform<f32> {
+        Transform::scale(Scale::new(2.0, 3.0))
+            .then_rotate(Degrees(30.0))
+            .then_translate(Offset::new(5.0, -7.0))
+    }
+
+    #[test]
+    fn transform_composition_order() {
+        let a = Transform::<f32>::scale(Scale::new(2.0, 1.0));
+        let b = Transform::<f32>::rotate(Degrees(90.0));
+        let p = Point::new(1.0, 1.0);
+
+        let composed = a.then(&b).transform_point(&p);
+        let sequential = b.transform_point(&a.transform_point(&p));
+
+        assert_near(composed.x, sequential.x);
+        assert_near(composed.y, sequential.y);
+        // Rotating (2, 1) by 90 degrees with the Y-axis pointing down.
+        assert_near(composed.x, -1.0);
+        assert_near(composed.y, 2.0);
+    }
+
+    #[test]
+    fn transform_inverse() {
+        let t = sample_transform();
+        let inverse = t.inverse().unwrap();
+
+        let p = Point::new(3.0, -4.0);
+        let q = inverse.transform_point(&t.transform_point(&p));
+        assert_near(p.x, q.x);
+        assert_near(p.y, q.y);
+
+        assert_transform_near(&t.then(&inverse), &Transform::identity());
+        assert_near(t.determinant(), 6.0);
+
+        let singular = Transform::<f32>::scale(Scale::new(0.0, 1.0));
+        assert!(singular.inverse().is_none());
+    }
+
+    #[test]
+    fn transform_vector_and_rect() {
+        let t =
+            Transform::<f32>::scale(Scale::new(2.0, 2.0)).then_translate(Offset::new(10.0, 0.0));
+
+        let v = t.transform_vector(Offset::new(1.0, 1.0));
+        assert_eq!((v.x, v.y), (2.0, 2.0));
+
+        let rotated = Transform::<f32>::rotate(Degrees(45.0))
+            .transform_rect(&Rect::new(Point::new(-1.0, -1.0), Point::new(1.0, 1.0)));
+        let half_diagonal = std::f32::consts::SQRT_2;
+        assert_near(rotated.p0.x, -half_diagonal);
+        assert_near(rotated.p0.y, -half_diagonal);
+        assert_near(rotated.p1.x, half_diagonal);
+        assert_near(rotated.p1.y, half_diagonal);
+    }
+
+    #[test]
+    fn transform_decompose() {
+        let t = sample_transform();
+        let parts = t.decompose();
+
+        assert_near(parts.scale.x, 2.0);
+        assert_near(parts.scale.y, 3.0);
+        assert_near(parts.rotation.to_degrees().0, 30.0);
+        assert_near(parts.skew, 0.0);
+        assert_transform_near(&Transform::recompose(&parts), &t);
+
+        let skewed = Transform {
+            m21: 0.5,
+            ..sample_transform()
+        };
+        assert_transform_near(&Transform::recompose(&skewed.decompose()), &skewed);
+
+        let reflected = Transform::<f32>::scale(Scale::new(1.0, -1.0)).then_rotate(Degrees(10.0));
+        assert_transform_near(&Transform::recompose(&reflected.decompose()), &reflected);
+    }
+
+    #[test]
+    fn decomposition_lerp_takes_shortest_path() {
+        let a = Transform::<f32>::rotate(Degrees(170.0)).decompose();
+        let b = Transform::<f32>::rotate(Degrees(-170.0)).decompose();
+
+        let halfway = Transform::<f32>::recompose(&a.lerp(&b, 0.5));
+        assert_transform_near(&halfway, &Transform::rotate(Degrees(180.0)));
+    }
+
+    #[test]
+    fn point_offset_arithmetic() {
+        let p = Point::<i32>::new(1, 2);
+        let o = Offset::<i32>::new(3, -4);
+
+        assert_eq!(p + o, Point::new(4, -2));
+        assert_eq!(p - o, Point::new(-2, 6));
+        assert_eq!((p + o) - p, o);
+        assert_eq!(-o, Offset::new(-3, 4));
+        assert_eq!(o + o, o * 2);
+        assert_eq!(o - o, Offset::zero());
+        assert_eq!((o * 2) / 2, o);
+        assert_eq!(p * Scale::new(2, 3), Point::new(2, 6));
+
+        let mut q = p;
+        q += o;
+        q -= o;
+        assert_eq!(q, p);
+
+        let mut m = o;
+        m *= 3;
+        m /= 3;
+        assert_eq!(m, o);
+    }
+
+    #[test]
+    fn extent_scale_arithmetic() {
+        let e = Extent::<i32>::new(10, 20);
+
+        assert_eq!(e * Scale::new(2, 3), Extent::new(20, 60));
+        assert_eq!(e / Scale::new(2, 4), Extent::new(5, 5));
+        assert_eq!(e + e, e * 2);
+        assert_eq!(e - e, Extent::zero());
+        assert_eq!(
+            Scale::<i32>::new(2, 3) * Scale::new(4, 5),
+            Scale::new(8, 15)
+        );
+        assert_eq!(e.min(&Extent::new(15, 15)), Extent::new(10, 15));
+        assert_eq!(e.max(&Extent::new(15, 15)), Extent::new(15, 20));
+    }
+
+    #[test]
+    fn offset_vector_ops() {
+        let a = Offset::<f32>::new(3.0, 4.0);
+        let b = Offset::<f32>::new(-4.0, 3.0);
+
+        assert_near(a.length(), 5.0);
+        assert_near(a.dot(&b), 0.0);
+        assert_near(a.cross(&b), 25.0);
+        assert_eq!(a.lerp(&b, 0.5), Offset::new(-0.5, 3.5));
+        assert_eq!(a.min(&b), Offset::new(-4.0, 3.0));
+        assert_eq!(a.max(&b), Offset::new(3.0, 4.0));
+        assert_eq!(
+            Point::<f32>::zero().lerp(&Point::new(2.0, 4.0), 0.25),
+            Point::new(0.5, 1.0)
+        );
+    }
+
+    #[test]
+    fn rect_adjust() {
+        let r = rect(10, 10, 20, 30);
+        assert_eq!(r.normalize(), r);
+        assert_eq!(rect(20, 30, 10, 10).normalize(), r);
+        assert_eq!(r.center(), Point::new(15, 20));
+        assert_eq!(r.translate(Offset::new(-10, 5)), rect(0, 15, 10, 35));
+        assert_eq!(r.inflate(Insets::new(1, 2, 3, 4)), rect(9, 8, 23, 34));
+        assert_eq!(r.inflate(Insets::uniform(2)).deflate(Insets::uniform(2)), r);
+        assert_eq!(
+            Rect::from_origin_extent(Point::new(10, 10), Extent::new(10, 20)),
+            r
+        );
+    }
+}
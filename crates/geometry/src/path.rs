@@ -0,0 +1,565 @@
+use crate::{
+    Arc, CubicBezier, Offset, Point, QuadraticBezier, Radians, Rect, Transform, UndefinedUnit,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Verb {
+    MoveTo,
+    LineTo,
+    QuadTo,
+    CubicTo,
+    Close,
+}
+
+/// A vector path made of lines and Bézier curves, possibly with several
+/// disjoint subpaths.
+///
+/// Paths are created with a [`PathBuilder`]:
+///
+/// ```
+/// use geometry::{Path, Point};
+///
+/// let mut builder = Path::<()>::builder();
+/// builder
+///     .move_to(Point::new(0.0, 0.0))
+///     .line_to(Point::new(10.0, 0.0))
+///     .quad_to(Point::new(10.0, 10.0), Point::new(0.0, 10.0))
+///     .close();
+/// let path = builder.build();
+/// ```
+#[derive(Debug, PartialEq)]
+pub struct Path<Unit = UndefinedUnit> {
+    verbs: Vec<Verb>,
+    points: Vec<Point<f32, Unit>>,
+}
+
+impl<Unit> Clone for Path<Unit> {
+    fn clone(&self) -> Self {
+        Self {
+            verbs: self.verbs.clone(),
+            points: self.points.clone(),
+        }
+    }
+}
+
+impl<Unit> Default for Path<Unit> {
+    fn default() -> Self {
+        Self {
+            verbs: Vec::new(),
+            points: Vec::new(),
+        }
+    }
+}
+
+impl<Unit> Path<Unit> {
+    #[must_use]
+    pub fn builder() -> PathBuilder<Unit> {
+        PathBuilder::new()
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.verbs.is_empty()
+    }
+
+    pub fn iter(&self) -> Iter<'_, Unit> {
+        Iter {
+            path: self,
+            verb: 0,
+            point: 0,
+            first: Point::zero(),
+            current: Point::zero(),
+            in_subpath: false,
+        }
+    }
+
+    /// Applies a transform to every point of the path. Since Bézier curves
+    /// are preserved by affine transforms, this is exact.
+    #[must_use]
+    pub fn transform<Dst>(&self, transform: &Transform<f32, Unit, Dst>) -> Path<Dst> {
+        Path {
+            verbs: self.verbs.clone(),
+            points: self
+                .points
+                .iter()
+                .map(|point| transform.transform_point(point))
+                .collect(),
+        }
+    }
+
+    /// Approximates every subpath with a polyline, such that no part of the
+    /// path is farther than `tolerance` from it. Tolerances are raised to at
+    /// least a ten-thousandth of a unit, as with
+    /// [`CubicBezier::flatten_into`](crate::CubicBezier::flatten_into).
+    #[must_use]
+    pub fn flatten(&self, tolerance: f32) -> Vec<Polyline<Unit>> {
+        let mut polylines = Vec::new();
+        let mut points = Vec::new();
+
+        for event in self.iter() {
+            match event {
+                PathEvent::Begin { at } => points.push(at),
+                PathEvent::Line { to, .. } => points.push(to),
+                PathEvent::Quadratic(curve) => curve.flatten_into(tolerance, &mut points),
+                PathEvent::Cubic(curve) => curve.flatten_into(tolerance, &mut points),
+                PathEvent::End { close, .. } => polylines.push(Polyline {
+                    points: std::mem::take(&mut points),
+                    closed: close,
+                }),
+            }
+        }
+
+        polylines
+    }
+
+    /// The tightest axis-aligned rectangle that contains the path, or `None`
+    /// if the path is empty.
+    #[must_use]
+    pub fn bounds(&self) -> Option<Rect<f32, Unit>> {
+        let mut bounds: Option<Rect<f32, Unit>> = None;
+        let mut include = |rect: Rect<f32, Unit>| {
+            bounds = Some(match bounds {
+                Some(bounds) => Rect::new(bounds.p0.min(&rect.p0), bounds.p1.max(&rect.p1)),
+                None => rect,
+            });
+        };
+
+        for event in self.iter() {
+            match event {
+                PathEvent::Begin { at } => include(Rect::new(at, at)),
+                PathEvent::Line { to, .. } => include(Rect::new(to, to)),
+                PathEvent::Quadratic(curve) => include(curve.bounds()),
+                PathEvent::Cubic(curve) => include(curve.bounds()),
+                PathEvent::End { .. } => {}
+            }
+        }
+
+        bounds
+    }
+
+    /// The length of the path, including the closing segments of closed
+    /// subpaths. Curves are measured by flattening them with `tolerance`.
+    #[must_use]
+    pub fn length(&self, tolerance: f32) -> f32 {
+        self.flatten(tolerance).iter().map(Polyline::length).sum()
+    }
+
+    /// The point `distance` along the path, measured as in [`Path::length`].
+    /// Returns `None` if `distance` is negative or longer than the path.
+    #[must_use]
+    pub fn point_at_distance(&self, distance: f32, tolerance: f32) -> Option<Point<f32, Unit>> {
+        if distance < 0.0 {
+            return None;
+        }
+
+        let mut remaining = distance;
+        for polyline in self.flatten(tolerance) {
+            for (from, to) in polyline.segments() {
+                let length = (to - from).length();
+                if remaining <= length {
+                    let t = if length > 0.0 {
+                        remaining / length
+                    } else {
+                        0.0
+                    };
+                    return Some(from.lerp(&to, t));
+                }
+                remaining -= length;
+            }
+        }
+
+        None
+    }
+}
+
+impl<'a, Unit> IntoIterator for &'a Path<Unit> {
+    type Item = PathEvent<Unit>;
+    type IntoIter = Iter<'a, Unit>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// A segment of a path, or the start or end of a subpath.
+///
+/// Every subpath begins with `Begin` and ends with `End`, with its segments in
+/// between.
+#[derive(Debug, PartialEq)]
+pub enum PathEvent<Unit = UndefinedUnit> {
+    Begin {
+        at: Point<f32, Unit>,
+    },
+    Line {
+        from: Point<f32, Unit>,
+        to: Point<f32, Unit>,
+    },
+    Quadratic(QuadraticBezier<Unit>),
+    Cubic(CubicBezier<Unit>),
+    End {
+        last: Point<f32, Unit>,
+        first: Point<f32, Unit>,
+        /// Whether the subpath is closed by a line from `last` to `first`.
+        close: bool,
+    },
+}
+
+pub struct Iter<'a, Unit> {
+    path: &'a Path<Unit>,
+    verb: usize,
+    point: usize,
+    first: Point<f32, Unit>,
+    current: Point<f32, Unit>,
+    in_subpath: bool,
+}
+
+impl<'a, Unit> Iter<'a, Unit> {
+    fn next_point(&mut self) -> Point<f32, Unit> {
+        let point = self.path.points[self.point];
+        self.point += 1;
+        point
+    }
+
+    fn end(&mut self, close: bool) -> PathEvent<Unit> {
+        self.in_subpath = false;
+        let last = self.current;
+        self.current = self.first;
+        PathEvent::End {
+            last,
+            first: self.first,
+            close,
+        }
+    }
+}
+
+impl<'a, Unit> Iterator for Iter<'a, Unit> {
+    type Item = PathEvent<Unit>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let Some(verb) = self.path.verbs.get(self.verb) else {
+            return self.in_subpath.then(|| self.end(false));
+        };
+
+        // A new subpath implicitly ends the current one.
+        if *verb == Verb::MoveTo && self.in_subpath {
+            return Some(self.end(false));
+        }
+
+        self.verb += 1;
+        let from = self.current;
+
+        let event = match verb {
+            Verb::MoveTo => {
+                self.in_subpath = true;
+                self.first = self.next_point();
+                self.current = self.first;
+                PathEvent::Begin { at: self.first }
+            }
+            Verb::LineTo => {
+                self.current = self.next_point();
+                PathEvent::Line {
+                    from,
+                    to: self.current,
+                }
+            }
+            Verb::QuadTo => {
+                let ctrl = self.next_point();
+                self.current = self.next_point();
+                PathEvent::Quadratic(QuadraticBezier::new(from, ctrl, self.current))
+            }
+            Verb::CubicTo => {
+                let ctrl1 = self.next_point();
+                let ctrl2 = self.next_point();
+                self.current = self.next_point();
+                PathEvent::Cubic(CubicBezier::new(from, ctrl1, ctrl2, self.current))
+            }
+            Verb::Close => self.end(true),
+        };
+
+        Some(event)
+    }
+}
+
+/// Builds a [`Path`] one segment at a time.
+///
+/// Drawing a segment without first calling `move_to` starts a new subpath at
+/// the current position, which is the origin for a new builder and the start
+/// of the previous subpath after `close`.
+pub struct PathBuilder<Unit = UndefinedUnit> {
+    path: Path<Unit>,
+    first: Point<f32, Unit>,
+    current: Point<f32, Unit>,
+    in_subpath: bool,
+}
+
+impl<Unit> Default for PathBuilder<Unit> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<Unit> PathBuilder<Unit> {
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            path: Path::default(),
+            first: Point::zero(),
+            current: Point::zero(),
+            in_subpath: false,
+        }
+    }
+
+    /// The end point of the last segment, or where the next segment will
+    /// start.
+    #[must_use]
+    pub fn current_position(&self) -> Point<f32, Unit> {
+        self.current
+    }
+
+    /// Starts a new subpath at `to`.
+    pub fn move_to(&mut self, to: Point<f32, Unit>) -> &mut Self {
+        self.path.verbs.push(Verb::MoveTo);
+        self.path.points.push(to);
+        self.first = to;
+        self.current = to;
+        self.in_subpath = true;
+        self
+    }
+
+    pub fn line_to(&mut self, to: Point<f32, Unit>) -> &mut Self {
+        self.begin_if_needed();
+        self.path.verbs.push(Verb::LineTo);
+        self.path.points.push(to);
+        self.current = to;
+        self
+    }
+
+    pub fn quad_to(&mut self, ctrl: Point<f32, Unit>, to: Point<f32, Unit>) -> &mut Self {
+        self.begin_if_needed();
+        self.path.verbs.push(Verb::QuadTo);
+        self.path.points.extend_from_slice(&[ctrl, to]);
+        self.current = to;
+        self
+    }
+
+    pub fn cubic_to(
+        &mut self,
+        ctrl1: Point<f32, Unit>,
+        ctrl2: Point<f32, Unit>,
+        to: Point<f32, Unit>,
+    ) -> &mut Self {
+        self.begin_if_needed();
+        self.path.verbs.push(Verb::CubicTo);
+        self.path.points.extend_from_slice(&[ctrl1, ctrl2, to]);
+        self.current = to;
+        self
+    }
+
+    /// Draws an elliptical arc to `to`, with the same parameters as SVG's `A`
+    /// command. The arc is stored as a sequence of cubic Bézier curves.
+    pub fn arc_to(
+        &mut self,
+        radii: Offset<f32, Unit>,
+        x_rotation: Radians<f32>,
+        large_arc: bool,
+        sweep: bool,
+        to: Point<f32, Unit>,
+    ) -> &mut Self {
+        let from = self.current;
+        match Arc::from_svg(from, to, radii, x_rotation, large_arc, sweep) {
+            Some(arc) => arc.for_each_cubic(|curve| {
+                self.cubic_to(curve.ctrl1, curve.ctrl2, curve.to);
+            }),
+            None if from.x != to.x || from.y != to.y => {
+                self.line_to(to);
+            }
+            None => {}
+        }
+        self
+    }
+
+    /// Closes the current subpath with a line back to its start.
+    pub fn close(&mut self) -> &mut Self {
+        if self.in_subpath {
+            self.path.verbs.push(Verb::Close);
+            self.current = self.first;
+            self.in_subpath = false;
+        }
+        self
+    }
+
+    #[must_use]
+    pub fn build(self) -> Path<Unit> {
+        self.path
+    }
+
+    fn begin_if_needed(&mut self) {
+        if !self.in_subpath {
+            self.move_to(self.current);
+        }
+    }
+}
+
+/// A sequence of connected line segments, produced by flattening a subpath.
+#[derive(Debug, PartialEq)]
+pub struct Polyline<Unit = UndefinedUnit> {
+    pub points: Vec<Point<f32, Unit>>,
+    /// Whether the last point connects back to the first.
+    pub closed: bool,
+}
+
+impl<Unit> Clone for Polyline<Unit> {
+    fn clone(&self) -> Self {
+        Self {
+            points: self.points.clone(),
+            closed: self.closed,
+        }
+    }
+}
+
+impl<Unit> Polyline<Unit> {
+    /// The line segments of the polyline, including the closing segment if the
+    /// polyline is closed.
+    pub fn segments(&self) -> impl Iterator<Item = (Point<f32, Unit>, Point<f32, Unit>)> + '_ {
+        let closing = match (self.closed, self.points.first(), self.points.last()) {
+            (true, Some(first), Some(last)) if self.points.len() > 1 => Some((*last, *first)),
+            _ => None,
+        };
+
+        self.points
+            .windows(2)
+            .map(|pair| (pair[0], pair[1]))
+            .chain(closing)
+    }
+
+    #[must_use]
+    pub fn length(&self) -> f32 {
+        self.segments().map(|(from, to)| (to - from).length()).sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    type P = Point<f32>;
+
+    fn square() -> Path {
+        let mut builder = Path::builder();
+        builder
+            .move_to(P::new(0.0, 0.0))
+            .line_to(P::new(10.0, 0.0))
+            .line_to(P::new(10.0, 10.0))
+            .line_to(P::new(0.0, 10.0))
+            .close();
+        builder.build()
+    }
+
+    #[test]
+    fn events() {
+        let mut builder = Path::builder();
+        builder
+            .move_to(P::new(1.0, 1.0))
+            .line_to(P::new(2.0, 1.0))
+            .move_to(P::new(5.0, 5.0))
+            .quad_to(P::new(6.0, 5.0), P::new(6.0, 6.0))
+            .close()
+            .line_to(P::new(7.0, 7.0));
+        let path = builder.build();
+
+        let events: Vec<_> = path.iter().collect();
+        assert_eq!(
+            events,
+            [
+                PathEvent::Begin {
+                    at: P::new(1.0, 1.0)
+                },
+                PathEvent::Line {
+                    from: P::new(1.0, 1.0),
+                    to: P::new(2.0, 1.0)
+                },
+                PathEvent::End {
+                    last: P::new(2.0, 1.0),
+                    first: P::new(1.0, 1.0),
+                    close: false
+                },
+                PathEvent::Begin {
+                    at: P::new(5.0, 5.0)
+                },
+                PathEvent::Quadratic(QuadraticBezier::new(
+                    P::new(5.0, 5.0),
+                    P::new(6.0, 5.0),
+                    P::new(6.0, 6.0)
+                )),
+                PathEvent::End {
+                    last: P::new(6.0, 6.0),
+                    first: P::new(5.0, 5.0),
+                    close: true
+                },
+                // Drawing after `close` starts a new subpath where the closed
+                // one began.
+                PathEvent::Begin {
+                    at: P::new(5.0, 5.0)
+                },
+                PathEvent::Line {
+                    from: P::new(5.0, 5.0),
+                    to: P::new(7.0, 7.0)
+                },
+                PathEvent::End {
+                    last: P::new(7.0, 7.0),
+                    first: P::new(5.0, 5.0),
+                    close: false
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn measure_square() {
+        let path = square();
+
+        assert_eq!(path.length(0.1), 40.0);
+        assert_eq!(
+            path.bounds(),
+            Some(Rect::new(P::new(0.0, 0.0), P::new(10.0, 10.0)))
+        );
+        assert_eq!(path.point_at_distance(15.0, 0.1), Some(P::new(10.0, 5.0)));
+        assert_eq!(path.point_at_distance(35.0, 0.1), Some(P::new(0.0, 5.0)));
+        assert_eq!(path.point_at_distance(41.0, 0.1), None);
+    }
+
+    #[test]
+    fn arc_to() {
+        // A half circle of radius 5 from (0, 0) to (10, 0). A zero sweep flag
+        // goes counter-clockwise on screen, through (5, 5).
+        let mut builder = Path::builder();
+        builder.move_to(P::new(0.0, 0.0)).arc_to(
+            Offset::new(5.0, 5.0),
+            Radians(0.0),
+            false,
+            false,
+            P::new(10.0, 0.0),
+        );
+        let path = builder.build();
+
+        let bounds = path.bounds().unwrap();
+        assert!(bounds.p0.y.abs() < 1e-3, "{bounds:?}");
+        assert!((bounds.p1.y - 5.0).abs() < 1e-3, "{bounds:?}");
+        assert!((path.length(0.001) - 5.0 * std::f32::consts::PI).abs() < 1e-2);
+        // A zero tolerance is raised to the minimum rather than never met.
+        assert!((path.length(0.0) - 5.0 * std::f32::consts::PI).abs() < 1e-2);
+        assert!(
+            (path
+                .point_at_distance(2.5 * std::f32::consts::PI, 0.001)
+                .unwrap()
+                - P::new(5.0, 5.0))
+            .length()
+                < 1e-2
+        );
+
+        // Zero radii produce a straight line.
+        let mut builder = Path::builder();
+        builder.arc_to(Offset::zero(), Radians(0.0), false, false, P::new(3.0, 4.0));
+        assert_eq!(builder.build().length(0.1), 5.0);
+    }
+}
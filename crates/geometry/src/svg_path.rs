@@ -0,0 +1,379 @@
+use crate::{Offset, Path, PathBuilder, Point, Radians};
+
+/// An error encountered while parsing SVG path data. Positions are byte
+/// offsets into the path data.
+#[derive(Debug, PartialEq, Eq)]
+pub enum SvgPathError {
+    /// The path data does not start with a `M` or `m` command.
+    MissingMoveTo,
+    /// A character that is not a command, number or separator.
+    UnexpectedCharacter { position: usize },
+    /// A command is missing one of its numbers.
+    ExpectedNumber { position: usize },
+    /// An arc is missing its `large-arc` or `sweep` flag, which must be `0` or
+    /// `1`.
+    ExpectedFlag { position: usize },
+}
+
+impl<Unit> Path<Unit> {
+    /// Parses the contents of an SVG `d` attribute, as described by the SVG
+    /// 1.1 path data grammar.
+    ///
+    /// ```
+    /// use geometry::Path;
+    ///
+    /// let path = Path::<()>::from_svg("M10 10 h 20 v 20 h -20 Z").unwrap();
+    /// assert_eq!(path.length(0.1), 80.0);
+    /// ```
+    pub fn from_svg(data: &str) -> Result<Self, SvgPathError> {
+        let mut parser = Parser {
+            data: data.as_bytes(),
+            position: 0,
+        };
+        let mut builder = PathBuilder::new();
+
+        // The control point of the previous curve, if it can be reflected by
+        // a smooth curve command.
+        let mut last_cubic_ctrl = None;
+        let mut last_quad_ctrl = None;
+        let mut command = None;
+        let mut started = false;
+
+        loop {
+            parser.skip_separators();
+            let Some(next) = parser.peek() else {
+                break;
+            };
+
+            if next.is_ascii_alphabetic() {
+                parser.position += 1;
+                command = Some(next);
+            } else if !is_number_start(next) {
+                return Err(SvgPathError::UnexpectedCharacter {
+                    position: parser.position,
+                });
+            }
+
+            // Numbers repeat the previous command.
+            let Some(cmd) = command else {
+                return Err(if started {
+                    SvgPathError::UnexpectedCharacter {
+                        position: parser.position,
+                    }
+                } else {
+                    SvgPathError::MissingMoveTo
+                });
+            };
+
+            if !started && !matches!(cmd, b'M' | b'm') {
+                return Err(SvgPathError::MissingMoveTo);
+            }
+            started = true;
+
+            let current = builder.current_position();
+            let relative = cmd.is_ascii_lowercase();
+            let origin = if relative {
+                Offset::from(current)
+            } else {
+                Offset::zero()
+            };
+
+            let mut cubic_ctrl = None;
+            let mut quad_ctrl = None;
+
+            match cmd.to_ascii_uppercase() {
+                b'M' => {
+                    builder.move_to(parser.point()? + origin);
+                    // Further coordinate pairs are implicit line-tos.
+                    command = Some(if relative { b'l' } else { b'L' });
+                }
+                b'L' => {
+                    builder.line_to(parser.point()? + origin);
+                }
+                b'H' => {
+                    let x = parser.number()? + origin.x;
+                    builder.line_to(Point::new(x, current.y));
+                }
+                b'V' => {
+                    let y = parser.number()? + origin.y;
+                    builder.line_to(Point::new(current.x, y));
+                }
+                b'C' => {
+                    let ctrl1 = parser.point()? + origin;
+                    let ctrl2 = parser.point()? + origin;
+                    let to = parser.point()? + origin;
+                    builder.cubic_to(ctrl1, ctrl2, to);
+                    cubic_ctrl = Some(ctrl2);
+                }
+                b'S' => {
+                    let ctrl1 = reflect(last_cubic_ctrl, current);
+                    let ctrl2 = parser.point()? + origin;
+                    let to = parser.point()? + origin;
+                    builder.cubic_to(ctrl1, ctrl2, to);
+                    cubic_ctrl = Some(ctrl2);
+                }
+                b'Q' => {
+                    let ctrl = parser.point()? + origin;
+                    let to = parser.point()? + origin;
+                    builder.quad_to(ctrl, to);
+                    quad_ctrl = Some(ctrl);
+                }
+                b'T' => {
+                    let ctrl = reflect(last_quad_ctrl, current);
+                    let to = parser.point()? + origin;
+                    builder.quad_to(ctrl, to);
+                    quad_ctrl = Some(ctrl);
+                }
+                b'A' => {
+                    let rx = parser.number()?;
+                    let ry = parser.number()?;
+                    let x_rotation = parser.number()?.to_radians();
+                    let large_arc = parser.flag()?;
+                    let sweep = parser.flag()?;
+                    let to = parser.point()? + origin;
+                    builder.arc_to(
+                        Offset::new(rx, ry),
+                        Radians(x_rotation),
+                        large_arc,
+                        sweep,
+                        to,
+                    );
+                }
+                b'Z' => {
+                    builder.close();
+                    // Closing takes no arguments, so it cannot repeat.
+                    command = None;
+                }
+                _ => {
+                    return Err(SvgPathError::UnexpectedCharacter {
+                        position: parser.position - 1,
+                    })
+                }
+            }
+
+            last_cubic_ctrl = cubic_ctrl;
+            last_quad_ctrl = quad_ctrl;
+        }
+
+        Ok(builder.build())
+    }
+}
+
+/// The first control point of a smooth curve: the reflection of the previous
+/// curve's last control point, or the current point if there is none.
+fn reflect<Unit>(ctrl: Option<Point<f32, Unit>>, current: Point<f32, Unit>) -> Point<f32, Unit> {
+    match ctrl {
+        Some(ctrl) => current + (current - ctrl),
+        None => current,
+    }
+}
+
+fn is_number_start(c: u8) -> bool {
+    c.is_ascii_digit() || matches!(c, b'+' | b'-' | b'.')
+}
+
+struct Parser<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&self) -> Option<u8> {
+        self.data.get(self.position).copied()
+    }
+
+    fn skip_separators(&mut self) {
+        while let Some(b' ' | b'\t' | b'\n' | b'\r' | b'\x0C' | b',') = self.peek() {
+            self.position += 1;
+        }
+    }
+
+    fn skip_digits(&mut self) -> usize {
+        let start = self.position;
+        while self.peek().is_some_and(|c| c.is_ascii_digit()) {
+            self.position += 1;
+        }
+        self.position - start
+    }
+
+    fn number(&mut self) -> Result<f32, SvgPathError> {
+        self.skip_separators();
+        let start = self.position;
+
+        if let Some(b'+' | b'-') = self.peek() {
+            self.position += 1;
+        }
+
+        let mut num_digits = self.skip_digits();
+        if self.peek() == Some(b'.') {
+            self.position += 1;
+            num_digits += self.skip_digits();
+        }
+
+        if num_digits == 0 {
+            self.position = start;
+            return Err(SvgPathError::ExpectedNumber { position: start });
+        }
+
+        // Only consume the exponent if it is well-formed, so that `1e` is
+        // left for the next command to report.
+        if let Some(b'e' | b'E') = self.peek() {
+            let mantissa_end = self.position;
+            self.position += 1;
+            if let Some(b'+' | b'-') = self.peek() {
+                self.position += 1;
+            }
+            if self.skip_digits() == 0 {
+                self.position = mantissa_end;
+            }
+        }
+
+        // The grammar above only accepts ASCII, and Rust's float syntax is a
+        // superset of SVG's.
+        std::str::from_utf8(&self.data[start..self.position])
+            .ok()
+            .and_then(|number| number.parse().ok())
+            .ok_or(SvgPathError::ExpectedNumber { position: start })
+    }
+
+    fn point<Unit>(&mut self) -> Result<Point<f32, Unit>, SvgPathError> {
+        Ok(Point::new(self.number()?, self.number()?))
+    }
+
+    /// Flags are a single digit and need not be followed by a separator.
+    fn flag(&mut self) -> Result<bool, SvgPathError> {
+        self.skip_separators();
+        let flag = match self.peek() {
+            Some(b'0') => false,
+            Some(b'1') => true,
+            _ => {
+                return Err(SvgPathError::ExpectedFlag {
+                    position: self.position,
+                })
+            }
+        };
+        self.position += 1;
+        Ok(flag)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{CubicBezier, PathEvent, QuadraticBezier};
+
+    type P = Point<f32>;
+
+    fn events(data: &str) -> Vec<PathEvent> {
+        Path::from_svg(data).unwrap().iter().collect()
+    }
+
+    #[test]
+    fn relative_commands() {
+        assert_eq!(
+            events("m1 1 2 0 v2 h-2z l1-1"),
+            events("M1 1 L3 1 V3 H1 Z L2 0"),
+        );
+    }
+
+    #[test]
+    fn compact_numbers() {
+        // Signs and a second decimal point both start a new number.
+        assert_eq!(
+            events("M.5.5-1-1e1L1.5e+1,2E-1"),
+            events("M 0.5 0.5 L -1 -10 L 15 0.2"),
+        );
+    }
+
+    #[test]
+    fn smooth_curves() {
+        let path = events("M0 0 C0 1 1 1 1 0 S2 -1 2 0 Q3 1 4 0 T6 0 t2 0");
+        assert_eq!(
+            path[1..6],
+            [
+                PathEvent::Cubic(CubicBezier::new(
+                    P::new(0.0, 0.0),
+                    P::new(0.0, 1.0),
+                    P::new(1.0, 1.0),
+                    P::new(1.0, 0.0)
+                )),
+                PathEvent::Cubic(CubicBezier::new(
+                    P::new(1.0, 0.0),
+                    P::new(1.0, -1.0),
+                    P::new(2.0, -1.0),
+                    P::new(2.0, 0.0)
+                )),
+                PathEvent::Quadratic(QuadraticBezier::new(
+                    P::new(2.0, 0.0),
+                    P::new(3.0, 1.0),
+                    P::new(4.0, 0.0)
+                )),
+                PathEvent::Quadratic(QuadraticBezier::new(
+                    P::new(4.0, 0.0),
+                    P::new(5.0, -1.0),
+                    P::new(6.0, 0.0)
+                )),
+                PathEvent::Quadratic(QuadraticBezier::new(
+                    P::new(6.0, 0.0),
+                    P::new(7.0, 1.0),
+                    P::new(8.0, 0.0)
+                )),
+            ]
+        );
+
+        // Without a previous curve, the control point is the current point.
+        assert_eq!(
+            events("M0 0 L1 0 S2 1 3 0")[2],
+            PathEvent::Cubic(CubicBezier::new(
+                P::new(1.0, 0.0),
+                P::new(1.0, 0.0),
+                P::new(2.0, 1.0),
+                P::new(3.0, 0.0)
+            ))
+        );
+    }
+
+    #[test]
+    fn arc_flags() {
+        // Flags may be written without separators.
+        assert_eq!(
+            events("M0 0 a5 5 0 1010 0"),
+            events("M0 0 A 5 5 0 1 0 10 0"),
+        );
+
+        let bounds = Path::<()>::from_svg("M0 0 a5 5 0 1010 0")
+            .unwrap()
+            .bounds()
+            .unwrap();
+        assert!((bounds.p1.y - 5.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(
+            Path::<()>::from_svg("L1 1"),
+            Err(SvgPathError::MissingMoveTo)
+        );
+        assert_eq!(
+            Path::<()>::from_svg("1 1"),
+            Err(SvgPathError::MissingMoveTo)
+        );
+        assert_eq!(
+            Path::<()>::from_svg("M1 1 L2"),
+            Err(SvgPathError::ExpectedNumber { position: 7 })
+        );
+        assert_eq!(
+            Path::<()>::from_svg("M1 1 A1 1 0 2 0 3 3"),
+            Err(SvgPathError::ExpectedFlag { position: 12 })
+        );
+        assert_eq!(
+            Path::<()>::from_svg("M1 1 X"),
+            Err(SvgPathError::UnexpectedCharacter { position: 5 })
+        );
+        assert_eq!(
+            Path::<()>::from_svg("M1 1 Z 2"),
+            Err(SvgPathError::UnexpectedCharacter { position: 7 })
+        );
+        assert!(Path::<()>::from_svg("").unwrap().is_empty());
+    }
+}
@@ -172,7 +172,9 @@ impl WindowHandler for AppWindow {
             &[0, 1, 2],
         );
 
-        self.graphics.draw(image.image(), &render_graph);
+        self.graphics
+            .draw(image.image(), &render_graph)
+            .expect("failed to draw render graph");
 
         image.present();
     }
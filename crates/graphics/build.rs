@@ -1,7 +1,10 @@
+#[cfg(windows)]
 use std::path::PathBuf;
 
+#[cfg(windows)]
 use windows::{s, w};
 
+#[cfg(windows)]
 #[derive(Clone, Copy)]
 enum ShaderKind {
     Vertex,
@@ -11,9 +14,13 @@ enum ShaderKind {
 fn main() {
     println!("cargo:rerun-if-changed=shaders");
 
+    // Shaders are only needed by the DX12 backend. Other platforms use the
+    // software rasterizer, which has no shaders to compile.
+    #[cfg(windows)]
     compile_shaders();
 }
 
+#[cfg(windows)]
 fn compile_shaders() {
     compile(
         w!("shaders/polygon.hlsl"),
@@ -27,8 +34,39 @@ fn compile_shaders() {
         s!("pixel_main"),
         "polygon_ps.cso",
     );
+    compile(
+        w!("shaders/composite.hlsl"),
+        ShaderKind::Vertex,
+        s!("vertex_main"),
+        "composite_vs.cso",
+    );
+    compile(
+        w!("shaders/composite.hlsl"),
+        ShaderKind::Pixel,
+        s!("pixel_main"),
+        "composite_ps.cso",
+    );
+    compile(
+        w!("shaders/image.hlsl"),
+        ShaderKind::Vertex,
+        s!("vertex_main"),
+        "image_vs.cso",
+    );
+    compile(
+        w!("shaders/image.hlsl"),
+        ShaderKind::Pixel,
+        s!("pixel_nearest"),
+        "image_nearest_ps.cso",
+    );
+    compile(
+        w!("shaders/image.hlsl"),
+        ShaderKind::Pixel,
+        s!("pixel_linear"),
+        "image_linear_ps.cso",
+    );
 }
 
+#[cfg(windows)]
 fn compile(
     path: windows::core::PCWSTR,
     kind: ShaderKind,
@@ -0,0 +1,36 @@
+#define RS "RootConstants(num32BitConstants = 1, b0), \
+            DescriptorTable(SRV(t0), visibility = SHADER_VISIBILITY_PIXEL)"
+
+struct CompositeConstants
+{
+    float opacity;
+};
+
+// Constants set by the root signature
+ConstantBuffer<CompositeConstants> composite_constants : register(b0);
+
+// The layer being composited, premultiplied by alpha. It is the same size as
+// the render target, so it is read without filtering.
+Texture2D<float4> layer : register(t0);
+
+struct VsOutput
+{
+    float4 position : SV_POSITION;
+};
+
+// Draws a single triangle that covers the whole render target, clockwise on
+// screen so that it is not culled.
+[RootSignature(RS)]
+VsOutput vertex_main(uint id : SV_VertexID)
+{
+    float2 uv = float2((id << 1) & 2, id & 2);
+
+    VsOutput output;
+    output.position = float4(uv * float2(2.0f, -2.0f) + float2(-1.0f, 1.0f), 0.0f, 1.0f);
+    return output;
+}
+
+float4 pixel_main(VsOutput input) : SV_TARGET
+{
+    return layer.Load(int3(input.position.xy, 0)) * composite_constants.opacity;
+}
@@ -0,0 +1,82 @@
+#define RS "RootFlags(ALLOW_INPUT_ASSEMBLER_INPUT_LAYOUT), \
+            RootConstants(num32BitConstants = 10, b0), \
+            DescriptorTable(SRV(t0), visibility = SHADER_VISIBILITY_PIXEL), \
+            StaticSampler(s0, \
+                          filter = FILTER_MIN_MAG_MIP_POINT, \
+                          addressU = TEXTURE_ADDRESS_CLAMP, \
+                          addressV = TEXTURE_ADDRESS_CLAMP, \
+                          visibility = SHADER_VISIBILITY_PIXEL), \
+            StaticSampler(s1, \
+                          filter = FILTER_MIN_MAG_MIP_LINEAR, \
+                          addressU = TEXTURE_ADDRESS_CLAMP, \
+                          addressV = TEXTURE_ADDRESS_CLAMP, \
+                          visibility = SHADER_VISIBILITY_PIXEL)"
+
+struct DrawConstants
+{
+    uint screen_width;
+    uint screen_height;
+    // The object to screen transform, one row of the 2x3 matrix at a time.
+    float2 transform_x;
+    float2 transform_y;
+    float2 transform_offset;
+    // Multiplies texture coordinates, for vertices with coordinates in texels.
+    float2 uv_scale;
+};
+
+// Constants set by the root signature
+ConstantBuffer<DrawConstants> draw_constants : register(b0);
+
+// The image being drawn, premultiplied by alpha.
+Texture2D<float4> image : register(t0);
+SamplerState point_sampler : register(s0);
+SamplerState linear_sampler : register(s1);
+
+struct VsInput
+{
+    float2 position : POSITION;
+    float2 uv : TEXCOORD;
+    float4 color : COLOR;
+};
+
+struct VsOutput
+{
+    float4 position : SV_POSITION;
+    float2 uv : TEXCOORD;
+    float4 color : COLOR;
+};
+
+[RootSignature(RS)]
+VsOutput vertex_main(VsInput input)
+{
+    VsOutput output;
+
+    float2 position = input.position.x * draw_constants.transform_x
+                    + input.position.y * draw_constants.transform_y
+                    + draw_constants.transform_offset;
+
+    output.position = float4((position.x / draw_constants.screen_width) * 2.0f - 1.0f,
+                             ((draw_constants.screen_height - position.y) / draw_constants.screen_height) * 2.0f - 1.0f,
+                             0.0f, 1.0f);
+    output.uv = input.uv * draw_constants.uv_scale;
+    output.color = input.color;
+
+    return output;
+}
+
+// The vertex color tints the image. Both are premultiplied before they are
+// multiplied together.
+float4 tint(float4 texel, float4 color)
+{
+    return texel * float4(color.rgb * color.a, color.a);
+}
+
+float4 pixel_nearest(VsOutput input) : SV_TARGET
+{
+    return tint(image.Sample(point_sampler, input.uv), input.color);
+}
+
+float4 pixel_linear(VsOutput input) : SV_TARGET
+{
+    return tint(image.Sample(linear_sampler, input.uv), input.color);
+}
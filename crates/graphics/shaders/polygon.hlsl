@@ -1,10 +1,14 @@
 #define RS "RootFlags(ALLOW_INPUT_ASSEMBLER_INPUT_LAYOUT), \
-                       RootConstants(num32BitConstants = 2, b0)"
+                       RootConstants(num32BitConstants = 8, b0)"
 
 struct DrawConstants
 {
     uint screen_width;
     uint screen_height;
+    // The object to screen transform, one row of the 2x3 matrix at a time.
+    float2 transform_x;
+    float2 transform_y;
+    float2 transform_offset;
 };
 
 // Constants set by the root signature
@@ -27,8 +31,12 @@ VsOutput vertex_main(VsInput input)
 {
     VsOutput output;
 
-    output.position = float4((input.position.x / draw_constants.screen_width) * 2.0f - 1.0f,
-                             ((draw_constants.screen_height - input.position.y) / draw_constants.screen_height) * 2.0f - 1.0f,
+    float2 position = input.position.x * draw_constants.transform_x
+                    + input.position.y * draw_constants.transform_y
+                    + draw_constants.transform_offset;
+
+    output.position = float4((position.x / draw_constants.screen_width) * 2.0f - 1.0f,
+                             ((draw_constants.screen_height - position.y) / draw_constants.screen_height) * 2.0f - 1.0f,
                              0.0f, 1.0f);
     output.color = input.color;
 
@@ -37,5 +45,6 @@ VsOutput vertex_main(VsInput input)
 
 float4 pixel_main(VsOutput input) : SV_TARGET
 {
-    return input.color;
+    // Render targets store premultiplied colors.
+    return float4(input.color.rgb * input.color.a, input.color.a);
 }
@@ -0,0 +1,836 @@
+use geometry::{Extent, ScreenSpace};
+
+use crate::{Color, ImageFormat};
+
+/// The file formats that [`Bitmap`] can decode.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BitmapFormat {
+    Png,
+    Jpeg,
+    Bmp,
+    Qoi,
+}
+
+/// How the color channels of an image were encoded, as described by the
+/// image's metadata. Images without color space information are assumed to
+/// be sRGB.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ColorSpace {
+    Srgb,
+    /// Channels are proportional to light intensity.
+    Linear,
+    /// Channels were encoded with a power law, `encoded = linear ^ gamma`.
+    Gamma(f32),
+}
+
+impl ColorSpace {
+    /// Encoders commonly write a gamma of 1/2.2 for sRGB images, which is
+    /// close enough to the sRGB curve that it is treated as such.
+    fn from_gamma(gamma: f32) -> Self {
+        if (gamma - 1.0 / 2.2).abs() < 0.005 {
+            Self::Srgb
+        } else if (gamma - 1.0).abs() < 0.005 {
+            Self::Linear
+        } else {
+            Self::Gamma(gamma)
+        }
+    }
+}
+
+/// An error encountered while loading a bitmap.
+#[derive(Debug)]
+pub enum BitmapError {
+    /// The file could not be read.
+    Io(std::io::Error),
+    /// The data does not start with the signature of a supported format.
+    UnknownFormat,
+    /// The data is not a valid image of its format.
+    Invalid {
+        format: BitmapFormat,
+        message: String,
+    },
+    /// The image is valid, but uses a feature of its format that is not
+    /// supported.
+    Unsupported {
+        format: BitmapFormat,
+        feature: &'static str,
+    },
+}
+
+impl From<std::io::Error> for BitmapError {
+    fn from(error: std::io::Error) -> Self {
+        Self::Io(error)
+    }
+}
+
+/// An image in CPU memory, stored as tightly packed rows of 8-bit sRGB pixels
+/// premultiplied by alpha, starting from the top-left corner. This is the
+/// layout expected by [`GraphicsContext::create_texture`].
+///
+/// Bitmaps are either decoded from image files, in which case images in other
+/// color spaces are converted to sRGB, or captured from rendered images with
+/// [`GraphicsContext::capture`].
+///
+/// [`GraphicsContext::create_texture`]: crate::GraphicsContext::create_texture
+/// [`GraphicsContext::capture`]: crate::GraphicsContext::capture
+pub struct Bitmap {
+    extent: Extent<u32, ScreenSpace>,
+    pixels: Vec<u8>,
+    format: Option<BitmapFormat>,
+    color_space: ColorSpace,
+    has_alpha: bool,
+}
+
+impl Bitmap {
+    /// Decodes a PNG, JPEG, BMP or QOI image. The format is detected from the
+    /// data's signature.
+    pub fn decode(data: &[u8]) -> Result<Self, BitmapError> {
+        match detect_format(data).ok_or(BitmapError::UnknownFormat)? {
+            BitmapFormat::Png => decode_png(data),
+            BitmapFormat::Jpeg => decode_jpeg(data),
+            BitmapFormat::Bmp => decode_bmp(data),
+            BitmapFormat::Qoi => decode_qoi(data),
+        }
+    }
+
+    /// Reads and decodes an image file. See [`Bitmap::decode`].
+    pub fn load(path: impl AsRef<std::path::Path>) -> Result<Self, BitmapError> {
+        Self::decode(&std::fs::read(path)?)
+    }
+
+    /// Converts pixels to premultiplied sRGB. `pixels` must hold 1 to 4
+    /// channels: gray, gray and alpha, RGB, or RGBA.
+    fn new(
+        format: BitmapFormat,
+        extent: Extent<u32, ScreenSpace>,
+        channels: usize,
+        pixels: &[u8],
+        color_space: ColorSpace,
+    ) -> Self {
+        let to_srgb = srgb_table(color_space);
+
+        let mut rgba = Vec::with_capacity(4 * extent.width as usize * extent.height as usize);
+        for pixel in pixels.chunks_exact(channels) {
+            let [r, g, b, a] = match *pixel {
+                [l] => [l, l, l, u8::MAX],
+                [l, a] => [l, l, l, a],
+                [r, g, b] => [r, g, b, u8::MAX],
+                [r, g, b, a] => [r, g, b, a],
+                _ => unreachable!(),
+            };
+
+            let premultiply = |c: u8| {
+                let c = to_srgb.map_or(c, |table| table[usize::from(c)]);
+                ((u32::from(c) * u32::from(a) + 127) / 255) as u8
+            };
+            rgba.extend_from_slice(&[premultiply(r), premultiply(g), premultiply(b), a]);
+        }
+
+        Self {
+            extent,
+            pixels: rgba,
+            format: Some(format),
+            color_space,
+            has_alpha: channels == 2 || channels == 4,
+        }
+    }
+
+    #[must_use]
+    pub fn extent(&self) -> Extent<u32, ScreenSpace> {
+        self.extent
+    }
+
+    /// The image's pixels, as 8-bit premultiplied sRGB.
+    #[must_use]
+    pub fn pixels(&self) -> &[u8] {
+        &self.pixels
+    }
+
+    /// Converts premultiplied colors read back from an image of `format` to
+    /// 8 bits.
+    ///
+    /// Colors brighter than white, which 16-bit float images can hold, are
+    /// tone mapped by scaling them down until their brightest channel fits.
+    /// This keeps their hue, where clamping each channel would not. 16-bit
+    /// float images hold linear colors, which are then encoded as sRGB.
+    /// 8-bit images are assumed to hold sRGB already.
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    pub(crate) fn from_colors(
+        extent: Extent<u32, ScreenSpace>,
+        format: ImageFormat,
+        colors: &[Color],
+    ) -> Self {
+        let unorm8 = |value: f32| (value.clamp(0.0, 1.0) * 255.0).round() as u8;
+
+        let mut pixels = Vec::with_capacity(4 * colors.len());
+        for color in colors {
+            // Premultiplied channels are never brighter than alpha in range.
+            let a = color.a.clamp(0.0, 1.0);
+            let brightest = color.r.max(color.g).max(color.b);
+            let scale = if brightest > a { a / brightest } else { 1.0 };
+
+            let channel = |c: f32| {
+                let c = (c * scale).clamp(0.0, a);
+                match format {
+                    ImageFormat::Rgba8 => unorm8(c),
+                    // Encoding applies to straight colors, not premultiplied.
+                    ImageFormat::Rgba16Float if a > 0.0 => unorm8(srgb_encode(c / a) * a),
+                    ImageFormat::Rgba16Float => 0,
+                }
+            };
+
+            pixels.extend_from_slice(&[
+                channel(color.r),
+                channel(color.g),
+                channel(color.b),
+                unorm8(a),
+            ]);
+        }
+
+        Self {
+            extent,
+            pixels,
+            format: None,
+            color_space: ColorSpace::Srgb,
+            has_alpha: true,
+        }
+    }
+
+    /// Encodes the bitmap as a PNG file. PNGs store colors that are not
+    /// premultiplied, so fully transparent pixels lose their color.
+    #[must_use]
+    pub fn encode_png(&self) -> Vec<u8> {
+        let straight: Vec<u8> = self
+            .pixels
+            .chunks_exact(4)
+            .flat_map(|pixel| {
+                let a = u32::from(pixel[3]);
+                let unpremultiply = |c: u8| match a {
+                    0 => 0,
+                    _ => ((u32::from(c) * 255 + a / 2) / a).min(255) as u8,
+                };
+                [
+                    unpremultiply(pixel[0]),
+                    unpremultiply(pixel[1]),
+                    unpremultiply(pixel[2]),
+                    pixel[3],
+                ]
+            })
+            .collect();
+
+        let mut data = Vec::new();
+        let mut encoder = png::Encoder::new(&mut data, self.extent.width, self.extent.height);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+
+        // Writing to memory cannot fail, and the bitmap's size always matches
+        // its extent.
+        encoder
+            .write_header()
+            .unwrap()
+            .write_image_data(&straight)
+            .unwrap();
+        data
+    }
+
+    /// Encodes the bitmap as a PNG file and writes it to `path`. See
+    /// [`Bitmap::encode_png`].
+    pub fn save_png(&self, path: impl AsRef<std::path::Path>) -> std::io::Result<()> {
+        std::fs::write(path, self.encode_png())
+    }
+
+    /// The file format the bitmap was decoded from, or `None` if it was
+    /// captured from an image.
+    #[must_use]
+    pub fn format(&self) -> Option<BitmapFormat> {
+        self.format
+    }
+
+    /// The color space that the image was encoded in, before it was
+    /// converted to sRGB.
+    #[must_use]
+    pub fn color_space(&self) -> ColorSpace {
+        self.color_space
+    }
+
+    /// Whether the image has an alpha channel. Images without one are opaque.
+    #[must_use]
+    pub fn has_alpha(&self) -> bool {
+        self.has_alpha
+    }
+}
+
+fn detect_format(data: &[u8]) -> Option<BitmapFormat> {
+    if data.starts_with(b"\x89PNG\r\n\x1a\n") {
+        Some(BitmapFormat::Png)
+    } else if data.starts_with(&[0xFF, 0xD8, 0xFF]) {
+        Some(BitmapFormat::Jpeg)
+    } else if data.starts_with(b"BM") {
+        Some(BitmapFormat::Bmp)
+    } else if data.starts_with(b"qoif") {
+        Some(BitmapFormat::Qoi)
+    } else {
+        None
+    }
+}
+
+fn invalid(format: BitmapFormat, message: impl ToString) -> BitmapError {
+    BitmapError::Invalid {
+        format,
+        message: message.to_string(),
+    }
+}
+
+/// A table mapping channel values in `color_space` to sRGB, or `None` if the
+/// color space is already sRGB.
+fn srgb_table(color_space: ColorSpace) -> Option<[u8; 256]> {
+    let decode = match color_space {
+        ColorSpace::Srgb => return None,
+        ColorSpace::Linear => 1.0,
+        ColorSpace::Gamma(gamma) => 1.0 / gamma,
+    };
+
+    Some(std::array::from_fn(|i| {
+        let linear = (i as f32 / 255.0).powf(decode);
+        (srgb_encode(linear) * 255.0).round() as u8
+    }))
+}
+
+/// Applies the sRGB transfer function to a linear channel value in 0..=1.
+fn srgb_encode(linear: f32) -> f32 {
+    if linear <= 0.003_130_8 {
+        linear * 12.92
+    } else {
+        1.055 * linear.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+fn decode_png(data: &[u8]) -> Result<Bitmap, BitmapError> {
+    let error = |e| invalid(BitmapFormat::Png, e);
+
+    // Expand palettes, low bit depths and transparency chunks into plain
+    // 8-bit channels.
+    let mut decoder = png::Decoder::new(data);
+    decoder.set_transformations(png::Transformations::EXPAND | png::Transformations::STRIP_16);
+    let mut reader = decoder.read_info().map_err(error)?;
+
+    let mut pixels = vec![0; reader.output_buffer_size()];
+    let frame = reader.next_frame(&mut pixels).map_err(error)?;
+    pixels.truncate(frame.buffer_size());
+
+    // An sRGB chunk takes precedence over a gamma chunk.
+    let info = reader.info();
+    let color_space = match (info.srgb, info.source_gamma) {
+        (None, Some(gamma)) => ColorSpace::from_gamma(gamma.into_value()),
+        _ => ColorSpace::Srgb,
+    };
+
+    Ok(Bitmap::new(
+        BitmapFormat::Png,
+        Extent::new(frame.width, frame.height),
+        frame.color_type.samples(),
+        &pixels,
+        color_space,
+    ))
+}
+
+fn decode_jpeg(data: &[u8]) -> Result<Bitmap, BitmapError> {
+    let mut decoder = jpeg_decoder::Decoder::new(data);
+    let pixels = decoder
+        .decode()
+        .map_err(|e| invalid(BitmapFormat::Jpeg, e))?;
+    let info = decoder.info().unwrap();
+    let extent = Extent::new(u32::from(info.width), u32::from(info.height));
+
+    let (channels, pixels) = match info.pixel_format {
+        jpeg_decoder::PixelFormat::L8 => (1, pixels),
+        jpeg_decoder::PixelFormat::RGB24 => (3, pixels),
+        jpeg_decoder::PixelFormat::CMYK32 => {
+            // A naive conversion, ignoring any embedded color profile.
+            let rgb = pixels
+                .chunks_exact(4)
+                .flat_map(|cmyk| {
+                    let k = u32::from(255 - cmyk[3]);
+                    [0, 1, 2].map(|i| (u32::from(255 - cmyk[i]) * k / 255) as u8)
+                })
+                .collect();
+            (3, rgb)
+        }
+        jpeg_decoder::PixelFormat::L16 => {
+            return Err(BitmapError::Unsupported {
+                format: BitmapFormat::Jpeg,
+                feature: "16-bit lossless images",
+            })
+        }
+    };
+
+    Ok(Bitmap::new(
+        BitmapFormat::Jpeg,
+        extent,
+        channels,
+        &pixels,
+        ColorSpace::Srgb,
+    ))
+}
+
+fn decode_qoi(data: &[u8]) -> Result<Bitmap, BitmapError> {
+    let (header, pixels) = qoi::decode_to_vec(data).map_err(|e| invalid(BitmapFormat::Qoi, e))?;
+
+    // QOI's linear color space also covers alpha, which is always linear.
+    let color_space = match header.colorspace {
+        qoi::ColorSpace::Srgb => ColorSpace::Srgb,
+        qoi::ColorSpace::Linear => ColorSpace::Linear,
+    };
+
+    Ok(Bitmap::new(
+        BitmapFormat::Qoi,
+        Extent::new(header.width, header.height),
+        header.channels.as_u8().into(),
+        &pixels,
+        color_space,
+    ))
+}
+
+/// Decodes an uncompressed Windows bitmap with any of the common header
+/// versions. Run-length encoded and embedded JPEG or PNG bitmaps are not
+/// supported.
+fn decode_bmp(data: &[u8]) -> Result<Bitmap, BitmapError> {
+    const BI_RGB: u32 = 0;
+    const BI_BITFIELDS: u32 = 3;
+    const BI_ALPHABITFIELDS: u32 = 6;
+
+    let truncated = || invalid(BitmapFormat::Bmp, "unexpected end of data");
+    let too_large = || invalid(BitmapFormat::Bmp, "image too large");
+    let read = |offset: usize, size: usize| {
+        data.get(offset..offset + size)
+            .map(|bytes| bytes.iter().rev().fold(0, |v, &b| (v << 8) | u32::from(b)))
+            .ok_or_else(truncated)
+    };
+
+    let data_offset = read(10, 4)? as usize;
+    let header_size = read(14, 4)? as usize;
+
+    // The original OS/2 header uses 16-bit sizes and 3-byte palette entries.
+    let (width, height, bit_count, compression, num_colors, palette_entry_size) =
+        if header_size == 12 {
+            (
+                read(18, 2)?,
+                read(20, 2)? as i32,
+                read(24, 2)?,
+                BI_RGB,
+                0,
+                3,
+            )
+        } else if header_size >= 40 {
+            let width = read(18, 4)? as i32;
+            if width < 0 {
+                return Err(invalid(BitmapFormat::Bmp, "negative width"));
+            }
+            (
+                width as u32,
+                read(22, 4)? as i32,
+                read(28, 2)?,
+                read(30, 4)?,
+                read(46, 4)?,
+                4,
+            )
+        } else {
+            return Err(invalid(BitmapFormat::Bmp, "unknown header size"));
+        };
+
+    // Rows are stored bottom-up unless the height is negative.
+    let top_down = height < 0;
+    let extent = Extent::new(width, height.unsigned_abs());
+
+    if !matches!(bit_count, 1 | 2 | 4 | 8 | 16 | 24 | 32) {
+        return Err(invalid(BitmapFormat::Bmp, "unknown bit count"));
+    }
+
+    let masks = match (compression, bit_count) {
+        (BI_RGB, 16) => [0x7C00, 0x03E0, 0x001F, 0],
+        (BI_RGB, 32) => [0x00FF_0000, 0x0000_FF00, 0x0000_00FF, 0],
+        (BI_RGB, _) => [0; 4],
+        (BI_BITFIELDS | BI_ALPHABITFIELDS, 16 | 32) => {
+            // Version 1 headers are followed by the masks, later versions
+            // include them.
+            let has_alpha_mask = compression == BI_ALPHABITFIELDS || header_size >= 56;
+            [
+                read(54, 4)?,
+                read(58, 4)?,
+                read(62, 4)?,
+                if has_alpha_mask { read(66, 4)? } else { 0 },
+            ]
+        }
+        _ => {
+            return Err(BitmapError::Unsupported {
+                format: BitmapFormat::Bmp,
+                feature: "compressed images",
+            })
+        }
+    };
+
+    let palette = if bit_count <= 8 {
+        let num_colors = if num_colors == 0 {
+            1 << bit_count
+        } else {
+            num_colors as usize
+        };
+        let start = 14 + header_size;
+        let end = num_colors
+            .checked_mul(palette_entry_size)
+            .and_then(|size| start.checked_add(size))
+            .ok_or_else(too_large)?;
+        let palette = data.get(start..end).ok_or_else(truncated)?;
+        palette
+            .chunks_exact(palette_entry_size)
+            .map(|bgr| [bgr[2], bgr[1], bgr[0]])
+            .collect()
+    } else {
+        Vec::new()
+    };
+
+    let bits_per_pixel = bit_count as usize;
+    // Sizes come from the header, so they may overflow on purpose.
+    let row_size = (extent.width as usize)
+        .checked_mul(bits_per_pixel)
+        .map(|bits| bits.div_ceil(32) * 4)
+        .ok_or_else(too_large)?;
+    let image_end = row_size
+        .checked_mul(extent.height as usize)
+        .and_then(|size| data_offset.checked_add(size))
+        .ok_or_else(too_large)?;
+    let rows = data.get(data_offset..image_end).ok_or_else(truncated)?;
+
+    let has_alpha = masks[3] != 0;
+    let channels = if has_alpha { 4 } else { 3 };
+    let mut pixels = Vec::with_capacity(channels * extent.width as usize * extent.height as usize);
+
+    for y in 0..extent.height as usize {
+        let row = if top_down {
+            y
+        } else {
+            extent.height as usize - 1 - y
+        };
+        let row = &rows[row * row_size..(row + 1) * row_size];
+
+        for x in 0..extent.width as usize {
+            let rgba = match bits_per_pixel {
+                1 | 2 | 4 | 8 => {
+                    let bit = x * bits_per_pixel;
+                    let shift = 8 - bits_per_pixel - bit % 8;
+                    let index = (row[bit / 8] >> shift) & ((1 << bits_per_pixel) - 1) as u8;
+                    let [r, g, b] = *palette
+                        .get(usize::from(index))
+                        .ok_or_else(|| invalid(BitmapFormat::Bmp, "palette index out of range"))?;
+                    [r, g, b, u8::MAX]
+                }
+                24 => [row[3 * x + 2], row[3 * x + 1], row[3 * x], u8::MAX],
+                16 | 32 => {
+                    let size = bits_per_pixel / 8;
+                    let value = row[size * x..size * (x + 1)]
+                        .iter()
+                        .rev()
+                        .fold(0, |v, &b| (v << 8) | u32::from(b));
+                    masks.map(|mask| extract_channel(value, mask))
+                }
+                _ => unreachable!("bit counts are checked after the header is read"),
+            };
+
+            pixels.extend_from_slice(&rgba[..channels]);
+        }
+    }
+
+    Ok(Bitmap::new(
+        BitmapFormat::Bmp,
+        extent,
+        channels,
+        &pixels,
+        ColorSpace::Srgb,
+    ))
+}
+
+/// Extracts the bits of `value` selected by `mask`, scaled to 8 bits.
+fn extract_channel(value: u32, mask: u32) -> u8 {
+    if mask == 0 {
+        return 0;
+    }
+
+    let max = u64::from(mask >> mask.trailing_zeros());
+    let channel = u64::from((value & mask) >> mask.trailing_zeros());
+    ((channel * 255 + max / 2) / max) as u8
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encode_png(
+        extent: (u32, u32),
+        color_type: png::ColorType,
+        gamma: Option<f32>,
+        pixels: &[u8],
+    ) -> Vec<u8> {
+        let mut data = Vec::new();
+        let mut encoder = png::Encoder::new(&mut data, extent.0, extent.1);
+        encoder.set_color(color_type);
+        encoder.set_depth(png::BitDepth::Eight);
+        if let Some(gamma) = gamma {
+            encoder.set_source_gamma(png::ScaledFloat::new(gamma));
+        }
+        encoder
+            .write_header()
+            .unwrap()
+            .write_image_data(pixels)
+            .unwrap();
+        data
+    }
+
+    /// Builds a BMP file with a `BITMAPINFOHEADER`.
+    fn encode_bmp(width: i32, height: i32, bit_count: u16, extra: &[u8], rows: &[u8]) -> Vec<u8> {
+        let compression: u32 = if extra.len() >= 12 && bit_count >= 16 {
+            3
+        } else {
+            0
+        };
+        let data_offset = (14 + 40 + extra.len()) as u32;
+
+        let mut data = Vec::new();
+        data.extend_from_slice(b"BM");
+        data.extend_from_slice(&(data_offset + rows.len() as u32).to_le_bytes());
+        data.extend_from_slice(&[0; 4]);
+        data.extend_from_slice(&data_offset.to_le_bytes());
+        data.extend_from_slice(&40u32.to_le_bytes());
+        data.extend_from_slice(&width.to_le_bytes());
+        data.extend_from_slice(&height.to_le_bytes());
+        data.extend_from_slice(&1u16.to_le_bytes());
+        data.extend_from_slice(&bit_count.to_le_bytes());
+        data.extend_from_slice(&compression.to_le_bytes());
+        data.extend_from_slice(&[0; 20]);
+        data.extend_from_slice(extra);
+        data.extend_from_slice(rows);
+        data
+    }
+
+    #[test]
+    fn png() {
+        let data = encode_png(
+            (2, 1),
+            png::ColorType::Rgba,
+            None,
+            &[255, 128, 0, 255, 255, 128, 0, 128],
+        );
+
+        let bitmap = Bitmap::decode(&data).unwrap();
+        assert_eq!(bitmap.format(), Some(BitmapFormat::Png));
+        assert_eq!(bitmap.extent(), Extent::new(2, 1));
+        assert_eq!(bitmap.color_space(), ColorSpace::Srgb);
+        assert!(bitmap.has_alpha());
+        assert_eq!(bitmap.pixels(), [255, 128, 0, 255, 128, 64, 0, 128]);
+    }
+
+    #[test]
+    fn png_gamma() {
+        let gray = [0, 128, 255];
+
+        // A gamma of 1/2.2 is close enough to sRGB to be left alone.
+        let bitmap = Bitmap::decode(&encode_png(
+            (3, 1),
+            png::ColorType::Grayscale,
+            Some(0.45455),
+            &gray,
+        ))
+        .unwrap();
+        assert_eq!(bitmap.color_space(), ColorSpace::Srgb);
+        assert_eq!(
+            bitmap.pixels(),
+            [0, 0, 0, 255, 128, 128, 128, 255, 255, 255, 255, 255]
+        );
+
+        // Linear mid-gray is lighter once encoded as sRGB.
+        let bitmap = Bitmap::decode(&encode_png(
+            (3, 1),
+            png::ColorType::Grayscale,
+            Some(1.0),
+            &gray,
+        ))
+        .unwrap();
+        assert_eq!(bitmap.color_space(), ColorSpace::Linear);
+        assert!(!bitmap.has_alpha());
+        assert_eq!(
+            bitmap.pixels(),
+            [0, 0, 0, 255, 188, 188, 188, 255, 255, 255, 255, 255]
+        );
+    }
+
+    #[test]
+    fn jpeg() {
+        let pixels = [200, 100, 50].repeat(64);
+        let mut data = Vec::new();
+        jpeg_encoder::Encoder::new(&mut data, 100)
+            .encode(&pixels, 8, 8, jpeg_encoder::ColorType::Rgb)
+            .unwrap();
+
+        let bitmap = Bitmap::decode(&data).unwrap();
+        assert_eq!(bitmap.format(), Some(BitmapFormat::Jpeg));
+        assert_eq!(bitmap.extent(), Extent::new(8, 8));
+        for pixel in bitmap.pixels().chunks_exact(4) {
+            for (actual, expected) in pixel.iter().zip([200, 100, 50, 255]) {
+                assert!(actual.abs_diff(expected) <= 2, "{pixel:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn qoi() {
+        let pixels = [255, 0, 0, 255, 0, 0, 255, 0];
+        let data = qoi::Encoder::new(&pixels, 2, 1)
+            .unwrap()
+            .with_colorspace(qoi::ColorSpace::Linear)
+            .encode_to_vec()
+            .unwrap();
+
+        let bitmap = Bitmap::decode(&data).unwrap();
+        assert_eq!(bitmap.format(), Some(BitmapFormat::Qoi));
+        assert_eq!(bitmap.color_space(), ColorSpace::Linear);
+        assert_eq!(bitmap.pixels(), [255, 0, 0, 255, 0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn bmp() {
+        // 24-bit, bottom-up, with rows padded to 4 bytes.
+        let bitmap =
+            Bitmap::decode(&encode_bmp(1, 2, 24, &[], &[255, 0, 0, 0, 0, 0, 255, 0])).unwrap();
+        assert_eq!(bitmap.extent(), Extent::new(1, 2));
+        assert!(!bitmap.has_alpha());
+        assert_eq!(bitmap.pixels(), [255, 0, 0, 255, 0, 0, 255, 255]);
+
+        // 1-bit palette, top-down.
+        let palette = [0, 0, 0, 0, 255, 255, 255, 0];
+        let bitmap =
+            Bitmap::decode(&encode_bmp(3, -1, 1, &palette, &[0b0100_0000, 0, 0, 0])).unwrap();
+        assert_eq!(
+            bitmap.pixels(),
+            [0, 0, 0, 255, 255, 255, 255, 255, 0, 0, 0, 255]
+        );
+
+        // 16-bit 4444 with an alpha mask.
+        let masks = [0x0F00u32, 0x00F0, 0x000F, 0xF000].map(u32::to_le_bytes);
+        let mut data = encode_bmp(1, 1, 16, masks.as_flattened(), &[0x0F, 0x8F, 0, 0]);
+        data[30] = 6; // BI_ALPHABITFIELDS
+        let bitmap = Bitmap::decode(&data).unwrap();
+        assert!(bitmap.has_alpha());
+        assert_eq!(bitmap.pixels(), [136, 0, 136, 136]);
+    }
+
+    #[test]
+    fn capture() {
+        let bitmap = Bitmap::from_colors(
+            Extent::new(4, 1),
+            ImageFormat::Rgba8,
+            &[
+                Color::new(0.5, 0.25, 0.0, 1.0),
+                // Too bright for 8 bits: scaled down, keeping its hue.
+                Color::new(4.0, 2.0, -1.0, 1.0),
+                Color::new(1.0, 0.5, 0.0, 0.5),
+                Color::new(0.0, 0.0, 0.0, 0.0),
+            ],
+        );
+        assert_eq!(bitmap.format(), None);
+        assert_eq!(
+            bitmap.pixels(),
+            [128, 64, 0, 255, 255, 128, 0, 255, 128, 64, 0, 128, 0, 0, 0, 0]
+        );
+
+        // PNGs are not premultiplied.
+        let data = bitmap.encode_png();
+        let mut reader = png::Decoder::new(&data[..]).read_info().unwrap();
+        let mut straight = vec![0; reader.output_buffer_size()];
+        reader.next_frame(&mut straight).unwrap();
+        assert_eq!(straight[8..12], [255, 128, 0, 128]);
+        assert_eq!(Bitmap::decode(&data).unwrap().pixels(), bitmap.pixels());
+    }
+
+    #[test]
+    fn capture_linear() {
+        // Linear mid-gray is lighter once encoded as sRGB. Transparent colors
+        // are encoded before they are premultiplied, and bright colors after
+        // they are tone mapped.
+        let bitmap = Bitmap::from_colors(
+            Extent::new(3, 1),
+            ImageFormat::Rgba16Float,
+            &[
+                Color::new(0.5, 0.5, 0.5, 1.0),
+                Color::new(0.25, 0.25, 0.25, 0.5),
+                Color::new(4.0, 2.0, 0.0, 1.0),
+            ],
+        );
+        assert_eq!(
+            bitmap.pixels(),
+            [188, 188, 188, 255, 94, 94, 94, 128, 255, 188, 0, 255]
+        );
+    }
+
+    #[test]
+    fn errors() {
+        assert!(matches!(
+            Bitmap::decode(b"GIF89a"),
+            Err(BitmapError::UnknownFormat)
+        ));
+        assert!(matches!(
+            Bitmap::decode(&encode_bmp(4, 4, 24, &[], &[0; 8])),
+            Err(BitmapError::Invalid {
+                format: BitmapFormat::Bmp,
+                ..
+            })
+        ));
+
+        // Malicious headers with enormous sizes are rejected, rather than
+        // overflowing on targets with 32-bit pointers.
+        let mut huge = encode_bmp(i32::MAX, i32::MIN, 32, &[], &[0; 4]);
+        assert!(matches!(
+            Bitmap::decode(&huge),
+            Err(BitmapError::Invalid {
+                format: BitmapFormat::Bmp,
+                ..
+            })
+        ));
+        huge[10..14].copy_from_slice(&u32::MAX.to_le_bytes());
+        huge[18..22].copy_from_slice(&1i32.to_le_bytes());
+        huge[22..26].copy_from_slice(&1i32.to_le_bytes());
+        assert!(matches!(
+            Bitmap::decode(&huge),
+            Err(BitmapError::Invalid {
+                format: BitmapFormat::Bmp,
+                ..
+            })
+        ));
+        let mut palette = encode_bmp(1, 1, 8, &[], &[0; 4]);
+        palette[46..50].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert!(matches!(
+            Bitmap::decode(&palette),
+            Err(BitmapError::Invalid {
+                format: BitmapFormat::Bmp,
+                ..
+            })
+        ));
+        assert!(matches!(
+            Bitmap::decode(&encode_bmp(1, 1, 0, &[], &[0; 4])),
+            Err(BitmapError::Invalid {
+                format: BitmapFormat::Bmp,
+                ..
+            })
+        ));
+
+        assert!(matches!(
+            Bitmap::decode(&b"\x89PNG\r\n\x1a\n"[..]),
+            Err(BitmapError::Invalid {
+                format: BitmapFormat::Png,
+                ..
+            })
+        ));
+        assert!(matches!(
+            Bitmap::load("does/not/exist.png"),
+            Err(BitmapError::Io(_))
+        ));
+    }
+}
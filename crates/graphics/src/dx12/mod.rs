@@ -1,10 +1,10 @@
 use std::{
     cell::{Cell, RefCell},
-    collections::VecDeque,
+    collections::{HashMap, VecDeque},
     rc::Rc,
 };
 
-use geometry::{Extent, ScreenSpace};
+use geometry::{Extent, ObjectSpace, Point, Rect, ScreenSpace, Transform};
 use raw_window_handle::RawWindowHandle;
 use smallvec::SmallVec;
 #[allow(clippy::wildcard_imports)]
@@ -16,21 +16,31 @@ use windows::{
     },
 };
 
-use crate::{render_graph::RenderGraph, GraphicsConfig, RenderGraphNodeId, Vertex};
+use crate::{
+    render_graph::{BlendFactor, IndexBuffer, RenderGraph},
+    BlendMode, Color, DrawError, Filter, GraphicsConfig, ImageFormat, RenderGraphNodeId,
+    UploadStatistics, Vertex,
+};
 
 mod dx;
 mod graphics;
 mod surface;
-mod temp_allocator;
 
 pub use surface::{Surface, SurfaceImage};
 
-use self::temp_allocator::FrameMarker;
+use crate::temp_allocator::{self, FrameMarker};
 
 struct Frame {
     barriers: SmallVec<[D3D12_RESOURCE_BARRIER; 2]>,
     command_list: ID3D12GraphicsCommandList,
     command_allocator: ID3D12CommandAllocator,
+    /// Intermediate images for layers, reused by every draw that the frame
+    /// records. Layers nested `n` deep draw into the `n`th image.
+    layers: Vec<LayerImage>,
+    /// Textures sampled by the frame, kept alive until the frame completes.
+    textures: Vec<Rc<Texture>>,
+    /// Meshes drawn by the frame, kept alive until the frame completes.
+    meshes: Vec<Rc<Mesh>>,
 }
 
 struct FrameInFlight {
@@ -43,17 +53,21 @@ pub struct GraphicsContext {
     dx: Rc<dx::Interfaces>,
     graphics_queue: Rc<RefCell<graphics::Queue>>,
     ui_shader: Polygon,
+    composite_shader: Composite,
+    image_shader: Textured,
 
-    upload_ptr: *mut std::ffi::c_void,
-    upload_buffer: ID3D12Resource,
-    upload_allocator: temp_allocator::Allocator,
+    upload_allocator: temp_allocator::Allocator<UploadChunk>,
 
     unused_frames: Vec<Frame>,
     frames_in_flight: VecDeque<FrameInFlight>,
 }
 
 impl GraphicsContext {
-    const UPLOAD_BUFFER_SIZE: u64 = 1024;
+    /// The initial size of the memory that render graphs are uploaded
+    /// through, which grows as needed. Buffers take up at least 64 KiB.
+    const UPLOAD_BUFFER_SIZE: u64 = 64 * 1024;
+    const CLEAR_COLOR: [f32; 4] = [0.5, 0.5, 0.5, 1.0];
+    const LAYER_CLEAR_COLOR: [f32; 4] = [0.0, 0.0, 0.0, 0.0];
 
     pub fn new(config: &GraphicsConfig) -> Self {
         let dx = dx::Interfaces::new(config);
@@ -61,11 +75,296 @@ impl GraphicsContext {
         let graphics_queue = graphics::Queue::new(&dx);
 
         let ui_shader = Polygon::new(&dx);
+        let image_shader = Textured::new(&dx);
+
+        let dx = Rc::new(dx);
+
+        let upload_allocator = temp_allocator::Allocator::new(Self::UPLOAD_BUFFER_SIZE, {
+            let dx = dx.clone();
+            move |size| UploadChunk::new(&dx, size)
+        });
+
+        Self {
+            dx,
+            graphics_queue: Rc::new(RefCell::new(graphics_queue)),
+            ui_shader,
+            composite_shader: Composite::new(),
+            image_shader,
+            upload_allocator,
+            unused_frames: Vec::new(),
+            frames_in_flight: VecDeque::new(),
+        }
+    }
+
+    pub fn create_surface(&self, window: RawWindowHandle) -> Surface {
+        match window {
+            RawWindowHandle::Win32(handle) => Surface::new(
+                self.dx.clone(),
+                self.graphics_queue.clone(),
+                HWND(handle.hwnd as _),
+            ),
+            _ => unimplemented!(),
+        }
+    }
+
+    pub fn create_image(&self, extent: Extent<u32, ScreenSpace>, format: ImageFormat) -> Image {
+        self.create_render_target(extent, format, Self::CLEAR_COLOR)
+            .unwrap()
+    }
+
+    fn create_render_target(
+        &self,
+        extent: Extent<u32, ScreenSpace>,
+        format: ImageFormat,
+        clear_color: [f32; 4],
+    ) -> windows::core::Result<Image> {
+        let dxgi_format = dxgi_format(format);
+
+        let resource: ID3D12Resource = unsafe {
+            let mut resource = None;
+            self.dx.device.CreateCommittedResource(
+                &D3D12_HEAP_PROPERTIES {
+                    Type: D3D12_HEAP_TYPE_DEFAULT,
+                    CPUPageProperty: D3D12_CPU_PAGE_PROPERTY_UNKNOWN,
+                    MemoryPoolPreference: D3D12_MEMORY_POOL_UNKNOWN,
+                    CreationNodeMask: 0,
+                    VisibleNodeMask: 0,
+                },
+                D3D12_HEAP_FLAG_NONE,
+                &D3D12_RESOURCE_DESC {
+                    Dimension: D3D12_RESOURCE_DIMENSION_TEXTURE2D,
+                    Alignment: 0,
+                    Width: u64::from(extent.width),
+                    Height: extent.height,
+                    DepthOrArraySize: 1,
+                    MipLevels: 1,
+                    Format: dxgi_format,
+                    SampleDesc: DXGI_SAMPLE_DESC {
+                        Count: 1,
+                        Quality: 0,
+                    },
+                    Layout: D3D12_TEXTURE_LAYOUT_UNKNOWN,
+                    Flags: D3D12_RESOURCE_FLAG_ALLOW_RENDER_TARGET,
+                },
+                // Images rest in the common state between draws, which is
+                // the same as the present state used by swapchain images.
+                D3D12_RESOURCE_STATE_COMMON,
+                Some(&D3D12_CLEAR_VALUE {
+                    Format: dxgi_format,
+                    Anonymous: D3D12_CLEAR_VALUE_0 { Color: clear_color },
+                }),
+                &mut resource,
+            )?;
+            resource.unwrap()
+        };
+
+        let rtv_heap: ID3D12DescriptorHeap = unsafe {
+            self.dx
+                .device
+                .CreateDescriptorHeap(&D3D12_DESCRIPTOR_HEAP_DESC {
+                    Type: D3D12_DESCRIPTOR_HEAP_TYPE_RTV,
+                    NumDescriptors: 1,
+                    Flags: D3D12_DESCRIPTOR_HEAP_FLAG_NONE,
+                    NodeMask: 0,
+                })
+        }?;
+
+        let rtv = unsafe { rtv_heap.GetCPUDescriptorHandleForHeapStart() };
+        unsafe { self.dx.device.CreateRenderTargetView(&resource, None, rtv) };
+
+        Ok(Image {
+            resource,
+            format,
+            last_use: Cell::new(0),
+            rtv,
+            _rtv_heap: Some(rtv_heap),
+        })
+    }
+
+    /// Creates an image for drawing a layer into, which can then be read by
+    /// the composite shader.
+    fn create_layer(
+        &self,
+        extent: Extent<u32, ScreenSpace>,
+        format: ImageFormat,
+    ) -> windows::core::Result<LayerImage> {
+        let image = self.create_render_target(extent, format, Self::LAYER_CLEAR_COLOR)?;
+
+        let srv_heap: ID3D12DescriptorHeap = unsafe {
+            self.dx
+                .device
+                .CreateDescriptorHeap(&D3D12_DESCRIPTOR_HEAP_DESC {
+                    Type: D3D12_DESCRIPTOR_HEAP_TYPE_CBV_SRV_UAV,
+                    NumDescriptors: 1,
+                    Flags: D3D12_DESCRIPTOR_HEAP_FLAG_SHADER_VISIBLE,
+                    NodeMask: 0,
+                })
+        }?;
+
+        unsafe {
+            self.dx.device.CreateShaderResourceView(
+                &image.resource,
+                None,
+                srv_heap.GetCPUDescriptorHandleForHeapStart(),
+            );
+        }
+
+        Ok(LayerImage { image, srv_heap })
+    }
+
+    /// Makes sure that a frame has enough layer images of the given extent and
+    /// format for a graph whose layers are nested `depth` deep. Images that no
+    /// longer match are released.
+    fn reserve_layers(
+        &self,
+        frame: &mut Frame,
+        extent: Extent<u32, ScreenSpace>,
+        format: ImageFormat,
+        depth: usize,
+    ) -> Result<(), DrawError> {
+        frame
+            .layers
+            .retain(|layer| layer.image.extent() == extent && layer.image.format == format);
+        frame.layers.truncate(depth);
+
+        while frame.layers.len() < depth {
+            let layer = self
+                .create_layer(extent, format)
+                .map_err(|_| DrawError::OutOfMemory)?;
+            frame.layers.push(layer);
+        }
+        Ok(())
+    }
+
+    pub fn create_texture(&mut self, extent: Extent<u32, ScreenSpace>, pixels: &[u8]) -> Texture {
+        let desc = D3D12_RESOURCE_DESC {
+            Dimension: D3D12_RESOURCE_DIMENSION_TEXTURE2D,
+            Alignment: 0,
+            Width: u64::from(extent.width),
+            Height: extent.height,
+            DepthOrArraySize: 1,
+            MipLevels: 1,
+            Format: DXGI_FORMAT_R8G8B8A8_UNORM,
+            SampleDesc: DXGI_SAMPLE_DESC {
+                Count: 1,
+                Quality: 0,
+            },
+            Layout: D3D12_TEXTURE_LAYOUT_UNKNOWN,
+            Flags: D3D12_RESOURCE_FLAG_NONE,
+        };
 
-        // create upload buffer
-        let upload_buffer: ID3D12Resource = unsafe {
+        let resource: ID3D12Resource = unsafe {
+            let mut resource = None;
+            self.dx
+                .device
+                .CreateCommittedResource(
+                    &D3D12_HEAP_PROPERTIES {
+                        Type: D3D12_HEAP_TYPE_DEFAULT,
+                        CPUPageProperty: D3D12_CPU_PAGE_PROPERTY_UNKNOWN,
+                        MemoryPoolPreference: D3D12_MEMORY_POOL_UNKNOWN,
+                        CreationNodeMask: 0,
+                        VisibleNodeMask: 0,
+                    },
+                    D3D12_HEAP_FLAG_NONE,
+                    &desc,
+                    D3D12_RESOURCE_STATE_COPY_DEST,
+                    None,
+                    &mut resource,
+                )
+                .unwrap();
+            resource.unwrap()
+        };
+
+        // New textures start out as copy destinations.
+        self.write_texture(
+            &resource,
+            Point::new(0, 0),
+            extent,
+            pixels,
+            D3D12_RESOURCE_STATE_COPY_DEST,
+        );
+
+        let srv_heap: ID3D12DescriptorHeap = unsafe {
+            self.dx
+                .device
+                .CreateDescriptorHeap(&D3D12_DESCRIPTOR_HEAP_DESC {
+                    Type: D3D12_DESCRIPTOR_HEAP_TYPE_CBV_SRV_UAV,
+                    NumDescriptors: 1,
+                    Flags: D3D12_DESCRIPTOR_HEAP_FLAG_SHADER_VISIBLE,
+                    NodeMask: 0,
+                })
+        }
+        .unwrap();
+
+        unsafe {
+            self.dx.device.CreateShaderResourceView(
+                &resource,
+                None,
+                srv_heap.GetCPUDescriptorHandleForHeapStart(),
+            );
+        }
+
+        Texture { resource, srv_heap }
+    }
+
+    pub fn update_texture(
+        &mut self,
+        texture: &Texture,
+        origin: Point<u32, ScreenSpace>,
+        extent: Extent<u32, ScreenSpace>,
+        pixels: &[u8],
+    ) {
+        self.write_texture(
+            &texture.resource,
+            origin,
+            extent,
+            pixels,
+            D3D12_RESOURCE_STATE_PIXEL_SHADER_RESOURCE,
+        );
+    }
+
+    /// Copies pixels into a region of a texture, leaving it ready to be read
+    /// by shaders. Copies are ordered after all previously submitted frames,
+    /// so frames that sampled the texture before it was written to are not
+    /// affected.
+    ///
+    /// This function will block until the copy has completed.
+    fn write_texture(
+        &mut self,
+        resource: &ID3D12Resource,
+        origin: Point<u32, ScreenSpace>,
+        extent: Extent<u32, ScreenSpace>,
+        pixels: &[u8],
+        state: D3D12_RESOURCE_STATES,
+    ) {
+        let desc = D3D12_RESOURCE_DESC {
+            Width: u64::from(extent.width),
+            Height: extent.height,
+            ..unsafe { resource.GetDesc() }
+        };
+
+        let mut footprint = D3D12_PLACED_SUBRESOURCE_FOOTPRINT::default();
+        let mut total_size = 0;
+        unsafe {
+            self.dx.device.GetCopyableFootprints(
+                &desc,
+                0,
+                1,
+                0,
+                Some(&mut footprint),
+                None,
+                None,
+                Some(&mut total_size),
+            );
+        }
+
+        // Texture rows in the staging buffer must be aligned to
+        // `D3D12_TEXTURE_DATA_PITCH_ALIGNMENT`, so they cannot go through the
+        // upload allocator.
+        let staging_buffer: ID3D12Resource = unsafe {
             let mut buffer = None;
-            dx.device
+            self.dx
+                .device
                 .CreateCommittedResource(
                     &D3D12_HEAP_PROPERTIES {
                         Type: D3D12_HEAP_TYPE_UPLOAD,
@@ -74,11 +373,11 @@ impl GraphicsContext {
                         CreationNodeMask: 0,
                         VisibleNodeMask: 0,
                     },
-                    D3D12_HEAP_FLAG_NONE, // set automatically by CreateCommitedResource
+                    D3D12_HEAP_FLAG_NONE,
                     &D3D12_RESOURCE_DESC {
                         Dimension: D3D12_RESOURCE_DIMENSION_BUFFER,
-                        Alignment: 0, // default: 64k
-                        Width: Self::UPLOAD_BUFFER_SIZE,
+                        Alignment: 0,
+                        Width: total_size,
                         Height: 1,
                         DepthOrArraySize: 1,
                         MipLevels: 1,
@@ -98,95 +397,172 @@ impl GraphicsContext {
             buffer.unwrap()
         };
 
-        let upload_ptr = unsafe {
-            // persistently mapped pointer
+        unsafe {
             let mut ptr = std::ptr::null_mut();
-            upload_buffer
-                .Map(0, Some(&D3D12_RANGE { Begin: 0, End: 0 }), Some(&mut ptr))
-                .unwrap();
-            ptr
-        };
+            staging_buffer.Map(0, None, Some(&mut ptr)).unwrap();
+            let staging = std::slice::from_raw_parts_mut(ptr.cast::<u8>(), total_size as usize);
+
+            let row_size = 4 * extent.width as usize;
+            let row_pitch = footprint.Footprint.RowPitch as usize;
+            for (src, dst) in pixels
+                .chunks_exact(row_size)
+                .zip(staging.chunks_mut(row_pitch))
+            {
+                dst[..row_size].copy_from_slice(src);
+            }
 
-        let upload_allocator = temp_allocator::Allocator::new(Self::UPLOAD_BUFFER_SIZE);
+            staging_buffer.Unmap(0, None);
+        }
 
-        Self {
-            dx: Rc::new(dx),
-            graphics_queue: Rc::new(RefCell::new(graphics_queue)),
-            ui_shader,
-            upload_ptr,
-            upload_buffer,
-            upload_allocator,
-            unused_frames: Vec::new(),
-            frames_in_flight: VecDeque::new(),
+        let frame = self.begin_frame();
+
+        unsafe {
+            if state != D3D12_RESOURCE_STATE_COPY_DEST {
+                frame.command_list.ResourceBarrier(&[transition_barrier(
+                    resource,
+                    state,
+                    D3D12_RESOURCE_STATE_COPY_DEST,
+                )]);
+            }
+
+            frame.command_list.CopyTextureRegion(
+                &D3D12_TEXTURE_COPY_LOCATION {
+                    pResource: windows::core::ManuallyDrop::new(resource),
+                    Type: D3D12_TEXTURE_COPY_TYPE_SUBRESOURCE_INDEX,
+                    Anonymous: D3D12_TEXTURE_COPY_LOCATION_0 {
+                        SubresourceIndex: 0,
+                    },
+                },
+                origin.x,
+                origin.y,
+                0,
+                &D3D12_TEXTURE_COPY_LOCATION {
+                    pResource: windows::core::ManuallyDrop::new(&staging_buffer),
+                    Type: D3D12_TEXTURE_COPY_TYPE_PLACED_FOOTPRINT,
+                    Anonymous: D3D12_TEXTURE_COPY_LOCATION_0 {
+                        PlacedFootprint: footprint,
+                    },
+                },
+                None,
+            );
+
+            frame.command_list.ResourceBarrier(&[transition_barrier(
+                resource,
+                D3D12_RESOURCE_STATE_COPY_DEST,
+                D3D12_RESOURCE_STATE_PIXEL_SHADER_RESOURCE,
+            )]);
         }
+
+        // Nothing was taken from the upload allocator, so the frame
+        // marker is empty.
+        let frame_marker = self.upload_allocator.begin_frame().finish();
+        let fence_value = self.submit_frame(frame, frame_marker);
+
+        // The staging buffer is freed when this function returns.
+        self.graphics_queue.borrow().wait_until(fence_value);
     }
 
-    pub fn create_surface(&self, window: RawWindowHandle) -> Surface {
-        match window {
-            RawWindowHandle::Win32(handle) => Surface::new(
-                self.dx.clone(),
-                self.graphics_queue.clone(),
-                HWND(handle.hwnd as _),
-            ),
-            _ => unimplemented!(),
+    /// Creates a mesh in a default heap buffer, with its vertices followed by
+    /// its indices. Indices are stored in 16 bits if they fit, as in render
+    /// graphs.
+    ///
+    /// This function will block until the copy has completed.
+    pub fn create_mesh(&mut self, vertices: &[Vertex], indices: &[u32]) -> Mesh {
+        let mut index_buffer = IndexBuffer::default();
+        index_buffer.extend(indices.iter().copied(), vertices.len());
+
+        let (index_data, index_format) = match &index_buffer {
+            IndexBuffer::U16(indices) => (as_bytes(indices), DXGI_FORMAT_R16_UINT),
+            IndexBuffer::U32(indices) => (as_bytes(indices), DXGI_FORMAT_R32_UINT),
+        };
+        let vertex_data = as_bytes(vertices);
+
+        // Vertices are a multiple of 4 bytes in size, so the indices that
+        // follow them are aligned. Buffers cannot be empty.
+        let size = (vertex_data.len() + index_data.len()).max(4) as u64;
+
+        let staging_buffer = create_buffer(
+            &self.dx,
+            D3D12_HEAP_TYPE_UPLOAD,
+            size,
+            D3D12_RESOURCE_STATE_GENERIC_READ,
+        )
+        .unwrap();
+        // Buffers are always created in the common state, and are promoted to
+        // copy destinations by the copy.
+        let buffer = create_buffer(
+            &self.dx,
+            D3D12_HEAP_TYPE_DEFAULT,
+            size,
+            D3D12_RESOURCE_STATE_COMMON,
+        )
+        .unwrap();
+
+        unsafe {
+            let mut ptr = std::ptr::null_mut();
+            staging_buffer.Map(0, None, Some(&mut ptr)).unwrap();
+            let staging = std::slice::from_raw_parts_mut(ptr.cast::<u8>(), size as usize);
+            staging[..vertex_data.len()].copy_from_slice(vertex_data);
+            staging[vertex_data.len()..][..index_data.len()].copy_from_slice(index_data);
+            staging_buffer.Unmap(0, None);
         }
-    }
 
-    pub fn draw(&mut self, target: &Image, content: &RenderGraph) {
         let frame = self.begin_frame();
 
-        let (frame_marker, imm_vertex_view, imm_index_view) = {
-            let mut frame_alloc = self.upload_allocator.begin_frame();
+        unsafe {
+            frame
+                .command_list
+                .CopyBufferRegion(&buffer, 0, &staging_buffer, 0, size);
 
-            let vertex_memory = frame_alloc
-                .allocate(
-                    (content.imm_vertices.len() * std::mem::size_of::<Vertex>()) as u64,
-                    std::mem::align_of::<Vertex>() as u64,
-                )
-                .expect("temporary memory allocation failed, todo: handle this gracefully");
+            frame.command_list.ResourceBarrier(&[transition_barrier(
+                &buffer,
+                D3D12_RESOURCE_STATE_COPY_DEST,
+                D3D12_RESOURCE_STATE_VERTEX_AND_CONSTANT_BUFFER | D3D12_RESOURCE_STATE_INDEX_BUFFER,
+            )]);
+        }
 
-            unsafe {
-                std::slice::from_raw_parts_mut(
-                    self.upload_ptr
-                        .add(vertex_memory.heap_offset as usize)
-                        .cast(),
-                    content.imm_vertices.len(),
-                )
-                .copy_from_slice(&content.imm_vertices);
-            }
+        // Nothing was taken from the upload allocator, so the frame
+        // marker is empty.
+        let frame_marker = self.upload_allocator.begin_frame().finish();
+        let fence_value = self.submit_frame(frame, frame_marker);
 
-            let vertex_view = D3D12_VERTEX_BUFFER_VIEW {
-                BufferLocation: unsafe { self.upload_buffer.GetGPUVirtualAddress() }
-                    + vertex_memory.heap_offset,
-                SizeInBytes: vertex_memory.size as u32,
-                StrideInBytes: std::mem::size_of::<Vertex>() as u32,
-            };
+        // The staging buffer is freed when this function returns.
+        self.graphics_queue.borrow().wait_until(fence_value);
 
-            let index_memory = frame_alloc
-                .allocate(
-                    (content.imm_indices.len() * std::mem::size_of::<u16>()) as u64,
-                    std::mem::align_of::<u16>() as u64,
-                )
-                .expect("temporary memory allocation failed, todo: handle this gracefully");
+        let address = unsafe { buffer.GetGPUVirtualAddress() };
+        Mesh {
+            vertices: D3D12_VERTEX_BUFFER_VIEW {
+                BufferLocation: address,
+                SizeInBytes: vertex_data.len() as u32,
+                StrideInBytes: std::mem::size_of::<Vertex>() as u32,
+            },
+            indices: D3D12_INDEX_BUFFER_VIEW {
+                BufferLocation: address + vertex_data.len() as u64,
+                SizeInBytes: index_data.len() as u32,
+                Format: index_format,
+            },
+            num_vertices: vertices.len(),
+            num_indices: indices.len() as u32,
+            _buffer: buffer,
+        }
+    }
 
-            unsafe {
-                std::slice::from_raw_parts_mut(
-                    self.upload_ptr
-                        .add(index_memory.heap_offset as usize)
-                        .cast(),
-                    content.imm_indices.len(),
-                )
-                .copy_from_slice(&content.imm_indices);
-            }
+    pub fn draw(&mut self, target: &Image, content: &RenderGraph) -> Result<(), DrawError> {
+        let mut frame = self.begin_frame();
 
-            let index_view = D3D12_INDEX_BUFFER_VIEW {
-                BufferLocation: unsafe { self.upload_buffer.GetGPUVirtualAddress() }
-                    + index_memory.heap_offset,
-                SizeInBytes: index_memory.size as u32,
-                Format: DXGI_FORMAT_R16_UINT,
-            };
+        let extent = target.extent();
+        let depth = layer_depth(content, RenderGraphNodeId::root());
+        let uploaded = self
+            .reserve_layers(&mut frame, extent, target.format, depth)
+            .and_then(|()| self.upload_render_graph(content));
 
-            (frame_alloc.finish(), vertex_view, index_view)
+        let (frame_marker, immediate) = match uploaded {
+            Ok(uploaded) => uploaded,
+            Err(error) => {
+                // Nothing was recorded, so the frame can be reused as is.
+                self.unused_frames.push(frame);
+                return Err(error);
+            }
         };
 
         unsafe {
@@ -200,16 +576,12 @@ impl GraphicsContext {
                 .command_list
                 .OMSetRenderTargets(1, Some(&target.rtv), false, None);
 
-            frame.command_list.ClearRenderTargetView(
-                target.rtv,
-                [0.5, 0.5, 0.5, 1.0].as_ptr(),
-                &[],
-            );
-
-            let target_desc = target.resource.GetDesc();
+            frame
+                .command_list
+                .ClearRenderTargetView(target.rtv, Self::CLEAR_COLOR.as_ptr(), &[]);
 
             let constants =
-                ShaderConstants::new(Extent::new(target_desc.Width as u32, target_desc.Height));
+                ShaderConstants::new(extent, Transform::identity().then(content.camera()));
 
             frame.command_list.RSSetViewports(&[D3D12_VIEWPORT {
                 TopLeftX: 0.0,
@@ -220,20 +592,24 @@ impl GraphicsContext {
                 MaxDepth: 1.0,
             }]);
 
-            frame.command_list.RSSetScissorRects(&[RECT {
-                left: 0,
-                top: 0,
-                right: constants.viewport.width.try_into().unwrap(),
-                bottom: constants.viewport.height.try_into().unwrap(),
-            }]);
+            let scissor = Rect::new(
+                Point::new(0, 0),
+                Point::new(constants.viewport.width, constants.viewport.height),
+            );
+            set_scissor(&frame.command_list, &scissor);
 
             self.record_render_graph(
-                &frame.command_list,
+                &mut frame,
                 content,
                 RenderGraphNodeId::root(),
-                &constants,
-                &imm_vertex_view,
-                &imm_index_view,
+                &DrawState {
+                    target: target.rtv,
+                    format: target.format,
+                    constants,
+                    scissor,
+                    layer: 0,
+                },
+                &immediate,
             );
 
             frame.command_list.ResourceBarrier(&[transition_barrier(
@@ -249,6 +625,33 @@ impl GraphicsContext {
 
         let fence_value = self.submit_frame(frame, frame_marker);
         target.last_use.set(fence_value);
+        Ok(())
+    }
+
+    pub fn upload_statistics(&self) -> UploadStatistics {
+        self.upload_allocator.statistics()
+    }
+
+    /// Copies a render graph's immediate-mode vertices and indices into upload
+    /// memory for the next frame.
+    fn upload_render_graph(
+        &mut self,
+        content: &RenderGraph,
+    ) -> Result<(FrameMarker, ImmediateBuffers), DrawError> {
+        let mut frame_alloc = self.upload_allocator.begin_frame();
+
+        let immediate = ImmediateBuffers {
+            vertices: upload(&mut frame_alloc, &content.imm_vertices)?.vertex_view(),
+            indices: upload_indices(&mut frame_alloc, &content.imm_indices)?,
+            textured_vertices: upload(&mut frame_alloc, &content.img_vertices)?.vertex_view(),
+            textured_indices: upload_indices(&mut frame_alloc, &content.img_indices)?,
+        };
+
+        Ok((frame_alloc.finish(), immediate))
+    }
+
+    pub fn read_pixels(&mut self, image: &Image) -> Vec<Color> {
+        read_back(&self.dx, &self.graphics_queue, image)
     }
 
     fn begin_frame(&mut self) -> Frame {
@@ -276,6 +679,9 @@ impl GraphicsContext {
                 barriers: SmallVec::new(),
                 command_list,
                 command_allocator: allocator,
+                layers: Vec::new(),
+                textures: Vec::new(),
+                meshes: Vec::new(),
             }
         })
     }
@@ -319,6 +725,8 @@ impl GraphicsContext {
             }
 
             self.upload_allocator.free_frame(frame_marker);
+            frame.textures.clear();
+            frame.meshes.clear();
 
             unsafe {
                 frame.command_allocator.Reset().unwrap();
@@ -333,44 +741,177 @@ impl GraphicsContext {
 
     fn record_render_graph(
         &self,
-        command_list: &ID3D12GraphicsCommandList,
+        frame: &mut Frame,
         content: &RenderGraph,
         node: RenderGraphNodeId,
-        constants: &ShaderConstants,
-        imm_vertex_view: &D3D12_VERTEX_BUFFER_VIEW,
-        imm_index_view: &D3D12_INDEX_BUFFER_VIEW,
+        state: &DrawState,
+        immediate: &ImmediateBuffers,
     ) {
-        use crate::render_graph::RenderGraphCommand;
+        use crate::render_graph::{pixel_bounds, RenderGraphCommand};
+
+        let command_list = frame.command_list.clone();
+        let mut inner = *state;
 
         match content.get(node) {
             RenderGraphCommand::Root => assert_eq!(node, RenderGraphNodeId::root()),
+            RenderGraphCommand::Group => {}
             RenderGraphCommand::DrawImmediate {
                 first_index,
                 num_indices,
             } => unsafe {
-                self.ui_shader.bind(command_list, constants);
-                command_list.IASetVertexBuffers(0, Some(&[*imm_vertex_view]));
-                command_list.IASetIndexBuffer(Some(imm_index_view));
-
-                command_list.DrawIndexedInstanced(
-                    u32::from(*num_indices),
-                    1,
-                    u32::from(*first_index),
-                    0,
-                    0,
-                );
+                self.ui_shader
+                    .bind(&command_list, state.format, &state.constants);
+                command_list.IASetVertexBuffers(0, Some(&[immediate.vertices]));
+                command_list.IASetIndexBuffer(Some(&immediate.indices));
+
+                command_list.DrawIndexedInstanced(*num_indices, 1, *first_index, 0, 0);
             },
+            RenderGraphCommand::DrawMesh { mesh } => {
+                let mesh = &content.meshes[*mesh as usize].inner;
+
+                self.ui_shader
+                    .bind(&command_list, state.format, &state.constants);
+                unsafe {
+                    command_list.IASetVertexBuffers(0, Some(&[mesh.vertices]));
+                    command_list.IASetIndexBuffer(Some(&mesh.indices));
+                    command_list.DrawIndexedInstanced(mesh.num_indices, 1, 0, 0, 0);
+                }
+
+                frame.meshes.push(mesh.clone());
+            }
+            RenderGraphCommand::DrawImage {
+                texture,
+                filter,
+                first_index,
+                num_indices,
+            } => {
+                let texture = &content.textures[*texture as usize].inner;
+
+                self.image_shader.bind(
+                    &command_list,
+                    state.format,
+                    *filter,
+                    &state.constants,
+                    texture,
+                    [1.0, 1.0],
+                );
+
+                unsafe {
+                    command_list.IASetVertexBuffers(0, Some(&[immediate.textured_vertices]));
+                    command_list.IASetIndexBuffer(Some(&immediate.textured_indices));
+                    command_list.DrawIndexedInstanced(*num_indices, 1, *first_index, 0, 0);
+                }
+
+                frame.textures.push(texture.clone());
+            }
+            RenderGraphCommand::DrawGlyphRun {
+                cache,
+                first_index,
+                num_indices,
+            } => {
+                let texture = content.glyph_caches[*cache as usize]
+                    .cache()
+                    .texture()
+                    .inner;
+
+                // Glyph runs have texture coordinates in texels.
+                let extent = texture.extent();
+                self.image_shader.bind(
+                    &command_list,
+                    state.format,
+                    Filter::Linear,
+                    &state.constants,
+                    &texture,
+                    [1.0 / extent.width as f32, 1.0 / extent.height as f32],
+                );
+
+                unsafe {
+                    command_list.IASetVertexBuffers(0, Some(&[immediate.textured_vertices]));
+                    command_list.IASetIndexBuffer(Some(&immediate.textured_indices));
+                    command_list.DrawIndexedInstanced(*num_indices, 1, *first_index, 0, 0);
+                }
+
+                frame.textures.push(texture);
+            }
+            RenderGraphCommand::Transform { transform } => {
+                inner.constants.transform =
+                    transform.then(&state.constants.transform.with_src().with_dst());
+            }
+            RenderGraphCommand::Clip { rect } => {
+                let Some(clip) = pixel_bounds(&state.constants.transform.transform_rect(rect))
+                    .intersection(&state.scissor)
+                else {
+                    // Nothing inside the clip can be visible.
+                    return;
+                };
+                inner.scissor = clip;
+                set_scissor(&command_list, &inner.scissor);
+            }
+            RenderGraphCommand::Layer {
+                opacity,
+                blend_mode,
+            } => {
+                // Layers nested at the same depth take turns with the same
+                // image, which is back in the common state after each use.
+                let layer = &frame.layers[state.layer];
+                let resource = layer.image.resource.clone();
+                let srv_heap = layer.srv_heap.clone();
+                inner.target = layer.image.rtv;
+                inner.layer = state.layer + 1;
+
+                unsafe {
+                    command_list.ResourceBarrier(&[transition_barrier(
+                        &resource,
+                        D3D12_RESOURCE_STATE_COMMON,
+                        D3D12_RESOURCE_STATE_RENDER_TARGET,
+                    )]);
+                    command_list.OMSetRenderTargets(1, Some(&inner.target), false, None);
+                    command_list.ClearRenderTargetView(
+                        inner.target,
+                        Self::LAYER_CLEAR_COLOR.as_ptr(),
+                        &[],
+                    );
+                }
+
+                for child in content.iter_children(node) {
+                    self.record_render_graph(frame, content, child, &inner, immediate);
+                }
+
+                unsafe {
+                    command_list.ResourceBarrier(&[transition_barrier(
+                        &resource,
+                        D3D12_RESOURCE_STATE_RENDER_TARGET,
+                        D3D12_RESOURCE_STATE_PIXEL_SHADER_RESOURCE,
+                    )]);
+                    command_list.OMSetRenderTargets(1, Some(&state.target), false, None);
+                }
+
+                self.composite_shader.draw(
+                    &self.dx,
+                    &command_list,
+                    state.format,
+                    *blend_mode,
+                    *opacity,
+                    &srv_heap,
+                );
+
+                unsafe {
+                    command_list.ResourceBarrier(&[transition_barrier(
+                        &resource,
+                        D3D12_RESOURCE_STATE_PIXEL_SHADER_RESOURCE,
+                        D3D12_RESOURCE_STATE_COMMON,
+                    )]);
+                }
+                return;
+            }
         }
 
         for child in content.iter_children(node) {
-            self.record_render_graph(
-                command_list,
-                content,
-                child,
-                constants,
-                imm_vertex_view,
-                imm_index_view,
-            );
+            self.record_render_graph(frame, content, child, &inner, immediate);
+        }
+
+        if inner.scissor != state.scissor {
+            set_scissor(&command_list, &state.scissor);
         }
     }
 }
@@ -383,8 +924,292 @@ impl Drop for GraphicsContext {
 
 pub struct Image {
     resource: ID3D12Resource,
+    format: ImageFormat,
     last_use: Cell<u64>,
     rtv: D3D12_CPU_DESCRIPTOR_HANDLE,
+    /// Offscreen images own the descriptor heap that `rtv` lives in. Swapchain
+    /// images share their surface's heap.
+    _rtv_heap: Option<ID3D12DescriptorHeap>,
+}
+
+impl Image {
+    pub fn extent(&self) -> Extent<u32, ScreenSpace> {
+        let desc = unsafe { self.resource.GetDesc() };
+        Extent::new(desc.Width as u32, desc.Height)
+    }
+
+    pub fn format(&self) -> ImageFormat {
+        self.format
+    }
+}
+
+/// An immutable RGBA8 texture that can be sampled by the image shader.
+pub struct Texture {
+    resource: ID3D12Resource,
+    /// A shader-visible descriptor heap holding the texture's SRV.
+    srv_heap: ID3D12DescriptorHeap,
+}
+
+impl Texture {
+    pub fn extent(&self) -> Extent<u32, ScreenSpace> {
+        let desc = unsafe { self.resource.GetDesc() };
+        Extent::new(desc.Width as u32, desc.Height)
+    }
+}
+
+/// An immutable mesh in device memory that can be drawn by the polygon shader.
+pub struct Mesh {
+    vertices: D3D12_VERTEX_BUFFER_VIEW,
+    indices: D3D12_INDEX_BUFFER_VIEW,
+    num_vertices: usize,
+    num_indices: u32,
+    /// The buffer that the views point into.
+    _buffer: ID3D12Resource,
+}
+
+impl Mesh {
+    pub fn num_vertices(&self) -> usize {
+        self.num_vertices
+    }
+
+    pub fn num_indices(&self) -> usize {
+        self.num_indices as usize
+    }
+}
+
+/// Copies the contents of an image back to the CPU, blocking until the copy
+/// and any pending draws to the image have completed.
+fn read_back(dx: &dx::Interfaces, queue: &RefCell<graphics::Queue>, image: &Image) -> Vec<Color> {
+    copy_to_readback(dx, queue, image).read(queue)
+}
+
+/// A copy of an image in CPU-visible memory, which can be read once the
+/// queue has finished making it.
+struct Readback {
+    buffer: ID3D12Resource,
+    footprint: D3D12_PLACED_SUBRESOURCE_FOOTPRINT,
+    num_rows: u32,
+    total_size: u64,
+    extent: Extent<u32, ScreenSpace>,
+    format: ImageFormat,
+    fence_value: u64,
+}
+
+/// Submits a copy of an image into a readback buffer, after any pending
+/// draws to the image. The image must be in the present state.
+fn copy_to_readback(
+    dx: &dx::Interfaces,
+    queue: &RefCell<graphics::Queue>,
+    image: &Image,
+) -> Readback {
+    let desc = unsafe { image.resource.GetDesc() };
+
+    let mut footprint = D3D12_PLACED_SUBRESOURCE_FOOTPRINT::default();
+    let mut num_rows = 0;
+    let mut total_size = 0;
+    unsafe {
+        dx.device.GetCopyableFootprints(
+            &desc,
+            0,
+            1,
+            0,
+            Some(&mut footprint),
+            Some(&mut num_rows),
+            None,
+            Some(&mut total_size),
+        );
+    }
+
+    let readback_buffer: ID3D12Resource = unsafe {
+        let mut buffer = None;
+        dx.device
+            .CreateCommittedResource(
+                &D3D12_HEAP_PROPERTIES {
+                    Type: D3D12_HEAP_TYPE_READBACK,
+                    CPUPageProperty: D3D12_CPU_PAGE_PROPERTY_UNKNOWN,
+                    MemoryPoolPreference: D3D12_MEMORY_POOL_UNKNOWN,
+                    CreationNodeMask: 0,
+                    VisibleNodeMask: 0,
+                },
+                D3D12_HEAP_FLAG_NONE,
+                &D3D12_RESOURCE_DESC {
+                    Dimension: D3D12_RESOURCE_DIMENSION_BUFFER,
+                    Alignment: 0,
+                    Width: total_size,
+                    Height: 1,
+                    DepthOrArraySize: 1,
+                    MipLevels: 1,
+                    Format: DXGI_FORMAT_UNKNOWN,
+                    SampleDesc: DXGI_SAMPLE_DESC {
+                        Count: 1,
+                        Quality: 0,
+                    },
+                    Layout: D3D12_TEXTURE_LAYOUT_ROW_MAJOR,
+                    Flags: D3D12_RESOURCE_FLAG_NONE,
+                },
+                D3D12_RESOURCE_STATE_COPY_DEST,
+                None,
+                &mut buffer,
+            )
+            .unwrap();
+        buffer.unwrap()
+    };
+
+    // Readbacks are rare and block anyway, so they record into their own
+    // command list rather than one of the context's frames.
+    let command_allocator: ID3D12CommandAllocator = unsafe {
+        dx.device
+            .CreateCommandAllocator(D3D12_COMMAND_LIST_TYPE_DIRECT)
+    }
+    .unwrap();
+    let command_list: ID3D12GraphicsCommandList = unsafe {
+        dx.device
+            .CreateCommandList(0, D3D12_COMMAND_LIST_TYPE_DIRECT, &command_allocator, None)
+    }
+    .unwrap();
+
+    unsafe {
+        command_list.ResourceBarrier(&[transition_barrier(
+            &image.resource,
+            D3D12_RESOURCE_STATE_PRESENT,
+            D3D12_RESOURCE_STATE_COPY_SOURCE,
+        )]);
+
+        command_list.CopyTextureRegion(
+            &D3D12_TEXTURE_COPY_LOCATION {
+                pResource: windows::core::ManuallyDrop::new(&readback_buffer),
+                Type: D3D12_TEXTURE_COPY_TYPE_PLACED_FOOTPRINT,
+                Anonymous: D3D12_TEXTURE_COPY_LOCATION_0 {
+                    PlacedFootprint: footprint,
+                },
+            },
+            0,
+            0,
+            0,
+            &D3D12_TEXTURE_COPY_LOCATION {
+                pResource: windows::core::ManuallyDrop::new(&image.resource),
+                Type: D3D12_TEXTURE_COPY_TYPE_SUBRESOURCE_INDEX,
+                Anonymous: D3D12_TEXTURE_COPY_LOCATION_0 {
+                    SubresourceIndex: 0,
+                },
+            },
+            None,
+        );
+
+        command_list.ResourceBarrier(&[transition_barrier(
+            &image.resource,
+            D3D12_RESOURCE_STATE_COPY_SOURCE,
+            D3D12_RESOURCE_STATE_PRESENT,
+        )]);
+    }
+
+    let fence_value = queue.borrow_mut().submit(&command_list);
+    image.last_use.set(fence_value);
+
+    Readback {
+        buffer: readback_buffer,
+        footprint,
+        num_rows,
+        total_size,
+        extent: image.extent(),
+        format: image.format,
+        fence_value,
+    }
+}
+
+impl Readback {
+    /// Converts the copy to colors, blocking until it has been made.
+    fn read(&self, queue: &RefCell<graphics::Queue>) -> Vec<Color> {
+        queue.borrow().wait_until(self.fence_value);
+
+        let bytes: &[u8] = unsafe {
+            let mut ptr = std::ptr::null_mut();
+            self.buffer
+                .Map(
+                    0,
+                    Some(&D3D12_RANGE {
+                        Begin: 0,
+                        End: self.total_size as usize,
+                    }),
+                    Some(&mut ptr),
+                )
+                .unwrap();
+            std::slice::from_raw_parts(ptr.cast(), self.total_size as usize)
+        };
+
+        let width = self.extent.width as usize;
+        let row_pitch = self.footprint.Footprint.RowPitch as usize;
+
+        let mut pixels = Vec::with_capacity(width * self.num_rows as usize);
+        for row in bytes.chunks(row_pitch).take(self.num_rows as usize) {
+            match self.format {
+                ImageFormat::Rgba8 => {
+                    pixels.extend(row[..width * 4].chunks_exact(4).map(|p| {
+                        Color::new(
+                            f32::from(p[0]) / 255.0,
+                            f32::from(p[1]) / 255.0,
+                            f32::from(p[2]) / 255.0,
+                            f32::from(p[3]) / 255.0,
+                        )
+                    }));
+                }
+                ImageFormat::Rgba16Float => {
+                    pixels.extend(row[..width * 8].chunks_exact(8).map(|p| {
+                        let channel = |i: usize| f16_to_f32(u16::from_le_bytes([p[i], p[i + 1]]));
+                        Color::new(channel(0), channel(2), channel(4), channel(6))
+                    }));
+                }
+            }
+        }
+
+        unsafe {
+            self.buffer
+                .Unmap(0, Some(&D3D12_RANGE { Begin: 0, End: 0 }))
+        };
+
+        pixels
+    }
+}
+
+fn blend_factor(factor: BlendFactor) -> D3D12_BLEND {
+    match factor {
+        BlendFactor::Zero => D3D12_BLEND_ZERO,
+        BlendFactor::One => D3D12_BLEND_ONE,
+        BlendFactor::InvSrcColor => D3D12_BLEND_INV_SRC_COLOR,
+        BlendFactor::SrcAlpha => D3D12_BLEND_SRC_ALPHA,
+        BlendFactor::InvSrcAlpha => D3D12_BLEND_INV_SRC_ALPHA,
+        BlendFactor::DstColor => D3D12_BLEND_DEST_COLOR,
+        BlendFactor::DstAlpha => D3D12_BLEND_DEST_ALPHA,
+        BlendFactor::InvDstAlpha => D3D12_BLEND_INV_DEST_ALPHA,
+    }
+}
+
+fn dxgi_format(format: ImageFormat) -> DXGI_FORMAT {
+    match format {
+        ImageFormat::Rgba8 => DXGI_FORMAT_R8G8B8A8_UNORM,
+        ImageFormat::Rgba16Float => DXGI_FORMAT_R16G16B16A16_FLOAT,
+    }
+}
+
+/// Converts an IEEE 754 half-precision float to single-precision.
+fn f16_to_f32(bits: u16) -> f32 {
+    let sign = u32::from(bits & 0x8000) << 16;
+    let exponent = u32::from((bits >> 10) & 0x1F);
+    let mantissa = u32::from(bits & 0x3FF);
+
+    let bits = match (exponent, mantissa) {
+        (0, 0) => sign,
+        (0, _) => {
+            // Subnormal: renormalize the mantissa.
+            let shift = mantissa.leading_zeros() - 21;
+            let mantissa = (mantissa << shift) & 0x3FF;
+            sign | ((113 - shift) << 23) | (mantissa << 13)
+        }
+        (0x1F, _) => sign | 0x7F80_0000 | (mantissa << 13),
+        _ => sign | ((exponent + 112) << 23) | (mantissa << 13),
+    };
+
+    f32::from_bits(bits)
 }
 
 fn transition_barrier(
@@ -406,21 +1231,244 @@ fn transition_barrier(
     }
 }
 
+/// The state that a render graph node inherits from its ancestors.
+#[derive(Clone, Copy)]
+struct DrawState {
+    /// The render target that draws are recorded into.
+    target: D3D12_CPU_DESCRIPTOR_HANDLE,
+    format: ImageFormat,
+    constants: ShaderConstants,
+    scissor: Rect<u32, ScreenSpace>,
+    /// The index of the frame's layer image that the next layer node draws
+    /// into.
+    layer: usize,
+}
+
+/// An intermediate image for a layer, with a shader-visible descriptor that
+/// the composite shader reads it through.
+struct LayerImage {
+    image: Image,
+    srv_heap: ID3D12DescriptorHeap,
+}
+
+/// The deepest nesting of layer nodes at or below `node`, which is the number
+/// of layer images needed to draw it.
+fn layer_depth(content: &RenderGraph, node: RenderGraphNodeId) -> usize {
+    use crate::render_graph::RenderGraphCommand;
+
+    let depth = content
+        .iter_children(node)
+        .map(|child| layer_depth(content, child))
+        .max()
+        .unwrap_or(0);
+    depth
+        + usize::from(matches!(
+            content.get(node),
+            RenderGraphCommand::Layer { .. }
+        ))
+}
+
+/// The upload memory ranges holding a render graph's immediate-mode vertices
+/// and indices.
+struct ImmediateBuffers {
+    vertices: D3D12_VERTEX_BUFFER_VIEW,
+    indices: D3D12_INDEX_BUFFER_VIEW,
+    textured_vertices: D3D12_VERTEX_BUFFER_VIEW,
+    textured_indices: D3D12_INDEX_BUFFER_VIEW,
+}
+
+/// A range of upload memory holding a copy of a slice.
+struct UploadedSlice {
+    address: u64,
+    size: u32,
+    stride: u32,
+}
+
+impl UploadedSlice {
+    fn vertex_view(&self) -> D3D12_VERTEX_BUFFER_VIEW {
+        D3D12_VERTEX_BUFFER_VIEW {
+            BufferLocation: self.address,
+            SizeInBytes: self.size,
+            StrideInBytes: self.stride,
+        }
+    }
+
+    fn index_view(&self, format: DXGI_FORMAT) -> D3D12_INDEX_BUFFER_VIEW {
+        D3D12_INDEX_BUFFER_VIEW {
+            BufferLocation: self.address,
+            SizeInBytes: self.size,
+            Format: format,
+        }
+    }
+}
+
+/// Copies a render graph's indices into upload memory, in whichever width
+/// the graph stores them.
+fn upload_indices(
+    frame_alloc: &mut temp_allocator::FrameAllocator<UploadChunk>,
+    indices: &IndexBuffer,
+) -> Result<D3D12_INDEX_BUFFER_VIEW, DrawError> {
+    Ok(match indices {
+        IndexBuffer::U16(indices) => upload(frame_alloc, indices)?.index_view(DXGI_FORMAT_R16_UINT),
+        IndexBuffer::U32(indices) => upload(frame_alloc, indices)?.index_view(DXGI_FORMAT_R32_UINT),
+    })
+}
+
+/// Copies `data` into upload memory for the frame being recorded.
+fn upload<T: Copy>(
+    frame_alloc: &mut temp_allocator::FrameAllocator<UploadChunk>,
+    data: &[T],
+) -> Result<UploadedSlice, DrawError> {
+    let (memory, chunk) = frame_alloc
+        .allocate(
+            std::mem::size_of_val(data) as u64,
+            std::mem::align_of::<T>() as u64,
+        )
+        .map_err(|temp_allocator::Error::OutOfMemory| DrawError::OutOfMemory)?;
+
+    unsafe {
+        std::slice::from_raw_parts_mut(
+            chunk.ptr.add(memory.heap_offset as usize).cast(),
+            data.len(),
+        )
+        .copy_from_slice(data);
+    }
+
+    Ok(UploadedSlice {
+        address: chunk.address + memory.heap_offset,
+        size: memory.size as u32,
+        stride: std::mem::size_of::<T>() as u32,
+    })
+}
+
+/// A persistently mapped upload heap buffer that the upload allocator hands
+/// out memory from.
+struct UploadChunk {
+    ptr: *mut std::ffi::c_void,
+    address: u64,
+    _buffer: ID3D12Resource,
+}
+
+impl UploadChunk {
+    /// Returns `None` if the buffer could not be created, such as when the
+    /// device is out of memory.
+    fn new(dx: &dx::Interfaces, size: u64) -> Option<Self> {
+        let buffer = create_buffer(
+            dx,
+            D3D12_HEAP_TYPE_UPLOAD,
+            size,
+            D3D12_RESOURCE_STATE_GENERIC_READ,
+        )
+        .ok()?;
+
+        let ptr = unsafe {
+            let mut ptr = std::ptr::null_mut();
+            buffer
+                .Map(0, Some(&D3D12_RANGE { Begin: 0, End: 0 }), Some(&mut ptr))
+                .ok()?;
+            ptr
+        };
+
+        Some(Self {
+            ptr,
+            address: unsafe { buffer.GetGPUVirtualAddress() },
+            _buffer: buffer,
+        })
+    }
+}
+
+fn create_buffer(
+    dx: &dx::Interfaces,
+    heap_type: D3D12_HEAP_TYPE,
+    size: u64,
+    state: D3D12_RESOURCE_STATES,
+) -> windows::core::Result<ID3D12Resource> {
+    let mut buffer = None;
+    unsafe {
+        dx.device.CreateCommittedResource(
+            &D3D12_HEAP_PROPERTIES {
+                Type: heap_type,
+                CPUPageProperty: D3D12_CPU_PAGE_PROPERTY_UNKNOWN,
+                MemoryPoolPreference: D3D12_MEMORY_POOL_UNKNOWN,
+                CreationNodeMask: 0,
+                VisibleNodeMask: 0,
+            },
+            D3D12_HEAP_FLAG_NONE,
+            &D3D12_RESOURCE_DESC {
+                Dimension: D3D12_RESOURCE_DIMENSION_BUFFER,
+                Alignment: 0, // default: 64k
+                Width: size,
+                Height: 1,
+                DepthOrArraySize: 1,
+                MipLevels: 1,
+                Format: DXGI_FORMAT_UNKNOWN,
+                SampleDesc: DXGI_SAMPLE_DESC {
+                    Count: 1,
+                    Quality: 0,
+                },
+                Layout: D3D12_TEXTURE_LAYOUT_ROW_MAJOR,
+                Flags: D3D12_RESOURCE_FLAG_NONE,
+            },
+            state,
+            None,
+            &mut buffer,
+        )?;
+    }
+    Ok(buffer.unwrap())
+}
+
+/// Views a slice of plain data, such as vertices or indices, as bytes.
+fn as_bytes<T: Copy>(data: &[T]) -> &[u8] {
+    unsafe { std::slice::from_raw_parts(data.as_ptr().cast(), std::mem::size_of_val(data)) }
+}
+
+fn set_scissor(command_list: &ID3D12GraphicsCommandList, rect: &Rect<u32, ScreenSpace>) {
+    unsafe {
+        command_list.RSSetScissorRects(&[RECT {
+            left: rect.p0.x.try_into().unwrap(),
+            top: rect.p0.y.try_into().unwrap(),
+            right: rect.p1.x.try_into().unwrap(),
+            bottom: rect.p1.y.try_into().unwrap(),
+        }]);
+    }
+}
+
+/// Root constants for the polygon and image shaders. Must match
+/// `DrawConstants` in `polygon.hlsl` and `image.hlsl`.
+#[derive(Clone, Copy)]
 struct ShaderConstants {
     viewport: Extent<u32, ScreenSpace>,
+    transform: Transform<f32, ObjectSpace, ScreenSpace>,
 }
 
 impl ShaderConstants {
-    fn new(viewport: Extent<u32, ScreenSpace>) -> Self {
-        Self { viewport }
+    fn new(
+        viewport: Extent<u32, ScreenSpace>,
+        transform: Transform<f32, ObjectSpace, ScreenSpace>,
+    ) -> Self {
+        Self {
+            viewport,
+            transform,
+        }
     }
 
     fn write(&self, command_list: &ID3D12GraphicsCommandList) {
+        let constants = [
+            self.viewport.width,
+            self.viewport.height,
+            self.transform.m11.to_bits(),
+            self.transform.m12.to_bits(),
+            self.transform.m21.to_bits(),
+            self.transform.m22.to_bits(),
+            self.transform.m31.to_bits(),
+            self.transform.m32.to_bits(),
+        ];
+
         unsafe {
             command_list.SetGraphicsRoot32BitConstants(
                 0,
-                2,
-                [self.viewport.width, self.viewport.height].as_ptr().cast(),
+                constants.len() as u32,
+                constants.as_ptr().cast(),
                 0,
             );
         }
@@ -428,7 +1476,8 @@ impl ShaderConstants {
 }
 
 struct Polygon {
-    shader: Shader,
+    rgba8: Shader,
+    rgba16_float: Shader,
 }
 
 impl Polygon {
@@ -458,25 +1507,189 @@ impl Polygon {
             },
         ];
 
-        let shader = Shader::new(
-            dx,
-            Self::UI_VERTEX_SHADER,
-            Self::UI_PIXEL_SHADER,
-            None,
-            DXGI_FORMAT_R16G16B16A16_FLOAT,
-            &input_elements,
-        );
+        // Pipelines are specific to the render target format, so one is needed
+        // for every image format.
+        let pipeline = |format| {
+            Shader::new(
+                dx,
+                Self::UI_VERTEX_SHADER,
+                Self::UI_PIXEL_SHADER,
+                None,
+                dxgi_format(format),
+                BlendMode::Normal,
+                &input_elements,
+            )
+        };
 
-        Self { shader }
+        Self {
+            rgba8: pipeline(ImageFormat::Rgba8),
+            rgba16_float: pipeline(ImageFormat::Rgba16Float),
+        }
     }
 
-    fn bind(&self, command_list: &ID3D12GraphicsCommandList, constants: &ShaderConstants) {
-        self.shader.bind(command_list);
+    fn bind(
+        &self,
+        command_list: &ID3D12GraphicsCommandList,
+        format: ImageFormat,
+        constants: &ShaderConstants,
+    ) {
+        match format {
+            ImageFormat::Rgba8 => self.rgba8.bind(command_list),
+            ImageFormat::Rgba16Float => self.rgba16_float.bind(command_list),
+        }
         constants.write(command_list);
         unsafe { command_list.IASetPrimitiveTopology(D3D_PRIMITIVE_TOPOLOGY_TRIANGLELIST) };
     }
 }
 
+/// Draws textured triangles, with one pipeline for every combination of image
+/// format and filter.
+struct Textured {
+    pipelines: HashMap<(ImageFormat, Filter), Shader>,
+}
+
+impl Textured {
+    const VERTEX_SHADER: &[u8] = include_bytes!(concat!(env!("OUT_DIR"), "/image_vs.cso"));
+    const NEAREST_PIXEL_SHADER: &[u8] =
+        include_bytes!(concat!(env!("OUT_DIR"), "/image_nearest_ps.cso"));
+    const LINEAR_PIXEL_SHADER: &[u8] =
+        include_bytes!(concat!(env!("OUT_DIR"), "/image_linear_ps.cso"));
+
+    fn new(dx: &dx::Interfaces) -> Self {
+        let input_elements = [
+            D3D12_INPUT_ELEMENT_DESC {
+                SemanticName: s!("POSITION"),
+                SemanticIndex: 0,
+                Format: DXGI_FORMAT_R32G32_FLOAT,
+                InputSlot: 0,
+                AlignedByteOffset: 0,
+                InputSlotClass: D3D12_INPUT_CLASSIFICATION_PER_VERTEX_DATA,
+                InstanceDataStepRate: 0,
+            },
+            D3D12_INPUT_ELEMENT_DESC {
+                SemanticName: s!("TEXCOORD"),
+                SemanticIndex: 0,
+                Format: DXGI_FORMAT_R32G32_FLOAT,
+                InputSlot: 0,
+                AlignedByteOffset: D3D12_APPEND_ALIGNED_ELEMENT,
+                InputSlotClass: D3D12_INPUT_CLASSIFICATION_PER_VERTEX_DATA,
+                InstanceDataStepRate: 0,
+            },
+            D3D12_INPUT_ELEMENT_DESC {
+                SemanticName: s!("COLOR"),
+                SemanticIndex: 0,
+                Format: DXGI_FORMAT_R32G32B32A32_FLOAT,
+                InputSlot: 0,
+                AlignedByteOffset: D3D12_APPEND_ALIGNED_ELEMENT,
+                InputSlotClass: D3D12_INPUT_CLASSIFICATION_PER_VERTEX_DATA,
+                InstanceDataStepRate: 0,
+            },
+        ];
+
+        let mut pipelines = HashMap::new();
+        for format in [ImageFormat::Rgba8, ImageFormat::Rgba16Float] {
+            for filter in [Filter::Nearest, Filter::Linear] {
+                let pixel_shader = match filter {
+                    Filter::Nearest => Self::NEAREST_PIXEL_SHADER,
+                    Filter::Linear => Self::LINEAR_PIXEL_SHADER,
+                };
+
+                let shader = Shader::new(
+                    dx,
+                    Self::VERTEX_SHADER,
+                    pixel_shader,
+                    None,
+                    dxgi_format(format),
+                    BlendMode::Normal,
+                    &input_elements,
+                );
+                pipelines.insert((format, filter), shader);
+            }
+        }
+
+        Self { pipelines }
+    }
+
+    fn bind(
+        &self,
+        command_list: &ID3D12GraphicsCommandList,
+        format: ImageFormat,
+        filter: Filter,
+        constants: &ShaderConstants,
+        texture: &Texture,
+        uv_scale: [f32; 2],
+    ) {
+        self.pipelines[&(format, filter)].bind(command_list);
+        constants.write(command_list);
+
+        unsafe {
+            // `uv_scale` follows the constants shared with `polygon.hlsl`.
+            let uv_scale = uv_scale.map(f32::to_bits);
+            command_list.SetGraphicsRoot32BitConstants(0, 2, uv_scale.as_ptr().cast(), 8);
+
+            command_list.SetDescriptorHeaps(&[Some(texture.srv_heap.clone())]);
+            command_list.SetGraphicsRootDescriptorTable(
+                1,
+                texture.srv_heap.GetGPUDescriptorHandleForHeapStart(),
+            );
+            command_list.IASetPrimitiveTopology(D3D_PRIMITIVE_TOPOLOGY_TRIANGLELIST);
+        }
+    }
+}
+
+/// Composites a layer's image over the render target. Pipelines are created
+/// on first use, as there is one for every combination of image format and
+/// blend mode.
+struct Composite {
+    pipelines: RefCell<HashMap<(ImageFormat, BlendMode), Shader>>,
+}
+
+impl Composite {
+    const VERTEX_SHADER: &[u8] = include_bytes!(concat!(env!("OUT_DIR"), "/composite_vs.cso"));
+    const PIXEL_SHADER: &[u8] = include_bytes!(concat!(env!("OUT_DIR"), "/composite_ps.cso"));
+
+    fn new() -> Self {
+        Self {
+            pipelines: RefCell::new(HashMap::new()),
+        }
+    }
+
+    fn draw(
+        &self,
+        dx: &dx::Interfaces,
+        command_list: &ID3D12GraphicsCommandList,
+        format: ImageFormat,
+        blend_mode: BlendMode,
+        opacity: f32,
+        layer: &ID3D12DescriptorHeap,
+    ) {
+        self.pipelines
+            .borrow_mut()
+            .entry((format, blend_mode))
+            .or_insert_with(|| {
+                Shader::new(
+                    dx,
+                    Self::VERTEX_SHADER,
+                    Self::PIXEL_SHADER,
+                    None,
+                    dxgi_format(format),
+                    blend_mode,
+                    &[],
+                )
+            })
+            .bind(command_list);
+
+        unsafe {
+            command_list.SetGraphicsRoot32BitConstants(0, 1, [opacity].as_ptr().cast(), 0);
+            command_list.SetDescriptorHeaps(&[Some(layer.clone())]);
+            command_list
+                .SetGraphicsRootDescriptorTable(1, layer.GetGPUDescriptorHandleForHeapStart());
+            command_list.IASetPrimitiveTopology(D3D_PRIMITIVE_TOPOLOGY_TRIANGLELIST);
+            command_list.DrawInstanced(3, 1, 0, 0);
+        }
+    }
+}
+
 struct Shader {
     root_signature: ID3D12RootSignature,
     pipeline_state: ID3D12PipelineState,
@@ -489,19 +1702,21 @@ impl Shader {
         pixel_shader: &[u8],
         geometry_shader: Option<&[u8]>,
         format: DXGI_FORMAT,
+        blend_mode: BlendMode,
         input: &[D3D12_INPUT_ELEMENT_DESC],
     ) -> Shader {
         let root_signature = unsafe { dx.device.CreateRootSignature(0, vertex_shader) }.unwrap();
 
+        let blend = blend_mode.equation();
         let mut blend_targets = [D3D12_RENDER_TARGET_BLEND_DESC::default(); 8];
         blend_targets[0] = D3D12_RENDER_TARGET_BLEND_DESC {
             BlendEnable: true.into(),
             LogicOpEnable: false.into(),
-            SrcBlend: D3D12_BLEND_ONE,
-            DestBlend: D3D12_BLEND_ZERO,
+            SrcBlend: blend_factor(blend.src_color),
+            DestBlend: blend_factor(blend.dst_color),
             BlendOp: D3D12_BLEND_OP_ADD,
-            SrcBlendAlpha: D3D12_BLEND_ONE,
-            DestBlendAlpha: D3D12_BLEND_ZERO,
+            SrcBlendAlpha: blend_factor(blend.src_alpha),
+            DestBlendAlpha: blend_factor(blend.dst_alpha),
             BlendOpAlpha: D3D12_BLEND_OP_ADD,
             LogicOp: D3D12_LOGIC_OP_NOOP,
             RenderTargetWriteMask: D3D12_COLOR_WRITE_ENABLE_ALL.0 as u8,
@@ -543,7 +1758,7 @@ impl Shader {
             SampleMask: u32::MAX,
             RasterizerState: D3D12_RASTERIZER_DESC {
                 FillMode: D3D12_FILL_MODE_SOLID,
-                CullMode: D3D12_CULL_MODE_BACK,
+                CullMode: D3D12_CULL_MODE_NONE,
                 FrontCounterClockwise: false.into(),
                 DepthBias: 0,
                 DepthBiasClamp: 0.0,
@@ -17,7 +17,10 @@ use windows::{
     },
 };
 
-use super::{dx, graphics, Image};
+use geometry::{Extent, ScreenSpace};
+
+use super::{copy_to_readback, dx, graphics, Image, Readback};
+use crate::{Color, ImageFormat};
 
 /// A `Surface` controls the acquisition and presentation of images to its
 /// associated window.
@@ -28,6 +31,11 @@ pub struct Surface {
     // Use swapchain3 for color space support
     swapchain: IDXGISwapChain3,
     image_index: u32,
+    /// Set by `request_capture` to copy the next image before it is
+    /// presented, as the swapchain discards its contents afterwards.
+    capture_requested: Cell<bool>,
+    /// The copy of the last image that was captured.
+    capture: RefCell<Option<Readback>>,
     frame_counter: Cell<u64>,
     render_targets: [Option<Image>; Surface::BUFFER_COUNT as usize],
     waitable_object: HANDLE,
@@ -108,6 +116,8 @@ impl Surface {
             flags,
             swapchain,
             image_index: 0,
+            capture_requested: Cell::new(false),
+            capture: RefCell::new(None),
             frame_counter: Cell::new(0),
             render_targets: [Some(a), Some(b)],
             waitable_object,
@@ -138,10 +148,26 @@ impl Surface {
         self.render_targets = [Some(a), Some(b)];
     }
 
+    /// Copies the next image to be presented before it is presented, so that
+    /// it can be read with `capture`.
+    pub fn request_capture(&self) {
+        self.capture_requested.set(true);
+    }
+
+    /// Reads the last image that was copied at the request of
+    /// `request_capture`.
+    ///
+    /// This function will block until the copy has completed.
+    pub fn capture(&self) -> Option<(Extent<u32, ScreenSpace>, Vec<Color>)> {
+        let capture = self.capture.borrow();
+        let capture = capture.as_ref()?;
+        Some((capture.extent, capture.read(&self.graphics_queue)))
+    }
+
     /// Retrieves the next image in the swap chain.
     ///
     /// This function will block until the next image is available.
-    pub fn get_next_image(&mut self) -> SurfaceImage {
+    pub fn get_next_image(&mut self) -> SurfaceImage<'_> {
         // block until the next image is available
         //
         // NOTE: should this instead be done just before presenting???
@@ -191,13 +217,17 @@ impl Surface {
             [
                 Image {
                     resource: buffer0,
+                    format: ImageFormat::Rgba16Float,
                     last_use: Cell::new(0),
                     rtv: rtv0,
+                    _rtv_heap: None,
                 },
                 Image {
                     resource: buffer1,
+                    format: ImageFormat::Rgba16Float,
                     last_use: Cell::new(0),
                     rtv: rtv1,
+                    _rtv_heap: None,
                 },
             ]
         }
@@ -224,6 +254,18 @@ impl SurfaceImage<'_> {
         self.surface
             .frame_counter
             .set(self.surface.frame_counter.get() + 1);
+
+        // With FLIP_DISCARD, the image's contents are undefined once it has
+        // been presented, so it is copied beforehand.
+        if self.surface.capture_requested.take() {
+            let readback = copy_to_readback(
+                &self.surface.dx,
+                &self.surface.graphics_queue,
+                self.get_image(),
+            );
+            *self.surface.capture.borrow_mut() = Some(readback);
+        }
+
         // We assume that the window is not typically in borderless fullscreen,
         // and so use a presentation interval of 1 (VSync).
         unsafe { self.surface.swapchain.Present(1, 0) }.unwrap();
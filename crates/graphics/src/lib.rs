@@ -34,12 +34,21 @@
 //!
 //! - 2022-12-19: Work begins after a few false starts.
 
-use std::cell::RefCell;
+use std::{cell::RefCell, rc::Rc};
 
-use geometry::Point;
+use geometry::{Extent, Point, ScreenSpace};
 use raw_window_handle::HasRawWindowHandle;
 
+mod bitmap;
 mod render_graph;
+mod stroker;
+mod svg;
+mod tessellator;
+// The allocator is only used by the DX12 backend, but is platform-independent
+// so that it is tested everywhere.
+#[cfg_attr(not(target_os = "windows"), allow(dead_code))]
+mod temp_allocator;
+mod text;
 
 #[cfg(target_os = "windows")]
 mod dx12;
@@ -47,9 +56,28 @@ mod dx12;
 #[cfg(target_os = "windows")]
 use dx12 as platform;
 
-pub use render_graph::{RenderGraph, RenderGraphNodeId};
+#[cfg(not(target_os = "windows"))]
+mod software;
 
-#[derive(Clone, Copy)]
+#[cfg(not(target_os = "windows"))]
+use software as platform;
+
+pub use bitmap::{Bitmap, BitmapError, BitmapFormat, ColorSpace};
+pub use render_graph::{
+    BatchStatistics, BlendMode, Filter, ImageOptions, RenderGraph, RenderGraphError,
+    RenderGraphNodeId, RenderGraphScope,
+};
+pub use stroker::{stroke_path, stroke_polylines, LineCap, LineJoin, StrokeOptions};
+pub use svg::{SvgDocument, SvgError};
+pub use tessellator::{fill_path, fill_polylines, FillOptions, FillRule, Tessellation};
+pub use text::{
+    Font, FontError, FontMetrics, GlyphBitmap, GlyphCache, GlyphId, GlyphMetrics, PositionedGlyph,
+    ShapedRun, ShapedText, TextAlign, TextLayout, TextLine, TextStyle,
+};
+
+/// An RGBA color with straight (not premultiplied) alpha. Colors are
+/// premultiplied when they are drawn, and images store premultiplied colors.
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub struct Color {
     pub r: f32,
     pub g: f32,
@@ -79,6 +107,13 @@ impl Color {
         a: 1.0,
     };
 
+    pub const WHITE: Self = Self {
+        r: 1.0,
+        g: 1.0,
+        b: 1.0,
+        a: 1.0,
+    };
+
     #[must_use]
     pub fn new(r: f32, g: f32, b: f32, a: f32) -> Self {
         Self { r, g, b, a }
@@ -91,6 +126,28 @@ pub struct Vertex {
     pub color: Color,
 }
 
+/// A vertex for drawing textures. The sampled texture color is multiplied by
+/// the vertex color.
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct TexturedVertex {
+    pub position: Point<f32>,
+    /// Texture coordinates, from (0, 0) in the top-left corner of the texture
+    /// to (1, 1) in the bottom-right corner.
+    pub uv: Point<f32>,
+    pub color: Color,
+}
+
+/// The pixel format of an image.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ImageFormat {
+    /// 8-bit unsigned normalized RGBA. Colors are stored as-is, without any
+    /// sRGB encoding.
+    Rgba8,
+    /// 16-bit floating point RGBA. This is the format used by window surfaces.
+    Rgba16Float,
+}
+
 #[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
 pub enum PowerPreference {
     #[default]
@@ -107,6 +164,32 @@ pub struct GraphicsConfig {
     pub power_preference: PowerPreference,
 }
 
+/// An error returned by [`GraphicsContext::draw`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DrawError {
+    /// Memory for copying the render graph's vertices and indices to the GPU,
+    /// or for the intermediate images that its layers are drawn into, could
+    /// not be allocated.
+    OutOfMemory,
+}
+
+/// Statistics about the memory that render graphs' vertices and indices are
+/// copied through on their way to the GPU. The memory is reused once the
+/// frames that used it have completed, and grows when a frame needs more than
+/// is free.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct UploadStatistics {
+    /// The total size of the memory, in bytes.
+    pub capacity: u64,
+    /// The number of bytes used by frames that have not completed.
+    pub bytes_in_use: u64,
+    /// The most bytes that have been in use at once. Memory only grows when
+    /// this exceeds its initial size.
+    pub peak_bytes_in_use: u64,
+    /// The number of times that memory was added because it was full.
+    pub num_grows: u64,
+}
+
 pub struct GraphicsContext {
     inner: RefCell<platform::GraphicsContext>,
 }
@@ -119,6 +202,13 @@ impl GraphicsContext {
         }
     }
 
+    /// Creates a surface that presents to a window.
+    ///
+    /// ## Panics
+    ///
+    /// Panics if the window is not a Win32 window, and always panics on
+    /// platforms other than Windows, where images are drawn in software and
+    /// cannot be presented.
     #[must_use]
     pub fn create_surface(&self, window: impl HasRawWindowHandle) -> Surface {
         Surface {
@@ -129,8 +219,133 @@ impl GraphicsContext {
         }
     }
 
-    pub fn draw(&self, target: &Image, content: &RenderGraph) {
-        self.inner.borrow_mut().draw(&target.inner, content);
+    /// Creates an offscreen image that can be drawn to and read back from, but
+    /// not presented.
+    #[must_use]
+    pub fn create_image(&self, extent: Extent<u32, ScreenSpace>, format: ImageFormat) -> Image {
+        Image {
+            inner: self.inner.borrow().create_image(extent, format),
+        }
+    }
+
+    /// Creates a texture from tightly packed rows of 8-bit RGBA pixels,
+    /// premultiplied by alpha, starting from the top-left corner.
+    ///
+    /// ## Panics
+    ///
+    /// Panics if `pixels` is not exactly `4 * width * height` bytes long.
+    #[must_use]
+    pub fn create_texture(&self, extent: Extent<u32, ScreenSpace>, pixels: &[u8]) -> Texture {
+        assert_eq!(
+            pixels.len(),
+            4 * extent.width as usize * extent.height as usize,
+            "texture data does not match its extent"
+        );
+
+        Texture {
+            inner: Rc::new(self.inner.borrow_mut().create_texture(extent, pixels)),
+        }
+    }
+
+    /// Replaces a region of a texture's pixels, in the same layout as
+    /// [`GraphicsContext::create_texture`]. Textures are immutable to users of
+    /// the crate, but glyph caches update their atlases as glyphs are added.
+    pub(crate) fn update_texture(
+        &self,
+        texture: &Texture,
+        origin: Point<u32, ScreenSpace>,
+        extent: Extent<u32, ScreenSpace>,
+        pixels: &[u8],
+    ) {
+        debug_assert_eq!(
+            pixels.len(),
+            4 * extent.width as usize * extent.height as usize
+        );
+
+        self.inner
+            .borrow_mut()
+            .update_texture(&texture.inner, origin, extent, pixels);
+    }
+
+    /// Creates a mesh whose vertices and indices are stored in device memory,
+    /// so that it can be drawn with [`RenderGraph::draw_mesh`] without being
+    /// copied every frame. Use this for large meshes that rarely change, such
+    /// as static backgrounds.
+    ///
+    /// This function will block until the mesh has been copied to the device.
+    ///
+    /// ## Panics
+    ///
+    /// Panics if an index is out of bounds, or if the number of indices is not
+    /// a multiple of 3. See [`GraphicsContext::try_create_mesh`].
+    #[must_use]
+    pub fn create_mesh(&self, vertices: &[Vertex], indices: &[u32]) -> MeshHandle {
+        self.try_create_mesh(vertices, indices)
+            .expect("failed to create mesh")
+    }
+
+    /// Like [`GraphicsContext::create_mesh`], but returns an error instead of
+    /// panicking.
+    ///
+    /// ## Errors
+    ///
+    /// Returns [`RenderGraphError::IndexOutOfBounds`] or
+    /// [`RenderGraphError::IncompleteTriangle`] if the indices do not describe
+    /// whole triangles of the mesh's vertices.
+    pub fn try_create_mesh(
+        &self,
+        vertices: &[Vertex],
+        indices: &[u32],
+    ) -> Result<MeshHandle, RenderGraphError> {
+        render_graph::check_mesh(vertices, indices)?;
+
+        Ok(MeshHandle {
+            inner: Rc::new(self.inner.borrow_mut().create_mesh(vertices, indices)),
+        })
+    }
+
+    /// Draws a render graph into `target`.
+    ///
+    /// ## Errors
+    ///
+    /// Returns [`DrawError::OutOfMemory`] if memory for the graph's vertices
+    /// and indices, or for its layers, could not be allocated, in which case
+    /// nothing is drawn.
+    pub fn draw(&self, target: &Image, content: &RenderGraph) -> Result<(), DrawError> {
+        for glyphs in &content.glyph_caches {
+            glyphs.cache().upload(self);
+        }
+
+        self.inner.borrow_mut().draw(&target.inner, content)
+    }
+
+    /// Statistics about the memory that render graphs' vertices and indices
+    /// are copied through when they are drawn, as of the last draw.
+    #[must_use]
+    pub fn upload_statistics(&self) -> UploadStatistics {
+        self.inner.borrow().upload_statistics()
+    }
+
+    /// Copies the contents of the image back to the CPU as an 8-bit bitmap,
+    /// which can be saved as a PNG. Colors in `Rgba16Float` images are linear,
+    /// so they are encoded as sRGB, after tone mapping those that are too
+    /// bright for 8 bits.
+    ///
+    /// This function will block until all pending draws to the image have
+    /// completed.
+    #[must_use]
+    pub fn capture(&self, image: &Image) -> Bitmap {
+        Bitmap::from_colors(image.extent(), image.format(), &self.read_pixels(image))
+    }
+
+    /// Copies the contents of the image back to the CPU, row by row from the
+    /// top-left corner. Colors are premultiplied by alpha.
+    ///
+    /// This function will block until all pending draws to the image have
+    /// completed.
+    #[must_use]
+    pub fn read_pixels(&self, image: &Image) -> Vec<Color> {
+        self.inner.borrow_mut().read_pixels(&image.inner)
     }
 }
 
@@ -142,7 +357,7 @@ impl Surface {
     /// Retrieves the next image from the surface's swapchain.
     ///
     /// This fucntion will block until the next image is available.
-    pub fn get_next_image(&mut self) -> SurfaceImage {
+    pub fn get_next_image(&mut self) -> SurfaceImage<'_> {
         SurfaceImage {
             inner: self.inner.get_next_image(),
         }
@@ -151,6 +366,29 @@ impl Surface {
     pub fn resize(&mut self) {
         self.inner.resize();
     }
+
+    /// Copies the next image that is presented, so that it can be read with
+    /// [`Surface::capture`]. The copy is made just before the image is
+    /// presented, as the swapchain's images are undefined afterwards.
+    pub fn request_capture(&self) {
+        self.inner.request_capture();
+    }
+
+    /// Reads the image that was last copied after a call to
+    /// [`Surface::request_capture`], as with [`GraphicsContext::capture`].
+    /// Returns `None` if no image has been copied yet.
+    ///
+    /// This function will block until the copy has completed.
+    #[must_use]
+    pub fn capture(&self) -> Option<Bitmap> {
+        let (extent, colors) = self.inner.capture()?;
+        // Surfaces are always `Rgba16Float`.
+        Some(Bitmap::from_colors(
+            extent,
+            ImageFormat::Rgba16Float,
+            &colors,
+        ))
+    }
 }
 
 pub struct SurfaceImage<'a> {
@@ -166,7 +404,7 @@ impl<'a> SurfaceImage<'a> {
     #[must_use]
     pub fn image(&self) -> &Image {
         // This is safe as long as Image remains repr(transparent).
-        unsafe { &*((self.inner.get_image() as *const dx12::Image).cast()) }
+        unsafe { &*((self.inner.get_image() as *const platform::Image).cast()) }
     }
 }
 
@@ -174,3 +412,54 @@ impl<'a> SurfaceImage<'a> {
 pub struct Image {
     inner: platform::Image,
 }
+
+impl Image {
+    #[must_use]
+    pub fn extent(&self) -> Extent<u32, ScreenSpace> {
+        self.inner.extent()
+    }
+
+    #[must_use]
+    pub fn format(&self) -> ImageFormat {
+        self.inner.format()
+    }
+}
+
+/// An immutable image that can be drawn with [`RenderGraph::draw_image`].
+/// Textures are reference counted, so cloning one is cheap.
+#[derive(Clone)]
+pub struct Texture {
+    inner: Rc<platform::Texture>,
+}
+
+impl Texture {
+    #[must_use]
+    pub fn extent(&self) -> Extent<u32, ScreenSpace> {
+        self.inner.extent()
+    }
+}
+
+/// A triangle mesh stored in device memory, created with
+/// [`GraphicsContext::create_mesh`]. Meshes are immutable and reference
+/// counted, so cloning one is cheap.
+///
+/// A mesh is destroyed when its last handle is dropped. Render graphs hold a
+/// handle to the meshes they draw, and the graphics context holds one until
+/// every submitted frame that draws the mesh has completed, so handles can be
+/// dropped at any time.
+#[derive(Clone)]
+pub struct MeshHandle {
+    inner: Rc<platform::Mesh>,
+}
+
+impl MeshHandle {
+    #[must_use]
+    pub fn num_vertices(&self) -> usize {
+        self.inner.num_vertices()
+    }
+
+    #[must_use]
+    pub fn num_indices(&self) -> usize {
+        self.inner.num_indices()
+    }
+}
@@ -1,23 +1,300 @@
-use crate::Vertex;
+use std::{collections::HashMap, rc::Rc};
+
+use geometry::{Insets, ObjectSpace, Point, Rect, ScreenSpace, Transform, WorldSpace};
+
+use crate::{
+    text::PinnedGlyphs, Color, Font, GlyphCache, MeshHandle, PositionedGlyph, Texture,
+    TexturedVertex, Vertex,
+};
 
 #[allow(clippy::module_name_repetitions)]
 #[repr(u16)]
 pub enum RenderGraphCommand {
     Root,
-    DrawImmediate { first_index: u16, num_indices: u16 },
+    /// Groups its children without affecting how they are drawn, so that they
+    /// can be placed and moved as a unit.
+    Group,
+    DrawImmediate {
+        first_index: u32,
+        num_indices: u32,
+    },
+    /// Draws a mesh that is stored in device memory. `mesh` indexes the
+    /// graph's meshes.
+    DrawMesh {
+        mesh: u32,
+    },
+    /// Transforms the contents of all descendants. Transforms nest, so the
+    /// world space of a transform node is the object space of its nearest
+    /// transformed ancestor.
+    Transform {
+        transform: Transform<f32, ObjectSpace, WorldSpace>,
+    },
+    /// Restricts the contents of all descendants to a rectangle, intersected
+    /// with the clips of its ancestors.
+    ///
+    /// Clips are applied in screen space, so a rectangle that is rotated by its
+    /// transform clips to its bounding box.
+    Clip {
+        rect: Rect<f32, ObjectSpace>,
+    },
+    /// Draws all descendants into a transparent intermediate image, then
+    /// composites it over the parent's contents with `opacity` and
+    /// `blend_mode`.
+    Layer {
+        opacity: f32,
+        blend_mode: BlendMode,
+    },
+    /// Draws a textured mesh. `texture` indexes the graph's textures, and the
+    /// indices refer to the graph's textured vertices.
+    DrawImage {
+        texture: u32,
+        filter: Filter,
+        first_index: u32,
+        num_indices: u32,
+    },
+    /// Draws a run of glyphs from a glyph cache's atlas. `cache` indexes the
+    /// graph's glyph caches, and the indices refer to the graph's textured
+    /// vertices, whose texture coordinates are in texels rather than
+    /// normalized, as the atlas may grow before the graph is drawn.
+    DrawGlyphRun {
+        cache: u32,
+        first_index: u32,
+        num_indices: u32,
+    },
+}
+
+/// How a texture is sampled between texel centers.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum Filter {
+    /// Uses the closest texel, keeping hard edges when scaled up. Suits pixel
+    /// art and images drawn at their native size.
+    Nearest,
+    /// Interpolates between the four closest texels.
+    #[default]
+    Linear,
+}
+
+/// Options for [`RenderGraph::draw_image`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ImageOptions {
+    pub filter: Filter,
+    /// Multiplies the texture's colors, for tinting or fading an image.
+    pub tint: Color,
+    /// Divides the image into a 3x3 grid, with borders of these sizes in
+    /// texels. The corners are drawn at their original size, the edges are
+    /// stretched along one axis, and only the center is stretched along both.
+    /// Borders shrink proportionally when the destination is too small for
+    /// them.
+    pub nine_slice: Option<Insets<f32>>,
+}
+
+impl Default for ImageOptions {
+    fn default() -> Self {
+        Self {
+            filter: Filter::default(),
+            tint: Color::WHITE,
+            nine_slice: None,
+        }
+    }
 }
 
+/// How a layer is combined with the contents beneath it.
+///
+/// Blending operates on colors premultiplied by alpha. In the descriptions
+/// below, `S` is the layer's color and `D` is the color beneath it.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum BlendMode {
+    /// `S` over `D`. This is the Porter-Duff source-over operator, and how
+    /// every draw is blended.
+    #[default]
+    Normal,
+    /// `S * D`, darkening the contents beneath. This is only exact where `D`
+    /// is opaque, as the layer fades out where `D` is transparent instead of
+    /// being drawn normally.
+    Multiply,
+    /// `S + D - S * D`, lightening the contents beneath.
+    Screen,
+    /// `S + D`, saturating in images that cannot store values above 1.
+    Additive,
+    /// Porter-Duff: neither, leaving transparency.
+    Clear,
+    /// Porter-Duff: `S` replaces `D`.
+    Source,
+    /// Porter-Duff: `D` is kept and `S` is discarded.
+    Destination,
+    /// Porter-Duff: `D` over `S`.
+    DestinationOver,
+    /// Porter-Duff: `S` where `D` is opaque.
+    SourceIn,
+    /// Porter-Duff: `D` where `S` is opaque.
+    DestinationIn,
+    /// Porter-Duff: `S` where `D` is transparent.
+    SourceOut,
+    /// Porter-Duff: `D` where `S` is transparent.
+    DestinationOut,
+    /// Porter-Duff: `S` where `D` is opaque, over `D`.
+    SourceAtop,
+    /// Porter-Duff: `D` where `S` is opaque, over `S`.
+    DestinationAtop,
+    /// Porter-Duff: `S` where `D` is transparent and `D` where `S` is
+    /// transparent.
+    Xor,
+}
+
+/// A factor in a blend equation.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum BlendFactor {
+    Zero,
+    One,
+    InvSrcColor,
+    SrcAlpha,
+    InvSrcAlpha,
+    DstColor,
+    DstAlpha,
+    InvDstAlpha,
+}
+
+/// The fixed-function blend equation `S * src + D * dst`, with separate
+/// factors for the color and alpha channels. Alpha factors never refer to
+/// color channels.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct BlendEquation {
+    pub src_color: BlendFactor,
+    pub dst_color: BlendFactor,
+    pub src_alpha: BlendFactor,
+    pub dst_alpha: BlendFactor,
+}
+
+impl BlendMode {
+    /// The blend equation implementing the mode. Every backend blends with
+    /// these equations so that they produce the same results.
+    pub(crate) fn equation(self) -> BlendEquation {
+        use BlendFactor::{
+            DstAlpha, DstColor, InvDstAlpha, InvSrcAlpha, InvSrcColor, One, SrcAlpha, Zero,
+        };
+
+        let (src_color, dst_color, src_alpha, dst_alpha) = match self {
+            Self::Normal => (One, InvSrcAlpha, One, InvSrcAlpha),
+            Self::Multiply => (DstColor, InvSrcAlpha, One, InvSrcAlpha),
+            Self::Screen => (One, InvSrcColor, One, InvSrcAlpha),
+            Self::Additive => (One, One, One, One),
+            Self::Clear => (Zero, Zero, Zero, Zero),
+            Self::Source => (One, Zero, One, Zero),
+            Self::Destination => (Zero, One, Zero, One),
+            Self::DestinationOver => (InvDstAlpha, One, InvDstAlpha, One),
+            Self::SourceIn => (DstAlpha, Zero, DstAlpha, Zero),
+            Self::DestinationIn => (Zero, SrcAlpha, Zero, SrcAlpha),
+            Self::SourceOut => (InvDstAlpha, Zero, InvDstAlpha, Zero),
+            Self::DestinationOut => (Zero, InvSrcAlpha, Zero, InvSrcAlpha),
+            Self::SourceAtop => (DstAlpha, InvSrcAlpha, DstAlpha, InvSrcAlpha),
+            Self::DestinationAtop => (InvDstAlpha, SrcAlpha, InvDstAlpha, SrcAlpha),
+            Self::Xor => (InvDstAlpha, InvSrcAlpha, InvDstAlpha, InvSrcAlpha),
+        };
+
+        BlendEquation {
+            src_color,
+            dst_color,
+            src_alpha,
+            dst_alpha,
+        }
+    }
+}
+
+/// An error returned by the checked drawing methods of [`RenderGraph`].
+#[allow(clippy::module_name_repetitions)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RenderGraphError {
+    /// An index refers to a vertex past the end of the mesh.
+    IndexOutOfBounds { index: u32, num_vertices: usize },
+    /// The number of indices is not a multiple of 3, so the last triangle is
+    /// incomplete.
+    IncompleteTriangle { num_indices: usize },
+    /// The graph already has as many nodes as 32-bit node IDs can address.
+    TooManyNodes,
+    /// The mesh would take the graph past the number of vertices that 32-bit
+    /// indices can address.
+    TooManyVertices,
+    /// The mesh would take the graph past `u32::MAX` indices.
+    TooManyIndices,
+}
+
+/// The number of draw calls that a graph takes to draw, before and after
+/// [`RenderGraph::batch`] merged them.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct BatchStatistics {
+    pub draw_calls_before: usize,
+    pub draw_calls_after: usize,
+}
+
+/// A graph's index buffer. Indices are stored in 16 bits until the graph has
+/// more vertices than they can address, and are then widened to 32 bits, so
+/// that backends only pay for wide indices when a graph needs them.
+pub(crate) enum IndexBuffer {
+    U16(Vec<u16>),
+    U32(Vec<u32>),
+}
+
+impl Default for IndexBuffer {
+    fn default() -> Self {
+        Self::U16(Vec::new())
+    }
+}
+
+impl IndexBuffer {
+    pub fn len(&self) -> usize {
+        match self {
+            Self::U16(indices) => indices.len(),
+            Self::U32(indices) => indices.len(),
+        }
+    }
+
+    pub fn get(&self, index: usize) -> u32 {
+        match self {
+            Self::U16(indices) => u32::from(indices[index]),
+            Self::U32(indices) => indices[index],
+        }
+    }
+
+    /// The indices of `count` consecutive triangles, starting at `first`.
+    pub fn triangles(&self, first: u32, count: u32) -> impl Iterator<Item = [u32; 3]> + '_ {
+        let first = first as usize;
+        (first..first + count as usize)
+            .step_by(3)
+            .map(|i| [self.get(i), self.get(i + 1), self.get(i + 2)])
+    }
+
+    /// Appends indices into a vertex buffer of `num_vertices` vertices,
+    /// widening the buffer if they do not all fit in 16 bits.
+    pub fn extend(&mut self, new_indices: impl Iterator<Item = u32>, num_vertices: usize) {
+        if let Self::U16(indices) = self {
+            if num_vertices > usize::from(u16::MAX) + 1 {
+                *self = Self::U32(indices.iter().copied().map(u32::from).collect());
+            }
+        }
+
+        match self {
+            #[allow(clippy::cast_possible_truncation)]
+            Self::U16(indices) => indices.extend(new_indices.map(|index| index as u16)),
+            Self::U32(indices) => indices.extend(new_indices),
+        }
+    }
+}
+
+/// A node and its links to its neighbors in the tree. Index 0 is the root,
+/// which is never a child, so it stands for no node in links.
 struct RenderGraphNode {
-    next: u16,
-    first_child: u16,
-    last_child: u16,
+    parent: u32,
+    prev: u32,
+    next: u32,
+    first_child: u32,
+    last_child: u32,
     command: RenderGraphCommand,
 }
 
 #[allow(clippy::module_name_repetitions)]
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub struct RenderGraphNodeId {
-    index: u16,
+    index: u32,
 }
 
 impl RenderGraphNodeId {
@@ -28,22 +305,36 @@ impl RenderGraphNodeId {
 }
 
 pub struct RenderGraph {
-    pub(crate) imm_indices: Vec<u16>,
+    pub(crate) imm_indices: IndexBuffer,
     pub(crate) imm_vertices: Vec<Vertex>,
+    pub(crate) img_indices: IndexBuffer,
+    pub(crate) img_vertices: Vec<TexturedVertex>,
+    pub(crate) textures: Vec<Texture>,
+    pub(crate) meshes: Vec<MeshHandle>,
+    pub(crate) glyph_caches: Vec<PinnedGlyphs>,
     nodes: Vec<RenderGraphNode>,
+    camera: Transform<f32, WorldSpace, ScreenSpace>,
 }
 
 impl Default for RenderGraph {
     fn default() -> Self {
         Self {
-            imm_indices: Vec::new(),
+            imm_indices: IndexBuffer::default(),
             imm_vertices: Vec::new(),
+            img_indices: IndexBuffer::default(),
+            img_vertices: Vec::new(),
+            textures: Vec::new(),
+            meshes: Vec::new(),
+            glyph_caches: Vec::new(),
             nodes: vec![RenderGraphNode {
+                parent: 0,
+                prev: 0,
                 next: 0,
                 first_child: 0,
                 last_child: 0,
                 command: RenderGraphCommand::Root,
             }],
+            camera: Transform::identity(),
         }
     }
 }
@@ -59,12 +350,26 @@ impl RenderGraph {
         &self.nodes[node.index as usize].command
     }
 
+    /// The transform from world space to screen space, applied to everything
+    /// in the graph. Defaults to the identity, so that world space units are
+    /// pixels.
+    #[must_use]
+    pub fn camera(&self) -> &Transform<f32, WorldSpace, ScreenSpace> {
+        &self.camera
+    }
+
+    /// Sets the camera transform. Changing the camera is cheap, so panning and
+    /// zooming does not require rebuilding the graph.
+    pub fn set_camera(&mut self, camera: Transform<f32, WorldSpace, ScreenSpace>) {
+        self.camera = camera;
+    }
+
     pub fn iter_children(
         &self,
         node: RenderGraphNodeId,
     ) -> impl Iterator<Item = RenderGraphNodeId> + '_ {
         struct It<'a> {
-            current: u16,
+            current: u32,
             graph: &'a RenderGraph,
         }
 
@@ -92,45 +397,1284 @@ impl RenderGraph {
 
     /// Embeds the given mesh into the render graph for drawing. Use this for
     /// small meshes that change frequently (every frame or thereabouts), such
-    /// as UI elements.
+    /// as UI elements. Meshes that rarely change should be created with
+    /// [`GraphicsContext::create_mesh`](crate::GraphicsContext::create_mesh)
+    /// and drawn with [`RenderGraph::draw_mesh`] instead.
     ///
     /// ## Panics
     ///
-    /// May panic if the number of vertices exceeds `u16::MAX`.
+    /// Panics if an index is out of bounds, if the number of indices is not a
+    /// multiple of 3, or if the graph is full. See
+    /// [`RenderGraph::try_draw_immediate`].
     pub fn draw_immediate(
         &mut self,
         parent: RenderGraphNodeId,
         vertices: &[Vertex],
         indices: &[u16],
-    ) {
-        let vertex_offset = self.imm_vertices.len();
-        self.imm_vertices.extend_from_slice(vertices);
+    ) -> RenderGraphNodeId {
+        self.add_immediate(parent, vertices, indices)
+            .expect("failed to add mesh to render graph")
+    }
+
+    /// Like [`RenderGraph::draw_immediate`], but takes 32-bit indices for
+    /// meshes with more than 65,536 vertices, and returns an error instead of
+    /// panicking. The graph is unchanged if an error is returned.
+    pub fn try_draw_immediate(
+        &mut self,
+        parent: RenderGraphNodeId,
+        vertices: &[Vertex],
+        indices: &[u32],
+    ) -> Result<RenderGraphNodeId, RenderGraphError> {
+        self.add_immediate(parent, vertices, indices)
+    }
+
+    fn add_immediate<I: Copy + Into<u32>>(
+        &mut self,
+        parent: RenderGraphNodeId,
+        vertices: &[Vertex],
+        indices: &[I],
+    ) -> Result<RenderGraphNodeId, RenderGraphError> {
+        self.check_node_capacity()?;
+        let (first_index, num_indices) = append_mesh(
+            &mut self.imm_vertices,
+            &mut self.imm_indices,
+            vertices,
+            indices,
+        )?;
+
+        Ok(self.add_node(
+            parent,
+            RenderGraphCommand::DrawImmediate {
+                first_index,
+                num_indices,
+            },
+        ))
+    }
+
+    /// Draws a mesh created with
+    /// [`GraphicsContext::create_mesh`](crate::GraphicsContext::create_mesh).
+    /// The mesh's vertices stay in device memory, so nothing is copied when
+    /// the graph is drawn. The graph keeps the mesh alive until it is dropped.
+    ///
+    /// ## Panics
+    ///
+    /// Panics if the graph is full.
+    pub fn draw_mesh(&mut self, parent: RenderGraphNodeId, mesh: &MeshHandle) -> RenderGraphNodeId {
+        self.check_node_capacity()
+            .expect("failed to add mesh to render graph");
+        // Every mesh has a node, so it fits if the node does.
+        let mesh_index = self.meshes.len().try_into().unwrap();
+        self.meshes.push(mesh.clone());
+
+        self.add_node(parent, RenderGraphCommand::DrawMesh { mesh: mesh_index })
+    }
+
+    /// Draws a texture stretched over `rect`.
+    ///
+    /// ## Panics
+    ///
+    /// Panics if the graph is full.
+    pub fn draw_image(
+        &mut self,
+        parent: RenderGraphNodeId,
+        texture: &Texture,
+        rect: &Rect<f32, ObjectSpace>,
+        options: &ImageOptions,
+    ) -> RenderGraphNodeId {
+        let extent = texture.extent();
+        let borders = options.nine_slice.unwrap_or(Insets::zero());
+
+        // Grid lines in the destination and in texture coordinates. Without
+        // nine-slice borders, the inner lines coincide with the edges and the
+        // border cells are empty.
+        let (xs, us) = slice_axis(
+            rect.p0.x,
+            rect.p1.x,
+            extent.width as f32,
+            (borders.left, borders.right),
+        );
+        let (ys, vs) = slice_axis(
+            rect.p0.y,
+            rect.p1.y,
+            extent.height as f32,
+            (borders.top, borders.bottom),
+        );
+
+        let mut vertices = Vec::with_capacity(16);
+        for (y, v) in ys.into_iter().zip(vs) {
+            for (x, u) in xs.into_iter().zip(us) {
+                vertices.push(TexturedVertex {
+                    position: Point::new(x, y),
+                    uv: Point::new(u, v),
+                    color: options.tint,
+                });
+            }
+        }
+
+        let mut indices: Vec<u16> = Vec::with_capacity(54);
+        for row in 0..3 {
+            for column in 0..3 {
+                let top_left = row * 4 + column;
+                let [a, b, c, d] = [top_left, top_left + 1, top_left + 5, top_left + 4];
+
+                // Skip empty cells.
+                let (p0, p1) = (vertices[a as usize].position, vertices[c as usize].position);
+                if p0.x == p1.x || p0.y == p1.y {
+                    continue;
+                }
+
+                indices.extend_from_slice(&[a, b, c, a, c, d]);
+            }
+        }
+
+        self.add_textured(parent, texture, options.filter, &vertices, &indices)
+            .expect("failed to add image to render graph")
+    }
+
+    /// Embeds a textured mesh into the render graph for drawing, like
+    /// [`RenderGraph::draw_immediate`].
+    ///
+    /// ## Panics
+    ///
+    /// Panics if an index is out of bounds, if the number of indices is not a
+    /// multiple of 3, or if the graph is full. See
+    /// [`RenderGraph::try_draw_textured`].
+    pub fn draw_textured(
+        &mut self,
+        parent: RenderGraphNodeId,
+        texture: &Texture,
+        filter: Filter,
+        vertices: &[TexturedVertex],
+        indices: &[u16],
+    ) -> RenderGraphNodeId {
+        self.add_textured(parent, texture, filter, vertices, indices)
+            .expect("failed to add mesh to render graph")
+    }
+
+    /// Like [`RenderGraph::draw_textured`], but takes 32-bit indices for
+    /// meshes with more than 65,536 vertices, and returns an error instead of
+    /// panicking. The graph is unchanged if an error is returned.
+    pub fn try_draw_textured(
+        &mut self,
+        parent: RenderGraphNodeId,
+        texture: &Texture,
+        filter: Filter,
+        vertices: &[TexturedVertex],
+        indices: &[u32],
+    ) -> Result<RenderGraphNodeId, RenderGraphError> {
+        self.add_textured(parent, texture, filter, vertices, indices)
+    }
+
+    fn add_textured<I: Copy + Into<u32>>(
+        &mut self,
+        parent: RenderGraphNodeId,
+        texture: &Texture,
+        filter: Filter,
+        vertices: &[TexturedVertex],
+        indices: &[I],
+    ) -> Result<RenderGraphNodeId, RenderGraphError> {
+        self.check_node_capacity()?;
+        // Every texture has a node, so it fits if the node does.
+        let texture_index = self.textures.len().try_into().unwrap();
+        let (first_index, num_indices) = append_mesh(
+            &mut self.img_vertices,
+            &mut self.img_indices,
+            vertices,
+            indices,
+        )?;
+        self.textures.push(texture.clone());
+
+        Ok(self.add_node(
+            parent,
+            RenderGraphCommand::DrawImage {
+                texture: texture_index,
+                filter,
+                first_index,
+                num_indices,
+            },
+        ))
+    }
+
+    /// Draws a run of glyphs in one font, size and color. Glyphs are taken
+    /// from `cache`, which rasterizes them the first time they are drawn, and
+    /// the whole run is drawn at once.
+    ///
+    /// Positions are snapped to a quarter of a unit horizontally and to whole
+    /// units vertically, so text is sharpest when one unit is one pixel.
+    /// Glyphs that do not fit in the cache's atlas are skipped.
+    ///
+    /// ## Panics
+    ///
+    /// Panics if the graph is full.
+    pub fn draw_glyph_run(
+        &mut self,
+        parent: RenderGraphNodeId,
+        cache: &GlyphCache,
+        font: &Font,
+        size: f32,
+        glyphs: &[PositionedGlyph],
+        color: Color,
+    ) -> RenderGraphNodeId {
+        let cache_index = match self
+            .glyph_caches
+            .iter()
+            .position(|pinned| pinned.cache().ptr_eq(cache))
+        {
+            Some(index) => index,
+            None => {
+                self.glyph_caches.push(PinnedGlyphs::new(cache));
+                self.glyph_caches.len() - 1
+            }
+        };
+        let pinned = &mut self.glyph_caches[cache_index];
+
+        let mut vertices = Vec::with_capacity(4 * glyphs.len());
+        let mut indices = Vec::with_capacity(6 * glyphs.len());
+        for glyph in glyphs {
+            let Some((rect, texels)) = pinned.quad(font, glyph.glyph, size, glyph.position) else {
+                continue;
+            };
+
+            let first: u32 = vertices.len().try_into().unwrap();
+            for (x, u) in [(rect.p0.x, texels.p0.x), (rect.p1.x, texels.p1.x)] {
+                for (y, v) in [(rect.p0.y, texels.p0.y), (rect.p1.y, texels.p1.y)] {
+                    vertices.push(TexturedVertex {
+                        position: Point::new(x, y),
+                        uv: Point::new(u as f32, v as f32),
+                        color,
+                    });
+                }
+            }
+
+            // Vertices are top-left, bottom-left, top-right, bottom-right.
+            indices.extend_from_slice(&[first, first + 2, first + 3, first, first + 3, first + 1]);
+        }
+
+        self.check_node_capacity()
+            .expect("failed to add glyph run to render graph");
+        let (first_index, num_indices) = append_mesh(
+            &mut self.img_vertices,
+            &mut self.img_indices,
+            &vertices,
+            &indices,
+        )
+        .expect("failed to add glyph run to render graph");
+
+        self.add_node(
+            parent,
+            RenderGraphCommand::DrawGlyphRun {
+                cache: cache_index.try_into().unwrap(),
+                first_index,
+                num_indices,
+            },
+        )
+    }
+
+    /// Adds a node that applies `transform` to all of its descendants, and
+    /// returns it so that children can be added to it.
+    ///
+    /// ## Panics
+    ///
+    /// Panics if the graph already has `u32::MAX` nodes.
+    pub fn transform(
+        &mut self,
+        parent: RenderGraphNodeId,
+        transform: &Transform<f32, ObjectSpace, WorldSpace>,
+    ) -> RenderGraphNodeId {
+        self.add_node(
+            parent,
+            RenderGraphCommand::Transform {
+                transform: *transform,
+            },
+        )
+    }
+
+    /// Adds a node that clips all of its descendants to `rect`, and returns it
+    /// so that children can be added to it. The rectangle is in the same space
+    /// as its siblings' vertices.
+    ///
+    /// ## Panics
+    ///
+    /// Panics if the graph already has `u32::MAX` nodes.
+    pub fn clip(
+        &mut self,
+        parent: RenderGraphNodeId,
+        rect: &Rect<f32, ObjectSpace>,
+    ) -> RenderGraphNodeId {
+        self.add_node(parent, RenderGraphCommand::Clip { rect: *rect })
+    }
+
+    /// Adds a node that draws its descendants as a group, and returns it so
+    /// that children can be added to it. The group is blended with the
+    /// contents beneath it as a whole, so overlapping children do not show
+    /// through each other when `opacity` is less than 1.
+    ///
+    /// ## Panics
+    ///
+    /// Panics if the graph already has `u32::MAX` nodes.
+    pub fn layer(
+        &mut self,
+        parent: RenderGraphNodeId,
+        opacity: f32,
+        blend_mode: BlendMode,
+    ) -> RenderGraphNodeId {
+        self.add_node(
+            parent,
+            RenderGraphCommand::Layer {
+                opacity,
+                blend_mode,
+            },
+        )
+    }
+
+    /// Adds a node that only holds other nodes, and returns it so that
+    /// children can be added to it.
+    ///
+    /// ## Panics
+    ///
+    /// Panics if the graph already has `u32::MAX` nodes.
+    pub fn group(&mut self, parent: RenderGraphNodeId) -> RenderGraphNodeId {
+        self.add_node(parent, RenderGraphCommand::Group)
+    }
+
+    /// Adds a group to `parent`, and calls `build` to add children to it.
+    /// Returns the group.
+    ///
+    /// ```
+    /// use geometry::{Point, Rect};
+    /// use graphics::{Color, RenderGraph, RenderGraphNodeId, Vertex};
+    ///
+    /// let square = [(0.0, 0.0), (8.0, 0.0), (8.0, 8.0), (0.0, 8.0)].map(|(x, y)| Vertex {
+    ///     position: Point::new(x, y),
+    ///     color: Color::WHITE,
+    /// });
+    /// let indices = [0, 1, 2, 0, 2, 3];
+    ///
+    /// let mut graph = RenderGraph::new();
+    /// let panel = graph.with_child(RenderGraphNodeId::root(), |panel| {
+    ///     panel.draw_immediate(&square, &indices);
+    ///     let inner = Rect::new(Point::new(2.0, 2.0), Point::new(6.0, 6.0));
+    ///     panel.with_clip(&inner, |content| {
+    ///         content.draw_immediate(&square, &indices);
+    ///     });
+    /// });
+    /// assert_eq!(graph.iter_children(panel).count(), 2);
+    /// ```
+    ///
+    /// ## Panics
+    ///
+    /// Panics if the graph already has `u32::MAX` nodes.
+    pub fn with_child(
+        &mut self,
+        parent: RenderGraphNodeId,
+        build: impl FnOnce(&mut RenderGraphScope),
+    ) -> RenderGraphNodeId {
+        let node = self.group(parent);
+        build(&mut RenderGraphScope { graph: self, node });
+        node
+    }
+
+    /// Moves `node`, with its descendants, to come just before `sibling`
+    /// under `sibling`'s parent. Nodes are drawn in order, so `node` is drawn
+    /// beneath `sibling`.
+    ///
+    /// ## Panics
+    ///
+    /// Panics if either node is the root, or if `node` is `sibling` or one of
+    /// its ancestors.
+    pub fn insert_before(&mut self, node: RenderGraphNodeId, sibling: RenderGraphNodeId) {
+        self.check_move(node, sibling);
+        self.detach(node.index);
+        let sibling = &self.nodes[sibling.index as usize];
+        self.attach(node.index, sibling.parent, sibling.prev);
+    }
+
+    /// Moves `node`, with its descendants, to come just after `sibling` under
+    /// `sibling`'s parent. Nodes are drawn in order, so `node` is drawn above
+    /// `sibling`.
+    ///
+    /// ## Panics
+    ///
+    /// Panics if either node is the root, or if `node` is `sibling` or one of
+    /// its ancestors.
+    pub fn insert_after(&mut self, node: RenderGraphNodeId, sibling: RenderGraphNodeId) {
+        self.check_move(node, sibling);
+        self.detach(node.index);
+        let parent = self.nodes[sibling.index as usize].parent;
+        self.attach(node.index, parent, sibling.index);
+    }
+
+    /// The parent of `node`, or `None` for the root.
+    #[must_use]
+    pub fn parent(&self, node: RenderGraphNodeId) -> Option<RenderGraphNodeId> {
+        (node != RenderGraphNodeId::root()).then(|| RenderGraphNodeId {
+            index: self.nodes[node.index as usize].parent,
+        })
+    }
+
+    /// The number of draw calls that drawing the graph takes, which is one for
+    /// every node that draws something.
+    #[must_use]
+    pub fn num_draw_calls(&self) -> usize {
+        self.nodes
+            .iter()
+            .filter(|node| {
+                matches!(
+                    node.command,
+                    RenderGraphCommand::DrawImmediate { .. }
+                        | RenderGraphCommand::DrawImage { .. }
+                        | RenderGraphCommand::DrawGlyphRun { .. }
+                        | RenderGraphCommand::DrawMesh { .. }
+                )
+            })
+            .count()
+    }
+
+    /// Merges sibling draws that use the same shader, texture and filter into
+    /// single draws, so that graphs with many small meshes, such as UIs made
+    /// of thousands of quads, take few draw calls. Siblings share the
+    /// transforms and clips of their ancestors, so their draws only differ in
+    /// what they draw with.
+    ///
+    /// A draw may be merged into an earlier sibling past other draws, as long
+    /// as its bounds do not overlap theirs, so that the graph still draws the
+    /// same pixels. Draws are never moved past nodes with children or past
+    /// meshes. Merged draws are replaced with empty groups, so their IDs stay
+    /// valid.
+    ///
+    /// Call this once the graph is complete, as draws added afterward are
+    /// only merged if this is called again.
+    pub fn batch(&mut self) -> BatchStatistics {
+        let draw_calls_before = self.num_draw_calls();
+
+        // The index ranges of draws that others were merged into.
+        let mut merged = HashMap::new();
+        for parent in 0..self.nodes.len() {
+            #[allow(clippy::cast_possible_truncation)]
+            self.batch_children(parent as u32, &mut merged);
+        }
+
+        if !merged.is_empty() {
+            self.gather_indices(&merged);
+        }
 
-        let first_index = self.imm_indices.len();
-        self.imm_indices.extend_from_slice(indices);
-        for index in &mut self.imm_indices[first_index..] {
-            *index = (*index as usize + vertex_offset).try_into().unwrap();
+        BatchStatistics {
+            draw_calls_before,
+            draw_calls_after: self.num_draw_calls(),
         }
+    }
+
+    /// Merges the children of `parent`, recording the index ranges of every
+    /// draw that others were merged into.
+    fn batch_children(&mut self, parent: u32, merged: &mut HashMap<u32, Vec<(u32, u32)>>) {
+        // Draws since the last node that draws cannot be moved past, with the
+        // bounds of everything merged into them.
+        let mut batches: Vec<(u32, Rect<f32>)> = Vec::new();
+
+        let mut child = self.nodes[parent as usize].first_child;
+        while child != 0 {
+            let node = &self.nodes[child as usize];
+            let next = node.next;
+
+            let range = match node.command {
+                RenderGraphCommand::DrawImmediate {
+                    first_index,
+                    num_indices,
+                }
+                | RenderGraphCommand::DrawImage {
+                    first_index,
+                    num_indices,
+                    ..
+                }
+                | RenderGraphCommand::DrawGlyphRun {
+                    first_index,
+                    num_indices,
+                    ..
+                } if node.first_child == 0 => (first_index, num_indices),
+                _ => {
+                    batches.clear();
+                    child = next;
+                    continue;
+                }
+            };
 
-        let node_id = self.nodes.len() as u16;
+            let bounds = self.draw_bounds(child);
+            let mut target = None;
+            for (i, (batch, batch_bounds)) in batches.iter().enumerate().rev() {
+                if self.same_pipeline(*batch, child) {
+                    target = Some(i);
+                    break;
+                }
+                if batch_bounds.intersects(&bounds) {
+                    break;
+                }
+            }
+
+            match target {
+                Some(i) => {
+                    let (batch, batch_bounds) = &mut batches[i];
+                    *batch_bounds = batch_bounds.union(&bounds);
+                    merged
+                        .entry(*batch)
+                        .or_insert_with(|| vec![self.draw_range(*batch)])
+                        .push(range);
+                    self.nodes[child as usize].command = RenderGraphCommand::Group;
+                }
+                None => batches.push((child, bounds)),
+            }
+
+            child = next;
+        }
+    }
+
+    /// Rebuilds the index buffers so that the ranges merged into each draw
+    /// are contiguous.
+    fn gather_indices(&mut self, merged: &HashMap<u32, Vec<(u32, u32)>>) {
+        let num_imm_vertices = self.imm_vertices.len();
+        let num_img_vertices = self.img_vertices.len();
+        let mut imm_indices = IndexBuffer::default();
+        let mut img_indices = IndexBuffer::default();
+
+        for (index, node) in (0..).zip(&mut self.nodes) {
+            let (indices, new_indices, num_vertices, first_index, num_indices) =
+                match &mut node.command {
+                    RenderGraphCommand::DrawImmediate {
+                        first_index,
+                        num_indices,
+                    } => (
+                        &self.imm_indices,
+                        &mut imm_indices,
+                        num_imm_vertices,
+                        first_index,
+                        num_indices,
+                    ),
+                    RenderGraphCommand::DrawImage {
+                        first_index,
+                        num_indices,
+                        ..
+                    }
+                    | RenderGraphCommand::DrawGlyphRun {
+                        first_index,
+                        num_indices,
+                        ..
+                    } => (
+                        &self.img_indices,
+                        &mut img_indices,
+                        num_img_vertices,
+                        first_index,
+                        num_indices,
+                    ),
+                    _ => continue,
+                };
+
+            let own_range = [(*first_index, *num_indices)];
+            let ranges = merged.get(&index).map_or(&own_range[..], Vec::as_slice);
+
+            // The new buffers hold the same number of indices as the old ones.
+            #[allow(clippy::cast_possible_truncation)]
+            let start = new_indices.len() as u32;
+            for &(first, count) in ranges {
+                new_indices.extend(
+                    (first..first + count).map(|i| indices.get(i as usize)),
+                    num_vertices,
+                );
+            }
+
+            *first_index = start;
+            #[allow(clippy::cast_possible_truncation)]
+            let end = new_indices.len() as u32;
+            *num_indices = end - start;
+        }
+
+        self.imm_indices = imm_indices;
+        self.img_indices = img_indices;
+    }
+
+    /// The first index and number of indices of a draw that uses the graph's
+    /// index buffers.
+    fn draw_range(&self, node: u32) -> (u32, u32) {
+        match self.nodes[node as usize].command {
+            RenderGraphCommand::DrawImmediate {
+                first_index,
+                num_indices,
+            }
+            | RenderGraphCommand::DrawImage {
+                first_index,
+                num_indices,
+                ..
+            }
+            | RenderGraphCommand::DrawGlyphRun {
+                first_index,
+                num_indices,
+                ..
+            } => (first_index, num_indices),
+            _ => unreachable!("node does not draw from the graph's index buffers"),
+        }
+    }
+
+    /// The bounding box of the triangles drawn by a node, in its object space.
+    /// The box is inverted, and so empty, if nothing is drawn.
+    fn draw_bounds(&self, node: u32) -> Rect<f32> {
+        let (first, count) = self.draw_range(node);
+        let immediate = matches!(
+            self.nodes[node as usize].command,
+            RenderGraphCommand::DrawImmediate { .. }
+        );
+        let position = |i: u32| {
+            if immediate {
+                self.imm_vertices[self.imm_indices.get(i as usize) as usize].position
+            } else {
+                self.img_vertices[self.img_indices.get(i as usize) as usize].position
+            }
+        };
+
+        (first..first + count).map(position).fold(
+            Rect::new(
+                Point::new(f32::INFINITY, f32::INFINITY),
+                Point::new(f32::NEG_INFINITY, f32::NEG_INFINITY),
+            ),
+            |bounds, position| {
+                Rect::new(
+                    Point::new(bounds.p0.x.min(position.x), bounds.p0.y.min(position.y)),
+                    Point::new(bounds.p1.x.max(position.x), bounds.p1.y.max(position.y)),
+                )
+            },
+        )
+    }
+
+    /// Returns `true` if two draws use the same shader and resources, so that
+    /// they can be drawn with one draw call.
+    fn same_pipeline(&self, a: u32, b: u32) -> bool {
+        match (
+            &self.nodes[a as usize].command,
+            &self.nodes[b as usize].command,
+        ) {
+            (
+                RenderGraphCommand::DrawImmediate { .. },
+                RenderGraphCommand::DrawImmediate { .. },
+            ) => true,
+            (
+                RenderGraphCommand::DrawImage {
+                    texture: texture_a,
+                    filter: filter_a,
+                    ..
+                },
+                RenderGraphCommand::DrawImage {
+                    texture: texture_b,
+                    filter: filter_b,
+                    ..
+                },
+            ) => {
+                filter_a == filter_b
+                    && Rc::ptr_eq(
+                        &self.textures[*texture_a as usize].inner,
+                        &self.textures[*texture_b as usize].inner,
+                    )
+            }
+            (
+                RenderGraphCommand::DrawGlyphRun { cache: cache_a, .. },
+                RenderGraphCommand::DrawGlyphRun { cache: cache_b, .. },
+            ) => cache_a == cache_b,
+            _ => false,
+        }
+    }
+
+    fn check_move(&self, node: RenderGraphNodeId, sibling: RenderGraphNodeId) {
+        assert!(
+            node != RenderGraphNodeId::root() && sibling != RenderGraphNodeId::root(),
+            "the root cannot have siblings"
+        );
+
+        let mut ancestor = Some(sibling);
+        while let Some(current) = ancestor {
+            assert!(current != node, "a node cannot be moved into itself");
+            ancestor = self.parent(current);
+        }
+    }
+
+    /// Unlinks a node from its parent and siblings.
+    fn detach(&mut self, index: u32) {
+        let RenderGraphNode {
+            parent, prev, next, ..
+        } = self.nodes[index as usize];
+
+        if prev == 0 {
+            self.nodes[parent as usize].first_child = next;
+        } else {
+            self.nodes[prev as usize].next = next;
+        }
+
+        if next == 0 {
+            self.nodes[parent as usize].last_child = prev;
+        } else {
+            self.nodes[next as usize].prev = prev;
+        }
+    }
+
+    /// Links a detached node into `parent`'s children after `prev`, or as the
+    /// first child if `prev` is 0.
+    fn attach(&mut self, index: u32, parent: u32, prev: u32) {
+        let next = if prev == 0 {
+            self.nodes[parent as usize].first_child
+        } else {
+            self.nodes[prev as usize].next
+        };
+
+        let node = &mut self.nodes[index as usize];
+        node.parent = parent;
+        node.prev = prev;
+        node.next = next;
+
+        if prev == 0 {
+            self.nodes[parent as usize].first_child = index;
+        } else {
+            self.nodes[prev as usize].next = index;
+        }
+
+        if next == 0 {
+            self.nodes[parent as usize].last_child = index;
+        } else {
+            self.nodes[next as usize].prev = index;
+        }
+    }
+
+    /// Checks that another node can be added, before adding anything that it
+    /// refers to.
+    fn check_node_capacity(&self) -> Result<(), RenderGraphError> {
+        if self.nodes.len() > u32::MAX as usize {
+            Err(RenderGraphError::TooManyNodes)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Appends a node as the last child of `parent`.
+    fn add_node(
+        &mut self,
+        parent: RenderGraphNodeId,
+        command: RenderGraphCommand,
+    ) -> RenderGraphNodeId {
+        let node_id: u32 = self
+            .nodes
+            .len()
+            .try_into()
+            .expect("render graph has too many nodes");
         self.nodes.push(RenderGraphNode {
+            parent: 0,
+            prev: 0,
             next: 0,
             first_child: 0,
             last_child: 0,
-            command: RenderGraphCommand::DrawImmediate {
-                first_index: first_index as u16,
-                num_indices: indices.len() as u16,
-            },
+            command,
         });
 
-        let parent = &mut self.nodes[parent.index as usize];
-        let prev_sibling = parent.last_child as usize;
-        parent.last_child = node_id;
+        let last_child = self.nodes[parent.index as usize].last_child;
+        self.attach(node_id, parent.index, last_child);
 
-        if parent.first_child == 0 {
-            parent.first_child = node_id;
-        } else {
-            self.nodes[prev_sibling].next = node_id;
-        }
+        RenderGraphNodeId { index: node_id }
+    }
+}
+
+/// A node of a graph being built with [`RenderGraph::with_child`]. Nodes
+/// created through the scope are added as children of its node.
+#[allow(clippy::module_name_repetitions)]
+pub struct RenderGraphScope<'a> {
+    graph: &'a mut RenderGraph,
+    node: RenderGraphNodeId,
+}
+
+impl RenderGraphScope<'_> {
+    /// The node that children are added to.
+    #[must_use]
+    pub fn node(&self) -> RenderGraphNodeId {
+        self.node
+    }
+
+    /// The graph being built, for calls that the scope does not provide.
+    pub fn graph(&mut self) -> &mut RenderGraph {
+        self.graph
+    }
+
+    /// See [`RenderGraph::draw_immediate`].
+    pub fn draw_immediate(&mut self, vertices: &[Vertex], indices: &[u16]) -> RenderGraphNodeId {
+        self.graph.draw_immediate(self.node, vertices, indices)
+    }
+
+    /// See [`RenderGraph::try_draw_immediate`].
+    pub fn try_draw_immediate(
+        &mut self,
+        vertices: &[Vertex],
+        indices: &[u32],
+    ) -> Result<RenderGraphNodeId, RenderGraphError> {
+        self.graph.try_draw_immediate(self.node, vertices, indices)
+    }
+
+    /// See [`RenderGraph::draw_mesh`].
+    pub fn draw_mesh(&mut self, mesh: &MeshHandle) -> RenderGraphNodeId {
+        self.graph.draw_mesh(self.node, mesh)
+    }
+
+    /// See [`RenderGraph::draw_textured`].
+    pub fn draw_textured(
+        &mut self,
+        texture: &Texture,
+        filter: Filter,
+        vertices: &[TexturedVertex],
+        indices: &[u16],
+    ) -> RenderGraphNodeId {
+        self.graph
+            .draw_textured(self.node, texture, filter, vertices, indices)
+    }
+
+    /// See [`RenderGraph::try_draw_textured`].
+    pub fn try_draw_textured(
+        &mut self,
+        texture: &Texture,
+        filter: Filter,
+        vertices: &[TexturedVertex],
+        indices: &[u32],
+    ) -> Result<RenderGraphNodeId, RenderGraphError> {
+        self.graph
+            .try_draw_textured(self.node, texture, filter, vertices, indices)
+    }
+
+    /// See [`RenderGraph::draw_image`].
+    pub fn draw_image(
+        &mut self,
+        texture: &Texture,
+        rect: &Rect<f32, ObjectSpace>,
+        options: &ImageOptions,
+    ) -> RenderGraphNodeId {
+        self.graph.draw_image(self.node, texture, rect, options)
+    }
+
+    /// See [`RenderGraph::draw_glyph_run`].
+    pub fn draw_glyph_run(
+        &mut self,
+        cache: &GlyphCache,
+        font: &Font,
+        size: f32,
+        glyphs: &[PositionedGlyph],
+        color: Color,
+    ) -> RenderGraphNodeId {
+        self.graph
+            .draw_glyph_run(self.node, cache, font, size, glyphs, color)
+    }
+
+    /// Adds a group, and calls `build` to add children to it.
+    pub fn with_child(&mut self, build: impl FnOnce(&mut RenderGraphScope)) -> RenderGraphNodeId {
+        self.graph.with_child(self.node, build)
+    }
+
+    /// Adds a transform node, and calls `build` to add children to it.
+    pub fn with_transform(
+        &mut self,
+        transform: &Transform<f32, ObjectSpace, WorldSpace>,
+        build: impl FnOnce(&mut RenderGraphScope),
+    ) -> RenderGraphNodeId {
+        let node = self.graph.transform(self.node, transform);
+        self.build(node, build)
+    }
+
+    /// Adds a clip node, and calls `build` to add children to it.
+    pub fn with_clip(
+        &mut self,
+        rect: &Rect<f32, ObjectSpace>,
+        build: impl FnOnce(&mut RenderGraphScope),
+    ) -> RenderGraphNodeId {
+        let node = self.graph.clip(self.node, rect);
+        self.build(node, build)
+    }
+
+    /// Adds a layer node, and calls `build` to add children to it.
+    pub fn with_layer(
+        &mut self,
+        opacity: f32,
+        blend_mode: BlendMode,
+        build: impl FnOnce(&mut RenderGraphScope),
+    ) -> RenderGraphNodeId {
+        let node = self.graph.layer(self.node, opacity, blend_mode);
+        self.build(node, build)
+    }
+
+    fn build(
+        &mut self,
+        node: RenderGraphNodeId,
+        build: impl FnOnce(&mut RenderGraphScope),
+    ) -> RenderGraphNodeId {
+        build(&mut RenderGraphScope {
+            graph: self.graph,
+            node,
+        });
+        node
+    }
+}
+
+/// Checks that a mesh is made of whole triangles whose indices are in bounds.
+pub(crate) fn check_mesh<V, I: Copy + Into<u32>>(
+    vertices: &[V],
+    indices: &[I],
+) -> Result<(), RenderGraphError> {
+    if !indices.len().is_multiple_of(3) {
+        return Err(RenderGraphError::IncompleteTriangle {
+            num_indices: indices.len(),
+        });
+    }
+
+    if let Some(&index) = indices
+        .iter()
+        .find(|&&index| index.into() as usize >= vertices.len())
+    {
+        return Err(RenderGraphError::IndexOutOfBounds {
+            index: index.into(),
+            num_vertices: vertices.len(),
+        });
+    }
+
+    Ok(())
+}
+
+/// Appends a mesh to one of a graph's vertex and index buffers, returning the
+/// offset of its first index and the number of indices. Nothing is appended if
+/// an error is returned.
+fn append_mesh<V: Copy, I: Copy + Into<u32>>(
+    vertices: &mut Vec<V>,
+    indices: &mut IndexBuffer,
+    new_vertices: &[V],
+    new_indices: &[I],
+) -> Result<(u32, u32), RenderGraphError> {
+    check_mesh(new_vertices, new_indices)?;
+
+    let vertex_offset = vertices.len();
+    let num_vertices = vertex_offset + new_vertices.len();
+    if num_vertices > u32::MAX as usize + 1 {
+        return Err(RenderGraphError::TooManyVertices);
+    }
+
+    let first_index = u32::try_from(indices.len()).ok();
+    let num_indices = u32::try_from(new_indices.len()).ok();
+    let (Some(first_index), Some(num_indices)) = (first_index, num_indices) else {
+        return Err(RenderGraphError::TooManyIndices);
+    };
+    if first_index.checked_add(num_indices).is_none() {
+        return Err(RenderGraphError::TooManyIndices);
+    }
+
+    vertices.extend_from_slice(new_vertices);
+    #[allow(clippy::cast_possible_truncation)]
+    let vertex_offset = vertex_offset as u32;
+    indices.extend(
+        new_indices
+            .iter()
+            .map(|&index| index.into() + vertex_offset),
+        num_vertices,
+    );
+
+    Ok((first_index, num_indices))
+}
+
+/// Divides one axis of a nine-slice image into the grid lines of its three
+/// cells, in the destination and in texture coordinates.
+fn slice_axis(start: f32, end: f32, size: f32, borders: (f32, f32)) -> ([f32; 4], [f32; 4]) {
+    let (mut near, mut far) = borders;
+
+    let length = (end - start).abs();
+    if near + far > length {
+        let scale = length / (near + far);
+        near *= scale;
+        far *= scale;
+    }
+
+    // The destination may be flipped.
+    let direction = (end - start).signum();
+
+    (
+        [start, start + near * direction, end - far * direction, end],
+        [0.0, borders.0 / size, 1.0 - borders.1 / size, 1.0],
+    )
+}
+
+/// The pixels whose centers lie within a screen space rectangle. This is the
+/// scissor rectangle that backends use to implement clipping, so it must
+/// cover the same pixels that would be covered by filling `rect`.
+#[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+pub(crate) fn pixel_bounds(rect: &Rect<f32, ScreenSpace>) -> Rect<u32, ScreenSpace> {
+    // Negative coordinates saturate to 0 when cast.
+    let pixel = |coordinate: f32| (coordinate - 0.5).ceil() as u32;
+    Rect::new(
+        Point::new(pixel(rect.p0.x), pixel(rect.p0.y)),
+        Point::new(pixel(rect.p1.x), pixel(rect.p1.y)),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{GraphicsConfig, GraphicsContext};
+
+    fn rect<T>(x0: T, y0: T, x1: T, y1: T) -> Rect<T, ScreenSpace> {
+        Rect::new(Point::new(x0, y0), Point::new(x1, y1))
+    }
+
+    fn vertices(count: usize) -> Vec<Vertex> {
+        vec![
+            Vertex {
+                position: Point::new(0.0, 0.0),
+                color: Color::WHITE,
+            };
+            count
+        ]
+    }
+
+    #[test]
+    fn widen_indices() {
+        let mut graph = RenderGraph::new();
+        graph.draw_immediate(RenderGraphNodeId::root(), &vertices(3), &[0, 1, 2]);
+        assert!(matches!(graph.imm_indices, IndexBuffer::U16(_)));
+
+        // Indices are widened once the graph has more vertices than 16 bits
+        // can address.
+        graph.draw_immediate(RenderGraphNodeId::root(), &vertices(65_533), &[0, 1, 2]);
+        assert!(matches!(graph.imm_indices, IndexBuffer::U16(_)));
+        graph.draw_immediate(RenderGraphNodeId::root(), &vertices(3), &[2, 1, 0]);
+        assert!(matches!(graph.imm_indices, IndexBuffer::U32(_)));
+
+        let indices: Vec<u32> = (0..9).map(|i| graph.imm_indices.get(i)).collect();
+        assert_eq!(indices, [0, 1, 2, 3, 4, 5, 65_538, 65_537, 65_536]);
+        assert_eq!(
+            graph.imm_indices.triangles(6, 3).collect::<Vec<_>>(),
+            [[65_538, 65_537, 65_536]]
+        );
+    }
+
+    #[test]
+    fn checked_errors() {
+        let mut graph = RenderGraph::new();
+        assert_eq!(
+            graph.try_draw_immediate(RenderGraphNodeId::root(), &vertices(3), &[0, 1, 3]),
+            Err(RenderGraphError::IndexOutOfBounds {
+                index: 3,
+                num_vertices: 3
+            })
+        );
+
+        assert_eq!(
+            graph.try_draw_immediate(RenderGraphNodeId::root(), &vertices(3), &[0, 1, 2, 0]),
+            Err(RenderGraphError::IncompleteTriangle { num_indices: 4 })
+        );
+
+        // The graph is unchanged by the failed calls.
+        assert_eq!(graph.iter_children(RenderGraphNodeId::root()).count(), 0);
+        assert!(graph.imm_vertices.is_empty());
+        assert_eq!(graph.imm_indices.len(), 0);
+
+        // Retained meshes are checked the same way.
+        let context = GraphicsContext::new(&GraphicsConfig::default());
+        assert_eq!(
+            context.try_create_mesh(&vertices(3), &[0, 1, 3]).err(),
+            Some(RenderGraphError::IndexOutOfBounds {
+                index: 3,
+                num_vertices: 3
+            })
+        );
+        assert_eq!(
+            context.try_create_mesh(&vertices(3), &[0, 1, 2, 0]).err(),
+            Some(RenderGraphError::IncompleteTriangle { num_indices: 4 })
+        );
+        assert!(context.try_create_mesh(&vertices(3), &[0, 1, 2]).is_ok());
+    }
+
+    #[test]
+    #[should_panic = "failed to create mesh"]
+    fn incomplete_mesh() {
+        let context = GraphicsContext::new(&GraphicsConfig::default());
+        let _ = context.create_mesh(&vertices(3), &[0, 1, 2, 0]);
+    }
+
+    #[test]
+    #[should_panic = "failed to add mesh to render graph"]
+    fn incomplete_triangle() {
+        let mut graph = RenderGraph::new();
+        graph.draw_immediate(RenderGraphNodeId::root(), &vertices(3), &[0, 1, 2, 0]);
+    }
+
+    fn children(graph: &RenderGraph, node: RenderGraphNodeId) -> Vec<RenderGraphNodeId> {
+        graph.iter_children(node).collect()
+    }
+
+    #[test]
+    fn insert_siblings() {
+        let root = RenderGraphNodeId::root();
+        let mut graph = RenderGraph::new();
+        let a = graph.group(root);
+        let b = graph.group(root);
+        let c = graph.group(root);
+
+        graph.insert_before(c, a);
+        assert_eq!(children(&graph, root), [c, a, b]);
+        graph.insert_after(c, b);
+        assert_eq!(children(&graph, root), [a, b, c]);
+        graph.insert_after(a, c);
+        assert_eq!(children(&graph, root), [b, c, a]);
+
+        // Nodes move to their sibling's parent, along with their children.
+        let d = graph.draw_immediate(b, &vertices(3), &[0, 1, 2]);
+        let e = graph.group(a);
+        graph.insert_before(a, d);
+        assert_eq!(children(&graph, root), [b, c]);
+        assert_eq!(children(&graph, b), [a, d]);
+        assert_eq!(children(&graph, a), [e]);
+        assert_eq!(graph.parent(a), Some(b));
+        assert_eq!(graph.parent(root), None);
+    }
+
+    #[test]
+    #[should_panic = "a node cannot be moved into itself"]
+    fn insert_into_descendant() {
+        let mut graph = RenderGraph::new();
+        let parent = graph.group(RenderGraphNodeId::root());
+        let child = graph.group(parent);
+        graph.insert_after(parent, child);
+    }
+
+    #[test]
+    fn scoped_builder() {
+        let mut graph = RenderGraph::new();
+        let mut inner = None;
+        let group = graph.with_child(RenderGraphNodeId::root(), |group| {
+            group.draw_immediate(&vertices(3), &[0, 1, 2]);
+            group.with_transform(&Transform::identity(), |transform| {
+                inner = Some(transform.with_child(|_| {}));
+            });
+            assert_eq!(
+                group.try_draw_immediate(&vertices(3), &[0, 1, 2, 0]),
+                Err(RenderGraphError::IncompleteTriangle { num_indices: 4 })
+            );
+        });
+
+        let [mesh, transform] = children(&graph, group)[..] else {
+            panic!("expected two children");
+        };
+        assert!(matches!(graph.get(group), RenderGraphCommand::Group));
+        assert!(matches!(
+            graph.get(mesh),
+            RenderGraphCommand::DrawImmediate { .. }
+        ));
+        assert!(matches!(
+            graph.get(transform),
+            RenderGraphCommand::Transform { .. }
+        ));
+        assert_eq!(children(&graph, transform), [inner.unwrap()]);
+    }
+
+    fn quad(x: f32, width: f32) -> [Vertex; 4] {
+        [(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0)].map(|(u, v)| Vertex {
+            position: Point::new(x + u * width, v * 4.0),
+            color: Color::WHITE,
+        })
+    }
+
+    fn texture() -> Texture {
+        crate::GraphicsContext::new(&crate::GraphicsConfig::default())
+            .create_texture(geometry::Extent::new(1, 1), &[255; 4])
+    }
+
+    #[test]
+    fn batch_past_disjoint_draws() {
+        let texture = texture();
+        let root = RenderGraphNodeId::root();
+        let mut graph = RenderGraph::new();
+
+        let a = graph.draw_immediate(root, &quad(0.0, 4.0), &[0, 1, 2, 0, 2, 3]);
+        graph.draw_image(
+            root,
+            &texture,
+            &Rect::new(Point::new(4.0, 0.0), Point::new(8.0, 4.0)),
+            &ImageOptions::default(),
+        );
+        // Only touches the image, so it can be moved beneath it.
+        let c = graph.draw_immediate(root, &quad(8.0, 4.0), &[0, 1, 2]);
+
+        // Draws are not moved past nodes with children.
+        let transform = graph.transform(root, &Transform::identity());
+        graph.draw_immediate(transform, &quad(16.0, 4.0), &[0, 1, 2]);
+        graph.draw_immediate(root, &quad(20.0, 4.0), &[0, 1, 2]);
+
+        assert_eq!(
+            graph.batch(),
+            BatchStatistics {
+                draw_calls_before: 5,
+                draw_calls_after: 4,
+            }
+        );
+        assert!(matches!(graph.get(c), RenderGraphCommand::Group));
+
+        let RenderGraphCommand::DrawImmediate {
+            first_index,
+            num_indices,
+        } = *graph.get(a)
+        else {
+            panic!("expected an immediate draw");
+        };
+        assert_eq!(
+            graph
+                .imm_indices
+                .triangles(first_index, num_indices)
+                .collect::<Vec<_>>(),
+            [[0, 1, 2], [0, 2, 3], [4, 5, 6]]
+        );
+        assert_eq!(graph.imm_indices.len(), 15);
+        assert_eq!(graph.img_indices.len(), 6);
+    }
+
+    #[test]
+    fn batch_keeps_overlapping_draws() {
+        let texture = texture();
+        let root = RenderGraphNodeId::root();
+        let mut graph = RenderGraph::new();
+
+        let image = |filter| ImageOptions {
+            filter,
+            ..ImageOptions::default()
+        };
+        let rect = Rect::new(Point::new(2.0, 0.0), Point::new(6.0, 4.0));
+
+        graph.draw_immediate(root, &quad(0.0, 4.0), &[0, 1, 2, 0, 2, 3]);
+        graph.draw_image(root, &texture, &rect, &image(Filter::Linear));
+        // Overlaps the image, so it must be drawn over it.
+        graph.draw_immediate(root, &quad(4.0, 4.0), &[0, 1, 2, 0, 2, 3]);
+        // Differs in filter from the image beneath it.
+        graph.draw_image(root, &texture, &rect, &image(Filter::Nearest));
+
+        assert_eq!(
+            graph.batch(),
+            BatchStatistics {
+                draw_calls_before: 4,
+                draw_calls_after: 4,
+            }
+        );
+    }
+
+    #[test]
+    fn nine_slice_borders() {
+        assert_eq!(
+            slice_axis(10.0, 30.0, 8.0, (2.0, 4.0)),
+            ([10.0, 12.0, 26.0, 30.0], [0.0, 0.25, 0.5, 1.0])
+        );
+        // Borders shrink to fit, and follow flipped destinations.
+        assert_eq!(
+            slice_axis(3.0, 0.0, 8.0, (2.0, 4.0)),
+            ([3.0, 2.0, 2.0, 0.0], [0.0, 0.25, 0.5, 1.0])
+        );
+    }
+
+    #[test]
+    fn pixel_bounds_follow_pixel_centers() {
+        assert_eq!(pixel_bounds(&rect(1.0, 2.0, 3.0, 4.0)), rect(1, 2, 3, 4));
+        // Centers on the top or left edge are inside, but not those on the
+        // bottom or right edge.
+        assert_eq!(pixel_bounds(&rect(0.5, 0.5, 2.5, 2.5)), rect(0, 0, 2, 2));
+        assert_eq!(pixel_bounds(&rect(0.6, 0.4, 2.6, 2.4)), rect(1, 0, 3, 2));
+        assert_eq!(pixel_bounds(&rect(-5.0, -1.0, 2.0, 2.0)), rect(0, 0, 2, 2));
     }
 }
@@ -0,0 +1,1149 @@
+//! A CPU implementation of the graphics backend.
+//!
+//! The software backend renders into in-memory images and mirrors the behavior
+//! of the DX12 backend as closely as practical, so that render graph features
+//! can be developed and tested on machines without a supported GPU.
+
+use std::cell::RefCell;
+
+use geometry::{Extent, ObjectSpace, Point, ScreenSpace, Transform};
+use raw_window_handle::RawWindowHandle;
+
+use crate::{
+    render_graph::{pixel_bounds, RenderGraph, RenderGraphCommand},
+    Color, DrawError, Filter, GraphicsConfig, ImageFormat, RenderGraphNodeId, TexturedVertex,
+    UploadStatistics, Vertex,
+};
+
+mod raster;
+
+use self::raster::Canvas;
+
+pub struct GraphicsContext {}
+
+impl GraphicsContext {
+    /// The color that images are cleared to at the start of every draw. Must
+    /// match the DX12 backend.
+    const CLEAR_COLOR: Color = Color {
+        r: 0.5,
+        g: 0.5,
+        b: 0.5,
+        a: 1.0,
+    };
+
+    pub fn new(_config: &GraphicsConfig) -> Self {
+        Self {}
+    }
+
+    pub fn create_surface(&self, _window: RawWindowHandle) -> Surface {
+        panic!("the software backend cannot present to a window")
+    }
+
+    pub fn create_image(&self, extent: Extent<u32, ScreenSpace>, format: ImageFormat) -> Image {
+        Image::new(extent, format)
+    }
+
+    pub fn create_texture(&mut self, extent: Extent<u32, ScreenSpace>, pixels: &[u8]) -> Texture {
+        Texture {
+            extent,
+            texels: RefCell::new(pixels.chunks_exact(4).map(texel).collect()),
+        }
+    }
+
+    pub fn update_texture(
+        &mut self,
+        texture: &Texture,
+        origin: Point<u32, ScreenSpace>,
+        extent: Extent<u32, ScreenSpace>,
+        pixels: &[u8],
+    ) {
+        let mut texels = texture.texels.borrow_mut();
+        let row_size = 4 * extent.width as usize;
+        for (y, row) in (origin.y..).zip(pixels.chunks_exact(row_size)) {
+            let start = (y * texture.extent.width + origin.x) as usize;
+            for (dst, src) in texels[start..].iter_mut().zip(row.chunks_exact(4)) {
+                *dst = texel(src);
+            }
+        }
+    }
+
+    pub fn create_mesh(&mut self, vertices: &[Vertex], indices: &[u32]) -> Mesh {
+        Mesh {
+            vertices: vertices.to_vec(),
+            indices: indices.to_vec(),
+        }
+    }
+
+    pub fn draw(&mut self, target: &Image, content: &RenderGraph) -> Result<(), DrawError> {
+        let mut pixels = target.pixels.borrow_mut();
+        let mut canvas = Canvas::new(
+            Extent::new(target.extent.width, target.extent.height),
+            &mut pixels,
+        );
+
+        canvas.clear(Self::CLEAR_COLOR);
+
+        let transform = Transform::identity().then(content.camera());
+        Self::record_render_graph(&mut canvas, content, RenderGraphNodeId::root(), &transform);
+        Ok(())
+    }
+
+    /// Render graphs are drawn straight from CPU memory, so nothing is ever
+    /// uploaded.
+    pub fn upload_statistics(&self) -> UploadStatistics {
+        UploadStatistics::default()
+    }
+
+    pub fn read_pixels(&mut self, image: &Image) -> Vec<Color> {
+        image
+            .pixels
+            .borrow()
+            .iter()
+            .map(|pixel| {
+                let [r, g, b, a] = match image.format {
+                    ImageFormat::Rgba8 => pixel.map(unorm8),
+                    ImageFormat::Rgba16Float => *pixel,
+                };
+                Color::new(r, g, b, a)
+            })
+            .collect()
+    }
+
+    /// Draws `node` and its descendants. Vertices are transformed to screen
+    /// space here, which is the software equivalent of the vertex shader.
+    fn record_render_graph(
+        canvas: &mut Canvas,
+        content: &RenderGraph,
+        node: RenderGraphNodeId,
+        transform: &Transform<f32, ObjectSpace, ScreenSpace>,
+    ) {
+        let mut transform = *transform;
+        let outer_scissor = canvas.scissor();
+
+        match content.get(node) {
+            RenderGraphCommand::Root => assert_eq!(node, RenderGraphNodeId::root()),
+            RenderGraphCommand::Group => {}
+            RenderGraphCommand::DrawImmediate {
+                first_index,
+                num_indices,
+            } => fill_mesh(
+                canvas,
+                &transform,
+                &content.imm_vertices,
+                content.imm_indices.triangles(*first_index, *num_indices),
+            ),
+            RenderGraphCommand::DrawMesh { mesh } => {
+                let mesh = &content.meshes[*mesh as usize].inner;
+                fill_mesh(
+                    canvas,
+                    &transform,
+                    &mesh.vertices,
+                    mesh.indices
+                        .chunks_exact(3)
+                        .map(|triangle| [triangle[0], triangle[1], triangle[2]]),
+                );
+            }
+            RenderGraphCommand::DrawImage {
+                texture,
+                filter,
+                first_index,
+                num_indices,
+            } => {
+                let texture = &content.textures[*texture as usize].inner;
+                let vertex = |index: u32| {
+                    let vertex = &content.img_vertices[index as usize];
+                    let position = transform
+                        .transform_point(&Point::new(vertex.position.x, vertex.position.y));
+                    TexturedVertex {
+                        position: Point::new(position.x, position.y),
+                        ..*vertex
+                    }
+                };
+
+                for triangle in content.img_indices.triangles(*first_index, *num_indices) {
+                    canvas.fill_textured_triangle(
+                        &vertex(triangle[0]),
+                        &vertex(triangle[1]),
+                        &vertex(triangle[2]),
+                        |uv| texture.sample(uv, *filter),
+                    );
+                }
+            }
+            RenderGraphCommand::DrawGlyphRun {
+                cache,
+                first_index,
+                num_indices,
+            } => {
+                let texture = content.glyph_caches[*cache as usize].cache().texture();
+                let texture = &texture.inner;
+                // Glyph runs have texture coordinates in texels.
+                let extent = texture.extent();
+                let vertex = |index: u32| {
+                    let vertex = &content.img_vertices[index as usize];
+                    let position = transform
+                        .transform_point(&Point::new(vertex.position.x, vertex.position.y));
+                    TexturedVertex {
+                        position: Point::new(position.x, position.y),
+                        uv: Point::new(
+                            vertex.uv.x / extent.width as f32,
+                            vertex.uv.y / extent.height as f32,
+                        ),
+                        color: vertex.color,
+                    }
+                };
+
+                for triangle in content.img_indices.triangles(*first_index, *num_indices) {
+                    canvas.fill_textured_triangle(
+                        &vertex(triangle[0]),
+                        &vertex(triangle[1]),
+                        &vertex(triangle[2]),
+                        |uv| texture.sample(uv, Filter::Linear),
+                    );
+                }
+            }
+            RenderGraphCommand::Transform { transform: local } => {
+                transform = local.then(&transform.with_src().with_dst());
+            }
+            RenderGraphCommand::Clip { rect } => {
+                let Some(scissor) =
+                    pixel_bounds(&transform.transform_rect(rect)).intersection(&outer_scissor)
+                else {
+                    // Nothing inside the clip can be visible.
+                    return;
+                };
+                canvas.set_scissor(&scissor);
+            }
+            RenderGraphCommand::Layer {
+                opacity,
+                blend_mode,
+            } => {
+                let extent = canvas.extent();
+                let mut pixels = vec![[0.0; 4]; extent.width as usize * extent.height as usize];
+
+                let mut layer = Canvas::new(extent, &mut pixels);
+                layer.set_scissor(&outer_scissor);
+                for child in content.iter_children(node) {
+                    Self::record_render_graph(&mut layer, content, child, &transform);
+                }
+
+                canvas.composite(&pixels, *opacity, *blend_mode);
+                return;
+            }
+        }
+
+        for child in content.iter_children(node) {
+            Self::record_render_graph(canvas, content, child, &transform);
+        }
+
+        canvas.set_scissor(&outer_scissor);
+    }
+}
+
+/// The software backend cannot present to windows, so no surface can ever be
+/// created.
+pub enum Surface {}
+
+impl Surface {
+    pub fn get_next_image(&mut self) -> SurfaceImage<'_> {
+        match *self {}
+    }
+
+    pub fn resize(&mut self) {
+        match *self {}
+    }
+
+    pub fn request_capture(&self) {
+        match *self {}
+    }
+
+    pub fn capture(&self) -> Option<(Extent<u32, ScreenSpace>, Vec<Color>)> {
+        match *self {}
+    }
+}
+
+#[allow(clippy::module_name_repetitions)]
+pub struct SurfaceImage<'a> {
+    surface: &'a Surface,
+}
+
+impl SurfaceImage<'_> {
+    pub fn present(self) {
+        match *self.surface {}
+    }
+
+    pub fn get_image(&self) -> &Image {
+        match *self.surface {}
+    }
+}
+
+/// An image stored in CPU memory.
+///
+/// Pixels are always stored as 32-bit floating point RGBA, regardless of
+/// format. The format is applied when the image is read back.
+pub struct Image {
+    extent: Extent<u32, ScreenSpace>,
+    format: ImageFormat,
+    pixels: RefCell<Vec<[f32; 4]>>,
+}
+
+impl Image {
+    fn new(extent: Extent<u32, ScreenSpace>, format: ImageFormat) -> Self {
+        let num_pixels = extent.width as usize * extent.height as usize;
+        Self {
+            extent,
+            format,
+            pixels: RefCell::new(vec![[0.0; 4]; num_pixels]),
+        }
+    }
+
+    pub fn extent(&self) -> Extent<u32, ScreenSpace> {
+        Extent::new(self.extent.width, self.extent.height)
+    }
+
+    pub fn format(&self) -> ImageFormat {
+        self.format
+    }
+}
+
+/// A texture stored in CPU memory as premultiplied 32-bit floating point RGBA.
+pub struct Texture {
+    extent: Extent<u32, ScreenSpace>,
+    texels: RefCell<Vec<[f32; 4]>>,
+}
+
+impl Texture {
+    pub fn extent(&self) -> Extent<u32, ScreenSpace> {
+        self.extent
+    }
+
+    /// Samples the texture the way the DX12 backend's samplers do, clamping
+    /// coordinates outside of the texture to its edges.
+    #[allow(
+        clippy::cast_possible_truncation,
+        clippy::cast_possible_wrap,
+        clippy::cast_precision_loss
+    )]
+    fn sample(&self, uv: &Point<f32>, filter: Filter) -> [f32; 4] {
+        let (width, height) = (self.extent.width as i32, self.extent.height as i32);
+        let texels = self.texels.borrow();
+        let texel = |x: i32, y: i32| {
+            let x = x.clamp(0, width - 1);
+            let y = y.clamp(0, height - 1);
+            texels[(y * width + x) as usize]
+        };
+
+        let x = uv.x * width as f32;
+        let y = uv.y * height as f32;
+
+        match filter {
+            Filter::Nearest => texel(x.floor() as i32, y.floor() as i32),
+            Filter::Linear => {
+                // Interpolate between the centers of the surrounding texels.
+                let (x, y) = (x - 0.5, y - 0.5);
+                let (x0, y0) = (x.floor(), y.floor());
+                let (fx, fy) = (x - x0, y - y0);
+                let (x0, y0) = (x0 as i32, y0 as i32);
+
+                let [t00, t10, t01, t11] = [
+                    texel(x0, y0),
+                    texel(x0 + 1, y0),
+                    texel(x0, y0 + 1),
+                    texel(x0 + 1, y0 + 1),
+                ];
+
+                std::array::from_fn(|i| {
+                    let top = t00[i] + (t10[i] - t00[i]) * fx;
+                    let bottom = t01[i] + (t11[i] - t01[i]) * fx;
+                    top + (bottom - top) * fy
+                })
+            }
+        }
+    }
+}
+
+/// An immutable mesh stored in CPU memory.
+pub struct Mesh {
+    vertices: Vec<Vertex>,
+    indices: Vec<u32>,
+}
+
+impl Mesh {
+    pub fn num_vertices(&self) -> usize {
+        self.vertices.len()
+    }
+
+    pub fn num_indices(&self) -> usize {
+        self.indices.len()
+    }
+}
+
+/// Fills triangles of untextured vertices, transforming the vertices to
+/// screen space.
+fn fill_mesh(
+    canvas: &mut Canvas,
+    transform: &Transform<f32, ObjectSpace, ScreenSpace>,
+    vertices: &[Vertex],
+    triangles: impl Iterator<Item = [u32; 3]>,
+) {
+    let vertex = |index: u32| {
+        let vertex = &vertices[index as usize];
+        let position = transform.transform_point(&Point::new(vertex.position.x, vertex.position.y));
+        Vertex {
+            position: Point::new(position.x, position.y),
+            color: vertex.color,
+        }
+    };
+
+    for triangle in triangles {
+        canvas.fill_triangle(
+            &vertex(triangle[0]),
+            &vertex(triangle[1]),
+            &vertex(triangle[2]),
+        );
+    }
+}
+
+/// Converts an 8-bit RGBA texel to floating point.
+fn texel(rgba: &[u8]) -> [f32; 4] {
+    std::array::from_fn(|i| f32::from(rgba[i]) / 255.0)
+}
+
+/// Rounds a channel to the nearest value representable in 8-bit unsigned
+/// normalized form.
+#[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+fn unorm8(value: f32) -> f32 {
+    f32::from((value.clamp(0.0, 1.0) * 255.0).round() as u8) / 255.0
+}
+
+#[cfg(test)]
+mod tests {
+    use geometry::{Insets, Offset, Rect, Scale, WorldSpace};
+
+    use super::*;
+    use crate::{BlendMode, ImageOptions};
+
+    fn vertex(x: f32, y: f32, color: Color) -> Vertex {
+        Vertex {
+            position: Point::new(x, y),
+            color,
+        }
+    }
+
+    fn pixel(image: &Image, x: u32, y: u32) -> [f32; 4] {
+        image.pixels.borrow()[(y * image.extent.width + x) as usize]
+    }
+
+    #[test]
+    fn draw_triangle() {
+        let mut context = GraphicsContext::new(&GraphicsConfig::default());
+        let image = Image::new(Extent::new(4, 4), ImageFormat::Rgba16Float);
+
+        let mut graph = RenderGraph::new();
+        graph.draw_immediate(
+            RenderGraphNodeId::root(),
+            &[
+                vertex(0.0, 0.0, Color::RED),
+                vertex(4.0, 0.0, Color::RED),
+                vertex(0.0, 4.0, Color::RED),
+            ],
+            &[0, 1, 2],
+        );
+
+        context.draw(&image, &graph).unwrap();
+
+        assert_eq!(pixel(&image, 0, 0), [1.0, 0.0, 0.0, 1.0]);
+        assert_eq!(pixel(&image, 1, 1), [1.0, 0.0, 0.0, 1.0]);
+        assert_eq!(pixel(&image, 3, 3), [0.5, 0.5, 0.5, 1.0]);
+        // The pixel center at (2.5, 1.5) lies on the hypotenuse, which is a
+        // right edge.
+        assert_eq!(pixel(&image, 2, 1), [0.5, 0.5, 0.5, 1.0]);
+    }
+
+    #[test]
+    fn draw_past_16_bit_indices() {
+        let mut context = GraphicsContext::new(&GraphicsConfig::default());
+        let image = Image::new(Extent::new(4, 4), ImageFormat::Rgba16Float);
+
+        // Fill the graph with vertices that are never drawn, so that the
+        // triangle's vertices are past what 16-bit indices can address.
+        let mut graph = RenderGraph::new();
+        let unused = vec![vertex(0.0, 0.0, Color::BLUE); 40_000];
+        for _ in 0..2 {
+            graph.draw_immediate(RenderGraphNodeId::root(), &unused, &[]);
+        }
+
+        // A single mesh may also have more vertices than 16 bits can address.
+        let mut vertices = vec![vertex(0.0, 0.0, Color::BLUE); 70_000];
+        vertices.extend([
+            vertex(0.0, 0.0, Color::RED),
+            vertex(4.0, 0.0, Color::RED),
+            vertex(0.0, 4.0, Color::RED),
+        ]);
+        graph
+            .try_draw_immediate(
+                RenderGraphNodeId::root(),
+                &vertices,
+                &[70_000, 70_001, 70_002],
+            )
+            .unwrap();
+
+        context.draw(&image, &graph).unwrap();
+
+        assert_eq!(pixel(&image, 0, 0), [1.0, 0.0, 0.0, 1.0]);
+        assert_eq!(pixel(&image, 3, 3), [0.5, 0.5, 0.5, 1.0]);
+    }
+
+    #[test]
+    fn draw_mesh() {
+        let mut context = GraphicsContext::new(&GraphicsConfig::default());
+        let image = Image::new(Extent::new(4, 4), ImageFormat::Rgba16Float);
+
+        let mesh = crate::MeshHandle {
+            inner: std::rc::Rc::new(
+                context.create_mesh(&square(0.0, 0.0, 2.0, Color::RED), &[0, 1, 2, 0, 2, 3]),
+            ),
+        };
+
+        // The same mesh is drawn twice, and the graph keeps it alive after the
+        // handle is dropped.
+        let mut graph = RenderGraph::new();
+        graph.draw_mesh(RenderGraphNodeId::root(), &mesh);
+        let offset = graph.transform(
+            RenderGraphNodeId::root(),
+            &Transform::translate(Offset::new(2.0, 2.0)),
+        );
+        graph.draw_mesh(offset, &mesh);
+        drop(mesh);
+
+        context.draw(&image, &graph).unwrap();
+
+        assert_eq!(pixel(&image, 1, 1), [1.0, 0.0, 0.0, 1.0]);
+        assert_eq!(pixel(&image, 3, 3), [1.0, 0.0, 0.0, 1.0]);
+        assert_eq!(pixel(&image, 3, 0), [0.5, 0.5, 0.5, 1.0]);
+    }
+
+    #[test]
+    fn fill_counter_clockwise() {
+        let mut context = GraphicsContext::new(&GraphicsConfig::default());
+        let image = Image::new(Extent::new(4, 4), ImageFormat::Rgba16Float);
+
+        let mut graph = RenderGraph::new();
+        graph.draw_immediate(
+            RenderGraphNodeId::root(),
+            &[
+                vertex(0.0, 0.0, Color::RED),
+                vertex(0.0, 4.0, Color::GREEN),
+                vertex(4.0, 0.0, Color::BLUE),
+            ],
+            &[0, 1, 2],
+        );
+
+        context.draw(&image, &graph).unwrap();
+
+        // Colors are interpolated from the vertices they belong to, whatever
+        // the winding.
+        assert_eq!(pixel(&image, 0, 0), [0.75, 0.125, 0.125, 1.0]);
+        assert_eq!(pixel(&image, 0, 2), [0.25, 0.625, 0.125, 1.0]);
+        assert_eq!(pixel(&image, 3, 3), [0.5, 0.5, 0.5, 1.0]);
+    }
+
+    #[test]
+    fn shared_edges_are_covered_once() {
+        let mut context = GraphicsContext::new(&GraphicsConfig::default());
+        let image = Image::new(Extent::new(4, 4), ImageFormat::Rgba16Float);
+
+        // Two triangles forming a square, split along the diagonal.
+        let mut graph = RenderGraph::new();
+        graph.draw_immediate(
+            RenderGraphNodeId::root(),
+            &[
+                vertex(0.0, 0.0, Color::RED),
+                vertex(4.0, 0.0, Color::RED),
+                vertex(0.0, 4.0, Color::RED),
+            ],
+            &[0, 1, 2],
+        );
+        graph.draw_immediate(
+            RenderGraphNodeId::root(),
+            &[
+                vertex(4.0, 0.0, Color::BLUE),
+                vertex(4.0, 4.0, Color::BLUE),
+                vertex(0.0, 4.0, Color::BLUE),
+            ],
+            &[0, 1, 2],
+        );
+
+        context.draw(&image, &graph).unwrap();
+
+        // Every pixel is covered by exactly one of the triangles. The diagonal
+        // is a left edge of the second triangle, so it wins.
+        for y in 0..4 {
+            for x in 0..4 {
+                let expected = if x + y < 3 {
+                    [1.0, 0.0, 0.0, 1.0]
+                } else {
+                    [0.0, 0.0, 1.0, 1.0]
+                };
+                assert_eq!(pixel(&image, x, y), expected, "pixel ({x}, {y})");
+            }
+        }
+    }
+
+    #[test]
+    fn interpolate_vertex_colors() {
+        let mut context = GraphicsContext::new(&GraphicsConfig::default());
+        let image = Image::new(Extent::new(2, 1), ImageFormat::Rgba16Float);
+
+        let mut graph = RenderGraph::new();
+        graph.draw_immediate(
+            RenderGraphNodeId::root(),
+            &[
+                vertex(0.0, 0.0, Color::RED),
+                vertex(2.0, 0.0, Color::GREEN),
+                vertex(2.0, 1.0, Color::GREEN),
+                vertex(0.0, 1.0, Color::RED),
+            ],
+            &[0, 1, 2, 0, 2, 3],
+        );
+
+        context.draw(&image, &graph).unwrap();
+
+        let [r, g, b, a] = pixel(&image, 0, 0);
+        assert!((r - 0.75).abs() < 1e-6 && (g - 0.25).abs() < 1e-6);
+        assert_eq!([b, a], [0.0, 1.0]);
+    }
+
+    #[test]
+    fn nested_transforms() {
+        let mut context = GraphicsContext::new(&GraphicsConfig::default());
+        let image = Image::new(Extent::new(8, 8), ImageFormat::Rgba16Float);
+
+        // A unit square, scaled up to 2x2 and then moved to (4, 2).
+        let mut graph = RenderGraph::new();
+        let outer = graph.transform(
+            RenderGraphNodeId::root(),
+            &Transform::translate(Offset::new(4.0, 2.0)),
+        );
+        let inner = graph.transform(outer, &Transform::scale(Scale::new(2.0, 2.0)));
+        graph.draw_immediate(
+            inner,
+            &[
+                vertex(0.0, 0.0, Color::RED),
+                vertex(1.0, 0.0, Color::RED),
+                vertex(1.0, 1.0, Color::RED),
+                vertex(0.0, 1.0, Color::RED),
+            ],
+            &[0, 1, 2, 0, 2, 3],
+        );
+
+        // Siblings are unaffected by each other's transforms.
+        graph.draw_immediate(
+            RenderGraphNodeId::root(),
+            &[
+                vertex(0.0, 0.0, Color::BLUE),
+                vertex(2.0, 0.0, Color::BLUE),
+                vertex(0.0, 2.0, Color::BLUE),
+            ],
+            &[0, 1, 2],
+        );
+
+        context.draw(&image, &graph).unwrap();
+
+        assert_eq!(pixel(&image, 0, 0), [0.0, 0.0, 1.0, 1.0]);
+        assert_eq!(pixel(&image, 4, 2), [1.0, 0.0, 0.0, 1.0]);
+        assert_eq!(pixel(&image, 5, 3), [1.0, 0.0, 0.0, 1.0]);
+        assert_eq!(pixel(&image, 6, 2), [0.5, 0.5, 0.5, 1.0]);
+        assert_eq!(pixel(&image, 3, 2), [0.5, 0.5, 0.5, 1.0]);
+    }
+
+    #[test]
+    fn camera() {
+        let mut context = GraphicsContext::new(&GraphicsConfig::default());
+        let image = Image::new(Extent::new(8, 8), ImageFormat::Rgba16Float);
+
+        let mut graph = RenderGraph::new();
+        let node = graph.transform(
+            RenderGraphNodeId::root(),
+            &Transform::translate(Offset::new(1.0, 1.0)),
+        );
+        graph.draw_immediate(
+            node,
+            &[
+                vertex(0.0, 0.0, Color::RED),
+                vertex(1.0, 0.0, Color::RED),
+                vertex(1.0, 1.0, Color::RED),
+                vertex(0.0, 1.0, Color::RED),
+            ],
+            &[0, 1, 2, 0, 2, 3],
+        );
+
+        // Zoom in by 4x around the origin, then pan left by a pixel. The camera
+        // applies after the node's transform.
+        graph.set_camera(
+            Transform::<f32, WorldSpace, ScreenSpace>::scale(Scale::new(4.0, 4.0))
+                .then_translate(Offset::new(-1.0, 0.0)),
+        );
+        context.draw(&image, &graph).unwrap();
+
+        for y in 0..8 {
+            for x in 0..8 {
+                let expected = if (3..7).contains(&x) && (4..8).contains(&y) {
+                    [1.0, 0.0, 0.0, 1.0]
+                } else {
+                    [0.5, 0.5, 0.5, 1.0]
+                };
+                assert_eq!(pixel(&image, x, y), expected, "pixel ({x}, {y})");
+            }
+        }
+    }
+
+    #[test]
+    fn nested_clips() {
+        let mut context = GraphicsContext::new(&GraphicsConfig::default());
+        let image = Image::new(Extent::new(8, 8), ImageFormat::Rgba16Float);
+
+        let full_screen = |color| {
+            [
+                vertex(0.0, 0.0, color),
+                vertex(8.0, 0.0, color),
+                vertex(8.0, 8.0, color),
+                vertex(0.0, 8.0, color),
+            ]
+        };
+
+        let mut graph = RenderGraph::new();
+        let outer = graph.clip(
+            RenderGraphNodeId::root(),
+            &Rect::new(Point::new(1.0, 1.0), Point::new(6.0, 6.0)),
+        );
+        // The inner clip is in the space of its transformed parent, and is
+        // intersected with the outer clip.
+        let offset = graph.transform(outer, &Transform::translate(Offset::new(2.0, 2.0)));
+        let inner = graph.clip(
+            offset,
+            &Rect::new(Point::new(0.0, 0.0), Point::new(6.0, 2.0)),
+        );
+        graph.draw_immediate(inner, &full_screen(Color::RED), &[0, 1, 2, 0, 2, 3]);
+
+        // The outer clip is restored once the inner clip's subtree is done.
+        graph.draw_immediate(
+            outer,
+            &[
+                vertex(0.0, 0.0, Color::BLUE),
+                vertex(8.0, 0.0, Color::BLUE),
+                vertex(8.0, 2.0, Color::BLUE),
+                vertex(0.0, 2.0, Color::BLUE),
+            ],
+            &[0, 1, 2, 0, 2, 3],
+        );
+
+        context.draw(&image, &graph).unwrap();
+
+        for y in 0..8 {
+            for x in 0..8 {
+                let expected = if (1..6).contains(&x) && y == 1 {
+                    [0.0, 0.0, 1.0, 1.0]
+                } else if (2..6).contains(&x) && (2..4).contains(&y) {
+                    [1.0, 0.0, 0.0, 1.0]
+                } else {
+                    [0.5, 0.5, 0.5, 1.0]
+                };
+                assert_eq!(pixel(&image, x, y), expected, "pixel ({x}, {y})");
+            }
+        }
+    }
+
+    fn square(x: f32, y: f32, size: f32, color: Color) -> [Vertex; 4] {
+        [
+            vertex(x, y, color),
+            vertex(x + size, y, color),
+            vertex(x + size, y + size, color),
+            vertex(x, y + size, color),
+        ]
+    }
+
+    #[test]
+    fn blend_translucent_colors() {
+        let mut context = GraphicsContext::new(&GraphicsConfig::default());
+        let image = Image::new(Extent::new(2, 2), ImageFormat::Rgba16Float);
+
+        let mut graph = RenderGraph::new();
+        graph.draw_immediate(
+            RenderGraphNodeId::root(),
+            &square(0.0, 0.0, 2.0, Color::new(1.0, 0.0, 0.0, 0.5)),
+            &[0, 1, 2, 0, 2, 3],
+        );
+
+        context.draw(&image, &graph).unwrap();
+
+        assert_eq!(pixel(&image, 1, 1), [0.75, 0.25, 0.25, 1.0]);
+    }
+
+    #[test]
+    fn group_opacity() {
+        let mut context = GraphicsContext::new(&GraphicsConfig::default());
+        let image = Image::new(Extent::new(4, 1), ImageFormat::Rgba16Float);
+
+        // Two overlapping squares faded together. The first square must not
+        // show through the second.
+        let mut graph = RenderGraph::new();
+        let layer = graph.layer(RenderGraphNodeId::root(), 0.5, BlendMode::Normal);
+        graph.draw_immediate(
+            layer,
+            &square(0.0, 0.0, 2.0, Color::RED),
+            &[0, 1, 2, 0, 2, 3],
+        );
+        graph.draw_immediate(
+            layer,
+            &square(1.0, 0.0, 2.0, Color::BLUE),
+            &[0, 1, 2, 0, 2, 3],
+        );
+
+        context.draw(&image, &graph).unwrap();
+
+        assert_eq!(pixel(&image, 0, 0), [0.75, 0.25, 0.25, 1.0]);
+        assert_eq!(pixel(&image, 1, 0), [0.25, 0.25, 0.75, 1.0]);
+        assert_eq!(pixel(&image, 2, 0), [0.25, 0.25, 0.75, 1.0]);
+        assert_eq!(pixel(&image, 3, 0), [0.5, 0.5, 0.5, 1.0]);
+    }
+
+    #[test]
+    fn blend_modes() {
+        let cases = [
+            (BlendMode::Normal, [0.2, 0.8, 0.0, 1.0]),
+            (BlendMode::Multiply, [0.1, 0.4, 0.0, 1.0]),
+            (BlendMode::Screen, [0.6, 0.9, 0.5, 1.0]),
+            (BlendMode::Additive, [0.7, 1.3, 0.5, 2.0]),
+            (BlendMode::Clear, [0.0, 0.0, 0.0, 0.0]),
+            (BlendMode::Source, [0.2, 0.8, 0.0, 1.0]),
+            (BlendMode::Destination, [0.5, 0.5, 0.5, 1.0]),
+            (BlendMode::DestinationOut, [0.0, 0.0, 0.0, 0.0]),
+        ];
+
+        let mut context = GraphicsContext::new(&GraphicsConfig::default());
+        let image = Image::new(Extent::new(1, 1), ImageFormat::Rgba16Float);
+
+        for (blend_mode, expected) in cases {
+            let mut graph = RenderGraph::new();
+            let layer = graph.layer(RenderGraphNodeId::root(), 1.0, blend_mode);
+            graph.draw_immediate(
+                layer,
+                &square(0.0, 0.0, 1.0, Color::new(0.2, 0.8, 0.0, 1.0)),
+                &[0, 1, 2, 0, 2, 3],
+            );
+
+            context.draw(&image, &graph).unwrap();
+
+            let actual = pixel(&image, 0, 0);
+            assert!(
+                actual
+                    .iter()
+                    .zip(expected)
+                    .all(|(a, e)| (a - e).abs() < 1e-6),
+                "{blend_mode:?}: {actual:?} != {expected:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn nested_layers() {
+        let mut context = GraphicsContext::new(&GraphicsConfig::default());
+        let image = Image::new(Extent::new(4, 1), ImageFormat::Rgba16Float);
+
+        // The inner layer punches a hole through the outer layer's contents,
+        // but not through what is beneath the outer layer.
+        let mut graph = RenderGraph::new();
+        let outer = graph.layer(RenderGraphNodeId::root(), 1.0, BlendMode::Normal);
+        graph.draw_immediate(
+            outer,
+            &square(0.0, 0.0, 4.0, Color::RED),
+            &[0, 1, 2, 0, 2, 3],
+        );
+        let hole = graph.layer(outer, 1.0, BlendMode::DestinationOut);
+        graph.draw_immediate(
+            hole,
+            &square(1.0, 0.0, 2.0, Color::BLUE),
+            &[0, 1, 2, 0, 2, 3],
+        );
+
+        context.draw(&image, &graph).unwrap();
+
+        assert_eq!(pixel(&image, 0, 0), [1.0, 0.0, 0.0, 1.0]);
+        assert_eq!(pixel(&image, 1, 0), [0.5, 0.5, 0.5, 1.0]);
+        assert_eq!(pixel(&image, 2, 0), [0.5, 0.5, 0.5, 1.0]);
+        assert_eq!(pixel(&image, 3, 0), [1.0, 0.0, 0.0, 1.0]);
+    }
+
+    fn texture(context: &mut GraphicsContext, width: u32, texels: &[[u8; 4]]) -> crate::Texture {
+        let height = texels.len() as u32 / width;
+        crate::Texture {
+            inner: std::rc::Rc::new(
+                context.create_texture(Extent::new(width, height), texels.as_flattened()),
+            ),
+        }
+    }
+
+    #[test]
+    fn draw_image_nearest() {
+        let mut context = GraphicsContext::new(&GraphicsConfig::default());
+        let image = Image::new(Extent::new(4, 4), ImageFormat::Rgba16Float);
+        let texture = texture(
+            &mut context,
+            2,
+            &[
+                [255, 0, 0, 255],
+                [0, 255, 0, 255],
+                [0, 0, 255, 255],
+                [0, 0, 0, 0],
+            ],
+        );
+
+        let mut graph = RenderGraph::new();
+        graph.draw_image(
+            RenderGraphNodeId::root(),
+            &texture,
+            &Rect::new(Point::new(0.0, 0.0), Point::new(4.0, 4.0)),
+            &ImageOptions {
+                filter: Filter::Nearest,
+                ..ImageOptions::default()
+            },
+        );
+
+        context.draw(&image, &graph).unwrap();
+
+        assert_eq!(pixel(&image, 1, 1), [1.0, 0.0, 0.0, 1.0]);
+        assert_eq!(pixel(&image, 2, 1), [0.0, 1.0, 0.0, 1.0]);
+        assert_eq!(pixel(&image, 1, 2), [0.0, 0.0, 1.0, 1.0]);
+        // Transparent texels let the background through.
+        assert_eq!(pixel(&image, 3, 3), [0.5, 0.5, 0.5, 1.0]);
+    }
+
+    #[test]
+    fn draw_image_linear() {
+        let mut context = GraphicsContext::new(&GraphicsConfig::default());
+        let image = Image::new(Extent::new(4, 1), ImageFormat::Rgba16Float);
+        let texture = texture(&mut context, 2, &[[0, 0, 0, 255], [255, 255, 255, 255]]);
+
+        let mut graph = RenderGraph::new();
+        graph.draw_image(
+            RenderGraphNodeId::root(),
+            &texture,
+            &Rect::new(Point::new(0.0, 0.0), Point::new(4.0, 1.0)),
+            &ImageOptions::default(),
+        );
+
+        context.draw(&image, &graph).unwrap();
+
+        // Coordinates beyond the outer texel centers are clamped.
+        for (x, expected) in [0.0, 0.25, 0.75, 1.0].into_iter().enumerate() {
+            assert_eq!(
+                pixel(&image, x as u32, 0),
+                [expected, expected, expected, 1.0]
+            );
+        }
+    }
+
+    #[test]
+    fn draw_image_tint() {
+        let mut context = GraphicsContext::new(&GraphicsConfig::default());
+        let image = Image::new(Extent::new(1, 1), ImageFormat::Rgba16Float);
+        let texture = texture(&mut context, 1, &[[255, 255, 255, 255]]);
+
+        let mut graph = RenderGraph::new();
+        graph.draw_image(
+            RenderGraphNodeId::root(),
+            &texture,
+            &Rect::new(Point::new(0.0, 0.0), Point::new(1.0, 1.0)),
+            &ImageOptions {
+                tint: Color::new(1.0, 0.0, 0.0, 0.5),
+                ..ImageOptions::default()
+            },
+        );
+
+        context.draw(&image, &graph).unwrap();
+
+        assert_eq!(pixel(&image, 0, 0), [0.75, 0.25, 0.25, 1.0]);
+    }
+
+    #[test]
+    fn draw_image_nine_slice() {
+        let mut context = GraphicsContext::new(&GraphicsConfig::default());
+        let image = Image::new(Extent::new(8, 6), ImageFormat::Rgba16Float);
+
+        // A 3x3 texture with a blue center and red borders.
+        let (r, b) = ([255, 0, 0, 255], [0, 0, 255, 255]);
+        let texture = texture(&mut context, 3, &[r, r, r, r, b, r, r, r, r]);
+
+        let mut graph = RenderGraph::new();
+        graph.draw_image(
+            RenderGraphNodeId::root(),
+            &texture,
+            &Rect::new(Point::new(0.0, 0.0), Point::new(8.0, 6.0)),
+            &ImageOptions {
+                filter: Filter::Nearest,
+                nine_slice: Some(Insets::uniform(1.0)),
+                ..ImageOptions::default()
+            },
+        );
+
+        context.draw(&image, &graph).unwrap();
+
+        // Borders stay one pixel wide, while the center stretches.
+        for y in 0..6 {
+            for x in 0..8 {
+                let expected = if (1..7).contains(&x) && (1..5).contains(&y) {
+                    [0.0, 0.0, 1.0, 1.0]
+                } else {
+                    [1.0, 0.0, 0.0, 1.0]
+                };
+                assert_eq!(pixel(&image, x, y), expected, "pixel ({x}, {y})");
+            }
+        }
+    }
+
+    #[test]
+    fn read_back_rgba8() {
+        let mut context = GraphicsContext::new(&GraphicsConfig::default());
+        let image = context.create_image(Extent::new(2, 2), ImageFormat::Rgba8);
+
+        let mut graph = RenderGraph::new();
+        graph.draw_immediate(
+            RenderGraphNodeId::root(),
+            &[
+                vertex(0.0, 0.0, Color::new(0.2, 1.5, -1.0, 1.0)),
+                vertex(2.0, 0.0, Color::new(0.2, 1.5, -1.0, 1.0)),
+                vertex(0.0, 2.0, Color::new(0.2, 1.5, -1.0, 1.0)),
+            ],
+            &[0, 1, 2],
+        );
+
+        context.draw(&image, &graph).unwrap();
+        let pixels = context.read_pixels(&image);
+
+        assert_eq!(pixels.len(), 4);
+        assert_eq!(pixels[0], Color::new(51.0 / 255.0, 1.0, 0.0, 1.0));
+        assert_eq!(
+            pixels[3],
+            Color::new(128.0 / 255.0, 128.0 / 255.0, 128.0 / 255.0, 1.0)
+        );
+    }
+
+    #[test]
+    fn capture_rgba16_float() {
+        let context = crate::GraphicsContext::new(&GraphicsConfig::default());
+        let image = context.create_image(Extent::new(2, 2), ImageFormat::Rgba16Float);
+
+        let bright = Color::new(2.0, 1.0, 0.0, 1.0);
+        let mut graph = RenderGraph::new();
+        graph.draw_immediate(
+            RenderGraphNodeId::root(),
+            &[
+                vertex(0.0, 0.0, bright),
+                vertex(2.0, 0.0, bright),
+                vertex(0.0, 2.0, bright),
+            ],
+            &[0, 1, 2],
+        );
+
+        context.draw(&image, &graph).unwrap();
+        let bitmap = context.capture(&image);
+
+        assert_eq!(bitmap.extent(), Extent::new(2, 2));
+        assert_eq!(bitmap.pixels()[..4], [255, 188, 0, 255]);
+        assert_eq!(bitmap.pixels()[12..], [188, 188, 188, 255]);
+    }
+
+    #[test]
+    fn draw_glyph_run() {
+        let context = crate::GraphicsContext::new(&GraphicsConfig::default());
+        let image = context.create_image(Extent::new(8, 10), ImageFormat::Rgba16Float);
+        let font = crate::text::test_font::test_font();
+        let cache = crate::GlyphCache::new();
+        let i = font.glyph_index('I').unwrap();
+
+        let draw = |x: f32| {
+            let mut graph = RenderGraph::new();
+            graph.draw_glyph_run(
+                RenderGraphNodeId::root(),
+                &cache,
+                &font,
+                10.0,
+                &[crate::PositionedGlyph {
+                    glyph: i,
+                    position: Point::new(x, 9.0),
+                }],
+                Color::RED,
+            );
+            context.draw(&image, &graph).unwrap();
+            context.read_pixels(&image)
+        };
+
+        // Glyphs are sampled with linear filtering, which is only exact up to
+        // rounding.
+        let close = |a: f32, b: f32| (a - b).abs() < 1e-5;
+
+        // At 10px, `I` covers 2 pixels from 1 pixel after the pen, and 7
+        // pixels above the baseline.
+        let pixels = draw(2.0);
+        for (index, pixel) in pixels.iter().enumerate() {
+            let (x, y) = (index % 8, index / 8);
+            let expected = if (3..5).contains(&x) && (2..9).contains(&y) {
+                Color::RED
+            } else {
+                Color::new(0.5, 0.5, 0.5, 1.0)
+            };
+            assert!(
+                close(pixel.r, expected.r) && close(pixel.g, expected.g),
+                "pixel ({x}, {y}) is {pixel:?}"
+            );
+        }
+
+        // A new subpixel position is added to the atlas, and only its region
+        // is uploaded.
+        let pixels = draw(2.5);
+        assert_eq!(cache.len(), 2);
+        for (pixel, expected) in pixels[5 * 8 + 3..5 * 8 + 6].iter().zip([0.25, 0.0, 0.25]) {
+            assert!((pixel.g - expected).abs() < 1.0 / 255.0, "{pixel:?}");
+        }
+    }
+
+    #[test]
+    fn redraw_glyph_run_after_eviction() {
+        let context = crate::GraphicsContext::new(&GraphicsConfig::default());
+        let image = context.create_image(Extent::new(8, 10), ImageFormat::Rgba16Float);
+        let font = crate::text::test_font::test_font();
+        // Room for about two `I`s, so that drawing more evicts glyphs.
+        let cache = crate::GlyphCache::with_limits(8, 8);
+        let i = font.glyph_index('I').unwrap();
+
+        let graph = |positions: &[f32]| {
+            let glyphs: Vec<_> = positions
+                .iter()
+                .map(|&x| crate::PositionedGlyph {
+                    glyph: i,
+                    position: Point::new(x, 9.0),
+                })
+                .collect();
+            let mut graph = RenderGraph::new();
+            graph.draw_glyph_run(
+                RenderGraphNodeId::root(),
+                &cache,
+                &font,
+                10.0,
+                &glyphs,
+                Color::RED,
+            );
+            graph
+        };
+
+        let retained = graph(&[2.0]);
+        context.draw(&image, &retained).unwrap();
+        let expected = context.read_pixels(&image);
+
+        // Another graph fills the atlas. The retained graph's glyphs are not
+        // evicted to make room, so it draws the same as before.
+        context.draw(&image, &graph(&[0.25, 0.5, 0.75])).unwrap();
+        context.draw(&image, &retained).unwrap();
+        assert_eq!(context.read_pixels(&image), expected);
+    }
+}
@@ -0,0 +1,248 @@
+use geometry::{Extent, Offset, Point, Rect, ScreenSpace};
+
+use crate::{
+    render_graph::{BlendEquation, BlendFactor, BlendMode},
+    Color, TexturedVertex, Vertex,
+};
+
+/// A mutable view into an image's pixels, stored row-major with the origin in
+/// the top-left corner.
+pub struct Canvas<'a> {
+    extent: Extent<u32, ScreenSpace>,
+    pixels: &'a mut [[f32; 4]],
+    scissor: Rect<u32, ScreenSpace>,
+}
+
+impl<'a> Canvas<'a> {
+    pub fn new(extent: Extent<u32, ScreenSpace>, pixels: &'a mut [[f32; 4]]) -> Self {
+        debug_assert_eq!(pixels.len(), extent.width as usize * extent.height as usize);
+        Self {
+            extent,
+            pixels,
+            scissor: Rect::new(Point::new(0, 0), Point::new(extent.width, extent.height)),
+        }
+    }
+
+    /// The rectangle of pixels that triangles are restricted to.
+    pub fn scissor(&self) -> Rect<u32, ScreenSpace> {
+        self.scissor
+    }
+
+    /// Restricts drawing to a rectangle of pixels. The rectangle is clamped to
+    /// the canvas.
+    pub fn set_scissor(&mut self, scissor: &Rect<u32, ScreenSpace>) {
+        let canvas = Rect::new(
+            Point::new(0, 0),
+            Point::new(self.extent.width, self.extent.height),
+        );
+        self.scissor = scissor.intersection(&canvas).unwrap_or_default();
+    }
+
+    pub fn extent(&self) -> Extent<u32, ScreenSpace> {
+        self.extent
+    }
+
+    pub fn clear(&mut self, color: Color) {
+        self.pixels.fill([color.r, color.g, color.b, color.a]);
+    }
+
+    /// Fills a triangle, interpolating vertex colors across its surface.
+    ///
+    /// This follows the same conventions as the DX12 pipeline so that both
+    /// backends produce the same coverage:
+    ///
+    /// - Pixels are sampled at their centers.
+    /// - Triangles are filled whichever way they are wound, so that meshes
+    ///   drawn with mirroring transforms do not disappear.
+    /// - Pixels that lie exactly on an edge are covered only if the edge is a
+    ///   top or left edge, so that adjacent triangles never cover the same
+    ///   pixel twice.
+    pub fn fill_triangle(&mut self, a: &Vertex, b: &Vertex, c: &Vertex) {
+        self.rasterize([&a.position, &b.position, &c.position], |[wa, wb, wc]| {
+            premultiply(&Color::new(
+                wa * a.color.r + wb * b.color.r + wc * c.color.r,
+                wa * a.color.g + wb * b.color.g + wc * c.color.g,
+                wa * a.color.b + wb * b.color.b + wc * c.color.b,
+                wa * a.color.a + wb * b.color.a + wc * c.color.a,
+            ))
+        });
+    }
+
+    /// Fills a triangle with texels returned by `sample` for the interpolated
+    /// texture coordinates, multiplied by the interpolated vertex colors. See
+    /// [`Canvas::fill_triangle`] for the rasterization rules.
+    pub fn fill_textured_triangle(
+        &mut self,
+        a: &TexturedVertex,
+        b: &TexturedVertex,
+        c: &TexturedVertex,
+        sample: impl Fn(&Point<f32>) -> [f32; 4],
+    ) {
+        self.rasterize([&a.position, &b.position, &c.position], |[wa, wb, wc]| {
+            let uv = Point::new(
+                wa * a.uv.x + wb * b.uv.x + wc * c.uv.x,
+                wa * a.uv.y + wb * b.uv.y + wc * c.uv.y,
+            );
+            let color = premultiply(&Color::new(
+                wa * a.color.r + wb * b.color.r + wc * c.color.r,
+                wa * a.color.g + wb * b.color.g + wc * c.color.g,
+                wa * a.color.b + wb * b.color.b + wc * c.color.b,
+                wa * a.color.a + wb * b.color.a + wc * c.color.a,
+            ));
+
+            let texel = sample(&uv);
+            [
+                texel[0] * color[0],
+                texel[1] * color[1],
+                texel[2] * color[2],
+                texel[3] * color[3],
+            ]
+        });
+    }
+
+    /// Blends the premultiplied colors returned by `shade` into every pixel
+    /// covered by a triangle. `shade` is given the pixel's barycentric
+    /// coordinates.
+    fn rasterize(&mut self, [a, b, c]: [&Point<f32>; 3], shade: impl Fn([f32; 3]) -> [f32; 4]) {
+        // Counter-clockwise triangles are filled as their clockwise
+        // equivalents, so that edges are classified the same way for both
+        // windings. Their last two barycentric coordinates are swapped back
+        // before shading.
+        let flipped = orient(a, b, c) < 0.0;
+        let (b, c) = if flipped { (c, b) } else { (b, c) };
+        let area = orient(a, b, c);
+
+        // Degenerate.
+        if area <= 0.0 || !area.is_finite() {
+            return;
+        }
+
+        let min_x = a.x.min(b.x).min(c.x);
+        let min_y = a.y.min(b.y).min(c.y);
+        let max_x = a.x.max(b.x).max(c.x);
+        let max_y = a.y.max(b.y).max(c.y);
+
+        let x0 = clamp_to_pixel(min_x - 0.5, self.scissor.p0.x, self.scissor.p1.x);
+        let y0 = clamp_to_pixel(min_y - 0.5, self.scissor.p0.y, self.scissor.p1.y);
+        let x1 = clamp_to_pixel(max_x + 0.5, self.scissor.p0.x, self.scissor.p1.x);
+        let y1 = clamp_to_pixel(max_y + 0.5, self.scissor.p0.y, self.scissor.p1.y);
+
+        let edges = [Edge::new(b, c), Edge::new(c, a), Edge::new(a, b)];
+
+        let inv_area = 1.0 / area;
+        let equation = BlendMode::Normal.equation();
+
+        for y in y0..y1 {
+            let row = y as usize * self.extent.width as usize;
+            for x in x0..x1 {
+                let p = Point::new(x as f32 + 0.5, y as f32 + 0.5);
+
+                let w = [
+                    edges[0].distance(&p),
+                    edges[1].distance(&p),
+                    edges[2].distance(&p),
+                ];
+
+                if !edges.iter().zip(w).all(|(edge, w)| edge.covers(w)) {
+                    continue;
+                }
+
+                let [wa, wb, wc] = w.map(|w| w * inv_area);
+                let color = shade(if flipped { [wa, wc, wb] } else { [wa, wb, wc] });
+
+                let pixel = &mut self.pixels[row + x as usize];
+                *pixel = blend(&equation, color, *pixel);
+            }
+        }
+    }
+
+    /// Blends premultiplied pixels of the same size as the canvas into it,
+    /// within the scissor rectangle.
+    pub fn composite(&mut self, source: &[[f32; 4]], opacity: f32, blend_mode: BlendMode) {
+        debug_assert_eq!(source.len(), self.pixels.len());
+        let equation = blend_mode.equation();
+
+        for y in self.scissor.p0.y..self.scissor.p1.y {
+            let row = y as usize * self.extent.width as usize;
+            for x in self.scissor.p0.x as usize..self.scissor.p1.x as usize {
+                let pixel = &mut self.pixels[row + x];
+                *pixel = blend(&equation, source[row + x].map(|c| c * opacity), *pixel);
+            }
+        }
+    }
+}
+
+fn premultiply(color: &Color) -> [f32; 4] {
+    [
+        color.r * color.a,
+        color.g * color.a,
+        color.b * color.a,
+        color.a,
+    ]
+}
+
+/// Blends a premultiplied source color into a premultiplied destination color.
+fn blend(equation: &BlendEquation, src: [f32; 4], dst: [f32; 4]) -> [f32; 4] {
+    let factor = |factor: BlendFactor, channel: usize| match factor {
+        BlendFactor::Zero => 0.0,
+        BlendFactor::One => 1.0,
+        BlendFactor::InvSrcColor => 1.0 - src[channel],
+        BlendFactor::SrcAlpha => src[3],
+        BlendFactor::InvSrcAlpha => 1.0 - src[3],
+        BlendFactor::DstColor => dst[channel],
+        BlendFactor::DstAlpha => dst[3],
+        BlendFactor::InvDstAlpha => 1.0 - dst[3],
+    };
+
+    let mut out = [0.0; 4];
+    for channel in 0..3 {
+        out[channel] = src[channel] * factor(equation.src_color, channel)
+            + dst[channel] * factor(equation.dst_color, channel);
+    }
+    out[3] = src[3] * factor(equation.src_alpha, 3) + dst[3] * factor(equation.dst_alpha, 3);
+    out
+}
+
+struct Edge {
+    origin: Point<f32>,
+    direction: Offset<f32>,
+    is_top_left: bool,
+}
+
+impl Edge {
+    fn new(from: &Point<f32>, to: &Point<f32>) -> Self {
+        let dx = to.x - from.x;
+        let dy = to.y - from.y;
+
+        Self {
+            origin: *from,
+            direction: Offset::new(dx, dy),
+            // With a clockwise winding and the Y-axis pointing down, left edges
+            // point up and top edges point right.
+            is_top_left: dy < 0.0 || (dy == 0.0 && dx > 0.0),
+        }
+    }
+
+    fn distance(&self, p: &Point<f32>) -> f32 {
+        self.direction.x * (p.y - self.origin.y) - self.direction.y * (p.x - self.origin.x)
+    }
+
+    fn covers(&self, distance: f32) -> bool {
+        distance > 0.0 || (distance == 0.0 && self.is_top_left)
+    }
+}
+
+/// Twice the signed area of the triangle `abc`. Positive if the triangle is
+/// clockwise on screen.
+fn orient(a: &Point<f32>, b: &Point<f32>, c: &Point<f32>) -> f32 {
+    (b.x - a.x) * (c.y - a.y) - (b.y - a.y) * (c.x - a.x)
+}
+
+#[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+fn clamp_to_pixel(v: f32, min: u32, max: u32) -> u32 {
+    if v <= 0.0 {
+        min
+    } else {
+        (v.ceil() as u32).clamp(min, max)
+    }
+}
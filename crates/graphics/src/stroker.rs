@@ -0,0 +1,578 @@
+use geometry::{Offset, Path, Point, Polyline};
+
+use crate::{fill_polylines, Color, FillRule, Tessellation};
+
+/// The shape drawn where two segments of a stroke meet.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum LineJoin {
+    /// Extends the outer edges of the segments until they meet, falling back
+    /// to `Bevel` for sharp corners that exceed the miter limit.
+    #[default]
+    Miter,
+    Round,
+    /// Connects the outer corners of the segments with a straight line.
+    Bevel,
+}
+
+/// The shape drawn at the ends of an open stroke.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum LineCap {
+    /// Ends the stroke exactly at its end points.
+    #[default]
+    Butt,
+    /// Extends the stroke by half its width past its end points.
+    Square,
+    Round,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct StrokeOptions {
+    pub width: f32,
+    pub line_join: LineJoin,
+    /// The longest a miter join may be, as a multiple of the stroke width.
+    pub miter_limit: f32,
+    pub line_cap: LineCap,
+    /// Alternating lengths of dashes and gaps, starting with a dash. An odd
+    /// number of lengths is repeated to make it even. The stroke is solid if
+    /// this is empty, if any length is negative or all are zero, or if the
+    /// pattern is so short that a subpath would have more than 10,000 dashes.
+    pub dashes: Vec<f32>,
+    /// How far into the dash pattern each subpath starts.
+    pub dash_offset: f32,
+    /// The maximum distance between a curve (or round join or cap) and the
+    /// line segments used to approximate it.
+    pub tolerance: f32,
+}
+
+impl Default for StrokeOptions {
+    fn default() -> Self {
+        Self {
+            width: 1.0,
+            line_join: LineJoin::Miter,
+            miter_limit: 4.0,
+            line_cap: LineCap::Butt,
+            dashes: Vec::new(),
+            dash_offset: 0.0,
+            tolerance: 0.1,
+        }
+    }
+}
+
+/// Triangulates the outline of a path.
+///
+/// ## Panics
+///
+/// Panics if the result needs more than `u32::MAX` vertices.
+#[must_use]
+pub fn stroke_path<Unit>(path: &Path<Unit>, options: &StrokeOptions, color: Color) -> Tessellation {
+    stroke_polylines(&path.flatten(options.tolerance), options, color)
+}
+
+/// Triangulates the outline of a set of polylines. The triangles do not
+/// overlap, even where the stroke crosses itself.
+///
+/// ## Panics
+///
+/// Panics if the result needs more than `u32::MAX` vertices.
+#[must_use]
+pub fn stroke_polylines<Unit>(
+    polylines: &[Polyline<Unit>],
+    options: &StrokeOptions,
+    color: Color,
+) -> Tessellation {
+    let half_width = options.width / 2.0;
+    if !(half_width > 0.0 && half_width.is_finite()) {
+        return Tessellation::default();
+    }
+
+    let dashes = dash_pattern(&options.dashes);
+    let mut stroker = Stroker {
+        options,
+        half_width,
+        pieces: Vec::new(),
+    };
+
+    for polyline in polylines {
+        let points = polyline
+            .points
+            .iter()
+            .map(|point| Point::new(point.x, point.y))
+            .collect::<Vec<_>>();
+
+        let split = dashes
+            .as_ref()
+            .and_then(|dashes| split_dashes(&points, polyline.closed, dashes, options.dash_offset));
+
+        if let Some(split) = split {
+            for dash in split {
+                stroker.stroke(&dash, false);
+            }
+        } else {
+            stroker.stroke(&points, polyline.closed);
+        }
+    }
+
+    // Segments, joins and caps are outlined separately, then merged by
+    // filling them together.
+    fill_polylines(&stroker.pieces, FillRule::NonZero, color)
+}
+
+struct Stroker<'a> {
+    options: &'a StrokeOptions,
+    half_width: f32,
+    pieces: Vec<Polyline>,
+}
+
+impl<'a> Stroker<'a> {
+    fn stroke(&mut self, points: &[Point<f32>], closed: bool) {
+        let mut points = points.to_vec();
+        points.dedup_by(|a, b| a.x == b.x && a.y == b.y);
+        if closed && points.len() > 1 {
+            let (first, last) = (points[0], points[points.len() - 1]);
+            if first.x == last.x && first.y == last.y {
+                points.pop();
+            }
+        }
+
+        match points.len() {
+            0 => {}
+            1 => self.dot(points[0]),
+            _ if closed => {
+                let n = points.len();
+                for i in 0..n {
+                    let (prev, point, next) =
+                        (points[(i + n - 1) % n], points[i], points[(i + 1) % n]);
+                    self.segment(point, next);
+                    self.join(point, direction(prev, point), direction(point, next));
+                }
+            }
+            n => {
+                for i in 0..n - 1 {
+                    self.segment(points[i], points[i + 1]);
+                }
+                for i in 1..n - 1 {
+                    let (prev, point, next) = (points[i - 1], points[i], points[i + 1]);
+                    self.join(point, direction(prev, point), direction(point, next));
+                }
+                self.cap(points[0], direction(points[1], points[0]));
+                self.cap(points[n - 1], direction(points[n - 2], points[n - 1]));
+            }
+        }
+    }
+
+    fn segment(&mut self, from: Point<f32>, to: Point<f32>) {
+        let normal = normal(direction(from, to)) * self.half_width;
+        self.push(vec![from + normal, to + normal, to - normal, from - normal]);
+    }
+
+    /// Fills the outer corner where a segment arriving in direction `d0` meets
+    /// one leaving in direction `d1`.
+    fn join(&mut self, point: Point<f32>, d0: Offset<f32>, d1: Offset<f32>) {
+        let cross = d0.cross(&d1);
+        let dot = d0.dot(&d1);
+        if cross == 0.0 && dot > 0.0 {
+            return;
+        }
+
+        // The outer corner is on the side opposite to the turn.
+        let (n0, n1) = if cross > 0.0 {
+            (-normal(d0) * self.half_width, -normal(d1) * self.half_width)
+        } else {
+            (normal(d0) * self.half_width, normal(d1) * self.half_width)
+        };
+
+        match self.options.line_join {
+            LineJoin::Miter => {
+                let bisector = n0 + n1;
+                // The cosine of half the angle between the two normals.
+                let cos_half = ((1.0 + n0.dot(&n1) / (self.half_width * self.half_width)) / 2.0)
+                    .max(0.0)
+                    .sqrt();
+
+                if cos_half > 0.0 && 1.0 / cos_half <= self.options.miter_limit {
+                    let tip = bisector / bisector.length() * (self.half_width / cos_half);
+                    self.push(vec![point, point + n0, point + tip, point + n1]);
+                } else {
+                    self.push(vec![point, point + n0, point + n1]);
+                }
+            }
+            LineJoin::Round => {
+                let angle = dot.clamp(-1.0, 1.0).acos();
+                // Turn towards `n1`, passing through the direction of travel
+                // if the stroke doubles back on itself.
+                let angle = if cross > 0.0 { angle } else { -angle };
+                let mut points = vec![point];
+                self.arc(point, n0, angle, &mut points);
+                self.push(points);
+            }
+            LineJoin::Bevel => self.push(vec![point, point + n0, point + n1]),
+        }
+    }
+
+    /// Caps the end of a stroke at `point`, which was approached in direction
+    /// `d`.
+    fn cap(&mut self, point: Point<f32>, d: Offset<f32>) {
+        let normal = normal(d) * self.half_width;
+        let forward = d * self.half_width;
+
+        match self.options.line_cap {
+            LineCap::Butt => {}
+            LineCap::Square => self.push(vec![
+                point + normal,
+                point + normal + forward,
+                point - normal + forward,
+                point - normal,
+            ]),
+            LineCap::Round => {
+                let mut points = Vec::new();
+                self.arc(point, normal, -std::f32::consts::PI, &mut points);
+                self.push(points);
+            }
+        }
+    }
+
+    /// Strokes a subpath of zero length, which only has caps.
+    fn dot(&mut self, point: Point<f32>) {
+        let d = Offset::new(1.0, 0.0);
+        self.cap(point, d);
+        self.cap(point, -d);
+    }
+
+    /// Appends points along a circular arc around `center`, starting at
+    /// `center + from` and turning by `angle` radians.
+    fn arc(&self, center: Point<f32>, from: Offset<f32>, angle: f32, out: &mut Vec<Point<f32>>) {
+        let ratio = (1.0 - self.options.tolerance / self.half_width).clamp(0.0, 1.0);
+        let max_step = (2.0 * ratio.acos()).clamp(0.01, std::f32::consts::FRAC_PI_2);
+
+        #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+        let n = (angle.abs() / max_step).ceil().max(1.0) as u32;
+
+        for i in 0..=n {
+            let (sin, cos) = (angle * i as f32 / n as f32).sin_cos();
+            out.push(
+                center + Offset::new(from.x * cos - from.y * sin, from.x * sin + from.y * cos),
+            );
+        }
+    }
+
+    fn push(&mut self, mut points: Vec<Point<f32>>) {
+        // Every piece must wind the same way for the non-zero fill to merge
+        // them.
+        let mut area = 0.0;
+        for (i, a) in points.iter().enumerate() {
+            let b = points[(i + 1) % points.len()];
+            area += a.x * b.y - b.x * a.y;
+        }
+
+        if area.abs() <= f32::EPSILON {
+            return;
+        }
+        if area < 0.0 {
+            points.reverse();
+        }
+
+        self.pieces.push(Polyline {
+            points,
+            closed: true,
+        });
+    }
+}
+
+fn direction(from: Point<f32>, to: Point<f32>) -> Offset<f32> {
+    let d = to - from;
+    d / d.length()
+}
+
+fn normal(d: Offset<f32>) -> Offset<f32> {
+    Offset::new(-d.y, d.x)
+}
+
+/// Normalizes a dash pattern, returning `None` if the stroke should be solid.
+fn dash_pattern(dashes: &[f32]) -> Option<Vec<f32>> {
+    let is_valid = dashes.iter().all(|d| *d >= 0.0 && d.is_finite());
+    let total: f32 = dashes.iter().sum();
+    if dashes.is_empty() || !is_valid || total <= 0.0 {
+        return None;
+    }
+
+    let mut pattern = dashes.to_vec();
+    if pattern.len() % 2 == 1 {
+        pattern.extend_from_slice(dashes);
+    }
+    Some(pattern)
+}
+
+/// The most times a dash pattern may repeat along a polyline.
+const MAX_DASHES: f32 = 10_000.0;
+
+/// Cuts a polyline into the dashes of a pattern, returning `None` if the
+/// pattern would repeat more than `MAX_DASHES` times along it. Such patterns
+/// are too fine to see, and lengths below the precision of the polyline's
+/// coordinates would never be stepped past.
+fn split_dashes(
+    points: &[Point<f32>],
+    closed: bool,
+    pattern: &[f32],
+    offset: f32,
+) -> Option<Vec<Vec<Point<f32>>>> {
+    let Some(first) = points.first() else {
+        return Some(Vec::new());
+    };
+
+    let closing = closed.then(|| (points[points.len() - 1], *first));
+    let segments = points
+        .windows(2)
+        .map(|pair| (pair[0], pair[1]))
+        .chain(closing);
+
+    let total: f32 = pattern.iter().sum();
+    let length: f32 = segments
+        .clone()
+        .map(|(from, to)| (to - from).length())
+        .sum();
+    if length.is_nan() || length / total > MAX_DASHES {
+        return None;
+    }
+
+    let mut offset = offset.rem_euclid(total);
+    let mut index = 0;
+    while offset > 0.0 && offset >= pattern[index] {
+        offset -= pattern[index];
+        index = (index + 1) % pattern.len();
+    }
+
+    let mut remaining = pattern[index] - offset;
+    let mut dashes = Vec::new();
+    let mut current = Vec::new();
+    if index % 2 == 0 {
+        current.push(*first);
+    }
+
+    for (from, to) in segments {
+        let length = (to - from).length();
+        if length == 0.0 {
+            continue;
+        }
+
+        let mut distance = 0.0;
+        while length - distance >= remaining {
+            distance += remaining;
+            current.push(from.lerp(&to, distance / length));
+            if index % 2 == 0 {
+                dashes.push(std::mem::take(&mut current));
+            }
+
+            index = (index + 1) % pattern.len();
+            remaining = pattern[index];
+        }
+
+        remaining -= length - distance;
+        if index % 2 == 0 {
+            current.push(to);
+        }
+    }
+
+    if !current.is_empty() {
+        dashes.push(current);
+    }
+
+    Some(dashes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn line(points: &[(f32, f32)], closed: bool) -> Polyline {
+        Polyline {
+            points: points.iter().map(|(x, y)| Point::new(*x, *y)).collect(),
+            closed,
+        }
+    }
+
+    fn area(tessellation: &Tessellation) -> f32 {
+        tessellation
+            .indices
+            .chunks_exact(3)
+            .map(|triangle| {
+                let [a, b, c] = [0, 1, 2].map(|i| {
+                    Offset::<f32>::from(tessellation.vertices[triangle[i] as usize].position)
+                });
+                (b - a).cross(&(c - a)) / 2.0
+            })
+            .sum()
+    }
+
+    fn stroke(polyline: &Polyline, options: &StrokeOptions) -> f32 {
+        area(&stroke_polylines(
+            std::slice::from_ref(polyline),
+            options,
+            Color::RED,
+        ))
+    }
+
+    fn assert_near(actual: f32, expected: f32, epsilon: f32) {
+        assert!(
+            (actual - expected).abs() <= epsilon,
+            "expected {expected}, got {actual}"
+        );
+    }
+
+    #[test]
+    fn caps() {
+        let polyline = line(&[(0.0, 0.0), (10.0, 0.0)], false);
+        let mut options = StrokeOptions {
+            width: 2.0,
+            ..StrokeOptions::default()
+        };
+
+        assert_near(stroke(&polyline, &options), 20.0, 1e-4);
+
+        options.line_cap = LineCap::Square;
+        assert_near(stroke(&polyline, &options), 24.0, 1e-4);
+
+        options.line_cap = LineCap::Round;
+        options.tolerance = 0.001;
+        assert_near(
+            stroke(&polyline, &options),
+            20.0 + std::f32::consts::PI,
+            1e-2,
+        );
+    }
+
+    #[test]
+    fn joins() {
+        // A right angle. The inner corner is covered by both segments but
+        // should only be counted once.
+        let polyline = line(&[(0.0, 0.0), (10.0, 0.0), (10.0, 10.0)], false);
+        let mut options = StrokeOptions {
+            width: 2.0,
+            ..StrokeOptions::default()
+        };
+
+        let bevel_area = 20.0 + 20.0 - 1.0 + 0.5;
+        assert_near(stroke(&polyline, &options), bevel_area + 0.5, 1e-4);
+
+        options.line_join = LineJoin::Bevel;
+        assert_near(stroke(&polyline, &options), bevel_area, 1e-4);
+
+        options.line_join = LineJoin::Round;
+        options.tolerance = 0.001;
+        let round_area = 20.0 + 20.0 - 1.0 + std::f32::consts::FRAC_PI_4;
+        assert_near(stroke(&polyline, &options), round_area, 1e-2);
+
+        // A right angle has a miter ratio of sqrt(2).
+        options.line_join = LineJoin::Miter;
+        options.miter_limit = 1.4;
+        assert_near(stroke(&polyline, &options), bevel_area, 1e-4);
+    }
+
+    #[test]
+    fn closed() {
+        let polyline = line(&[(0.0, 0.0), (10.0, 0.0), (10.0, 10.0), (0.0, 10.0)], true);
+        let options = StrokeOptions {
+            width: 2.0,
+            line_cap: LineCap::Round,
+            ..StrokeOptions::default()
+        };
+
+        // Caps don't apply to closed outlines.
+        assert_near(stroke(&polyline, &options), 12.0 * 12.0 - 8.0 * 8.0, 1e-4);
+    }
+
+    #[test]
+    fn self_intersecting() {
+        let polyline = line(&[(0.0, 0.0), (10.0, 0.0), (5.0, -5.0), (5.0, 5.0)], false);
+        let options = StrokeOptions {
+            width: 2.0,
+            line_join: LineJoin::Bevel,
+            ..StrokeOptions::default()
+        };
+
+        let tessellation = stroke_polylines(&[polyline], &options, Color::RED);
+        for triangle in tessellation.indices.chunks_exact(3) {
+            let [a, b, c] = [0, 1, 2]
+                .map(|i| Offset::<f32>::from(tessellation.vertices[triangle[i] as usize].position));
+            assert!((b - a).cross(&(c - a)) > 0.0);
+        }
+
+        // The crossing at (5, 0) is only covered once.
+        let segments = 20.0 + 2.0 * 50f32.sqrt() + 20.0;
+        assert!(area(&tessellation) < segments);
+    }
+
+    #[test]
+    fn dashes() {
+        let polyline = line(&[(0.0, 0.0), (10.0, 0.0)], false);
+        let mut options = StrokeOptions {
+            width: 2.0,
+            dashes: vec![2.0, 2.0],
+            ..StrokeOptions::default()
+        };
+
+        // Dashes at 0-2, 4-6 and 8-10.
+        assert_near(stroke(&polyline, &options), 12.0, 1e-4);
+
+        // Dashes at 0-1, 3-5 and 7-9.
+        options.dash_offset = 1.0;
+        assert_near(stroke(&polyline, &options), 10.0, 1e-4);
+
+        // An odd pattern is repeated: dashes at 0-3, 4-5 and 8-9.
+        options.dashes = vec![3.0, 1.0, 1.0];
+        options.dash_offset = 0.0;
+        assert_near(stroke(&polyline, &options), 10.0, 1e-4);
+
+        // Zero-length dashes with round caps become dots.
+        options.dashes = vec![0.0, 5.0];
+        options.line_cap = LineCap::Round;
+        options.tolerance = 0.001;
+        assert_near(
+            stroke(&polyline, &options),
+            3.0 * std::f32::consts::PI,
+            2e-2,
+        );
+
+        // Invalid patterns are ignored.
+        options.dashes = vec![1.0, -1.0];
+        options.line_cap = LineCap::Butt;
+        assert_near(stroke(&polyline, &options), 20.0, 1e-4);
+
+        // Patterns too fine to step through are drawn solid.
+        options.dashes = vec![1e-7, 1e-7];
+        assert_near(stroke(&polyline, &options), 20.0, 1e-4);
+        let points = [Point::new(0.0, 0.0), Point::new(10.0, 0.0)];
+        assert_eq!(split_dashes(&points, false, &[1e-7, 1e-7], 0.0), None);
+        assert_eq!(
+            split_dashes(&points, false, &[1e-3, 1e-3], 0.0)
+                .unwrap()
+                .len(),
+            5000
+        );
+    }
+
+    #[test]
+    fn dashes_follow_corners() {
+        let points = [
+            Point::new(0.0, 0.0),
+            Point::new(4.0, 0.0),
+            Point::new(4.0, 4.0),
+        ];
+
+        let dashes = split_dashes(&points, true, &[3.0, 2.0], 0.0).unwrap();
+        let lengths: Vec<f32> = dashes
+            .iter()
+            .map(|dash| {
+                dash.windows(2)
+                    .map(|pair| (pair[1] - pair[0]).length())
+                    .sum()
+            })
+            .collect();
+
+        // The closing segment has a length of sqrt(32).
+        assert_eq!(dashes.len(), 3);
+        assert_eq!(dashes[1], [Point::new(4.0, 1.0), Point::new(4.0, 4.0)]);
+        assert_near(lengths[0], 3.0, 1e-5);
+        assert_near(lengths[1], 3.0, 1e-5);
+        assert_near(lengths[2], 3.0, 1e-5);
+    }
+}
@@ -0,0 +1,575 @@
+use geometry::{Degrees, Extent, Offset, Path, Point, Radians, Scale, SvgPathError, Transform};
+
+use crate::{
+    fill_path, Color, FillOptions, FillRule, RenderGraph, RenderGraphError, RenderGraphNodeId,
+};
+
+/// An error encountered while loading an SVG document.
+#[derive(Debug, PartialEq, Eq)]
+pub enum SvgError {
+    /// The document is not well-formed XML. Lines and columns start at 1.
+    InvalidXml { line: u32, column: u32 },
+    /// The root element is not `<svg>`.
+    NotSvg,
+    /// A `<path>` element's `d` attribute is not valid path data.
+    InvalidPathData(SvgPathError),
+    /// An attribute or style property has a value that could not be parsed,
+    /// or that uses a feature that is not supported.
+    InvalidAttribute { name: String, value: String },
+}
+
+/// A static SVG image, reduced to a list of filled shapes.
+///
+/// Only a small subset of SVG is supported: `<path>`, `<rect>` and `<circle>`
+/// elements, nested in `<g>` elements with `transform` attributes, with solid
+/// fills. Other elements are skipped along with their children.
+pub struct SvgDocument {
+    size: Extent<f32>,
+    shapes: Vec<Shape>,
+}
+
+struct Shape {
+    /// The outline of the shape, in the document's coordinate space.
+    path: Path,
+    color: Color,
+    fill_rule: FillRule,
+}
+
+impl SvgDocument {
+    pub fn parse(source: &str) -> Result<Self, SvgError> {
+        let document = roxmltree::Document::parse(source).map_err(|error| {
+            let position = error.pos();
+            SvgError::InvalidXml {
+                line: position.row,
+                column: position.col,
+            }
+        })?;
+
+        let root = document.root_element();
+        if root.tag_name().name() != "svg" {
+            return Err(SvgError::NotSvg);
+        }
+
+        let view_box = root
+            .attribute("viewBox")
+            .map(|value| parse_numbers(value, "viewBox"))
+            .transpose()?;
+        let view_box = match view_box.as_deref() {
+            Some(&[x, y, width, height]) => Some((x, y, width, height)),
+            Some(_) => return Err(invalid("viewBox", root.attribute("viewBox"))),
+            None => None,
+        };
+
+        let width = length_attribute(root, "width")?;
+        let height = length_attribute(root, "height")?;
+        let size = Extent::new(
+            width.or(view_box.map(|v| v.2)).unwrap_or(0.0),
+            height.or(view_box.map(|v| v.3)).unwrap_or(0.0),
+        );
+
+        // The view box is scaled uniformly to fit the viewport and centered
+        // within it (`preserveAspectRatio="xMidYMid meet"`).
+        let transform = match view_box {
+            Some((x, y, vb_width, vb_height)) if vb_width > 0.0 && vb_height > 0.0 => {
+                let scale = (size.width / vb_width).min(size.height / vb_height);
+                Transform::translate(Offset::new(-x, -y))
+                    .then_scale(Scale::new(scale, scale))
+                    .then_translate(Offset::new(
+                        (size.width - vb_width * scale) / 2.0,
+                        (size.height - vb_height * scale) / 2.0,
+                    ))
+            }
+            _ => Transform::identity(),
+        };
+
+        // The root's presentation attributes are inherited like a group's.
+        let style = Style::default().apply(root)?;
+        let mut shapes = Vec::new();
+        load_children(root, &transform, &style, &mut shapes)?;
+
+        Ok(Self { size, shapes })
+    }
+
+    /// The size of the document's viewport.
+    #[must_use]
+    pub fn size(&self) -> Extent<f32> {
+        self.size
+    }
+
+    /// Adds a node for every shape in the document to `parent`, positioned
+    /// with `transform`. If a shape does not fit in the graph, the shapes
+    /// before it are kept and the error is returned.
+    pub fn draw(
+        &self,
+        graph: &mut RenderGraph,
+        parent: RenderGraphNodeId,
+        transform: &Transform<f32>,
+    ) -> Result<(), RenderGraphError> {
+        for shape in &self.shapes {
+            // Curves are flattened after the transform is applied, so that
+            // the tolerance is in the target's units.
+            let options = FillOptions {
+                fill_rule: shape.fill_rule,
+                ..FillOptions::default()
+            };
+            let tessellation = fill_path(&shape.path.transform(transform), &options, shape.color);
+
+            if !tessellation.indices.is_empty() {
+                graph.try_draw_immediate(parent, &tessellation.vertices, &tessellation.indices)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// The inherited presentation attributes of an element.
+#[derive(Clone, Copy)]
+struct Style {
+    /// `None` if the element is not filled.
+    fill: Option<Paint>,
+    fill_rule: FillRule,
+    fill_opacity: f32,
+    /// The value of `currentColor`.
+    color: Color,
+    /// The product of the `opacity` of the element and all of its ancestors.
+    opacity: f32,
+}
+
+#[derive(Clone, Copy)]
+enum Paint {
+    Color(Color),
+    CurrentColor,
+}
+
+impl Default for Style {
+    fn default() -> Self {
+        Self {
+            fill: Some(Paint::Color(Color::new(0.0, 0.0, 0.0, 1.0))),
+            fill_rule: FillRule::NonZero,
+            fill_opacity: 1.0,
+            color: Color::new(0.0, 0.0, 0.0, 1.0),
+            opacity: 1.0,
+        }
+    }
+}
+
+impl Style {
+    fn apply(&self, node: roxmltree::Node) -> Result<Self, SvgError> {
+        let mut style = *self;
+
+        // Presentation attributes are overridden by the `style` attribute.
+        let attributes = node.attributes().map(|a| (a.name(), a.value()));
+        let declarations = node
+            .attribute("style")
+            .into_iter()
+            .flat_map(|style| style.split(';'))
+            .filter_map(|declaration| declaration.split_once(':'))
+            .map(|(name, value)| (name.trim(), value.trim()));
+
+        for (name, value) in attributes.chain(declarations) {
+            match name {
+                "fill" => {
+                    style.fill = match value {
+                        "none" => None,
+                        "currentColor" => Some(Paint::CurrentColor),
+                        _ => Some(Paint::Color(
+                            parse_color(value).ok_or_else(|| invalid(name, Some(value)))?,
+                        )),
+                    }
+                }
+                "fill-rule" => {
+                    style.fill_rule = match value {
+                        "nonzero" => FillRule::NonZero,
+                        "evenodd" => FillRule::EvenOdd,
+                        _ => return Err(invalid(name, Some(value))),
+                    }
+                }
+                "fill-opacity" => style.fill_opacity = parse_opacity(value, name)?,
+                "color" => {
+                    style.color = parse_color(value).ok_or_else(|| invalid(name, Some(value)))?;
+                }
+                // Opacity applies to a group as a whole, but is approximated
+                // by applying it to each shape in the group.
+                "opacity" => style.opacity *= parse_opacity(value, name)?,
+                _ => {}
+            }
+        }
+
+        Ok(style)
+    }
+
+    fn fill_color(&self) -> Option<Color> {
+        let color = match self.fill? {
+            Paint::Color(color) => color,
+            Paint::CurrentColor => self.color,
+        };
+
+        Some(Color {
+            a: color.a * self.fill_opacity * self.opacity,
+            ..color
+        })
+    }
+}
+
+fn load_children(
+    parent: roxmltree::Node,
+    transform: &Transform<f32>,
+    style: &Style,
+    shapes: &mut Vec<Shape>,
+) -> Result<(), SvgError> {
+    for node in parent.children().filter(roxmltree::Node::is_element) {
+        let name = node.tag_name().name();
+        if !matches!(name, "g" | "path" | "rect" | "circle") {
+            continue;
+        }
+
+        let style = style.apply(node)?;
+        let transform = match node.attribute("transform") {
+            Some(value) => parse_transform(value)?.then(transform),
+            None => *transform,
+        };
+
+        let path = match name {
+            "g" => {
+                load_children(node, &transform, &style, shapes)?;
+                continue;
+            }
+            "path" => Path::from_svg(node.attribute("d").unwrap_or_default())
+                .map_err(SvgError::InvalidPathData)?,
+            "rect" => rect(node)?,
+            "circle" => circle(node)?,
+            _ => unreachable!(),
+        };
+
+        if let Some(color) = style.fill_color() {
+            shapes.push(Shape {
+                path: path.transform(&transform),
+                color,
+                fill_rule: style.fill_rule,
+            });
+        }
+    }
+
+    Ok(())
+}
+
+fn rect(node: roxmltree::Node) -> Result<Path, SvgError> {
+    let x = length_attribute(node, "x")?.unwrap_or(0.0);
+    let y = length_attribute(node, "y")?.unwrap_or(0.0);
+    let width = length_attribute(node, "width")?.unwrap_or(0.0);
+    let height = length_attribute(node, "height")?.unwrap_or(0.0);
+
+    let mut builder = Path::builder();
+    if width <= 0.0 || height <= 0.0 {
+        return Ok(builder.build());
+    }
+
+    // A missing corner radius takes the value of the other one.
+    let rx = length_attribute(node, "rx")?;
+    let ry = length_attribute(node, "ry")?;
+    let rx = rx.or(ry).unwrap_or(0.0).clamp(0.0, width / 2.0);
+    let ry = ry.or(Some(rx)).unwrap_or(0.0).clamp(0.0, height / 2.0);
+
+    let radii = Offset::new(rx, ry);
+    let corner = |builder: &mut geometry::PathBuilder, to: Point<f32>| {
+        builder.arc_to(radii, Radians(0.0), false, true, to);
+    };
+
+    builder.move_to(Point::new(x + rx, y));
+    builder.line_to(Point::new(x + width - rx, y));
+    corner(&mut builder, Point::new(x + width, y + ry));
+    builder.line_to(Point::new(x + width, y + height - ry));
+    corner(&mut builder, Point::new(x + width - rx, y + height));
+    builder.line_to(Point::new(x + rx, y + height));
+    corner(&mut builder, Point::new(x, y + height - ry));
+    builder.line_to(Point::new(x, y + ry));
+    corner(&mut builder, Point::new(x + rx, y));
+    builder.close();
+
+    Ok(builder.build())
+}
+
+fn circle(node: roxmltree::Node) -> Result<Path, SvgError> {
+    let cx = length_attribute(node, "cx")?.unwrap_or(0.0);
+    let cy = length_attribute(node, "cy")?.unwrap_or(0.0);
+    let r = length_attribute(node, "r")?.unwrap_or(0.0);
+
+    let mut builder = Path::builder();
+    if r > 0.0 {
+        let radii = Offset::new(r, r);
+        builder
+            .move_to(Point::new(cx + r, cy))
+            .arc_to(radii, Radians(0.0), false, true, Point::new(cx - r, cy))
+            .arc_to(radii, Radians(0.0), false, true, Point::new(cx + r, cy))
+            .close();
+    }
+
+    Ok(builder.build())
+}
+
+fn invalid(name: &str, value: Option<&str>) -> SvgError {
+    SvgError::InvalidAttribute {
+        name: name.to_string(),
+        value: value.unwrap_or_default().to_string(),
+    }
+}
+
+fn length_attribute(node: roxmltree::Node, name: &str) -> Result<Option<f32>, SvgError> {
+    node.attribute(name)
+        .map(|value| parse_length(value).ok_or_else(|| invalid(name, Some(value))))
+        .transpose()
+}
+
+/// Parses a length in user units. Only unitless and `px` lengths are
+/// supported.
+fn parse_length(value: &str) -> Option<f32> {
+    let trimmed = value.trim();
+    let number = trimmed.strip_suffix("px").unwrap_or(trimmed);
+    number.parse().ok()
+}
+
+/// Parses a list of numbers separated by whitespace and/or commas.
+fn parse_numbers(value: &str, name: &str) -> Result<Vec<f32>, SvgError> {
+    value
+        .split(|c: char| c == ',' || c.is_ascii_whitespace())
+        .filter(|number| !number.is_empty())
+        .map(|number| number.parse().map_err(|_| invalid(name, Some(value))))
+        .collect()
+}
+
+fn parse_opacity(value: &str, name: &str) -> Result<f32, SvgError> {
+    let opacity: f32 = value.parse().map_err(|_| invalid(name, Some(value)))?;
+    Ok(opacity.clamp(0.0, 1.0))
+}
+
+/// Parses a color in hex notation, functional `rgb()` notation, or one of a
+/// few common color keywords.
+fn parse_color(value: &str) -> Option<Color> {
+    let rgb = |r: u8, g: u8, b: u8| {
+        Color::new(
+            f32::from(r) / 255.0,
+            f32::from(g) / 255.0,
+            f32::from(b) / 255.0,
+            1.0,
+        )
+    };
+
+    let value = value.trim();
+    if let Some(hex) = value.strip_prefix('#') {
+        parse_hex_color(hex)
+    } else if let Some(args) = value
+        .strip_prefix("rgb(")
+        .and_then(|rest| rest.strip_suffix(')'))
+    {
+        let channels = args
+            .split(',')
+            .map(|channel| {
+                let channel = channel.trim();
+                match channel.strip_suffix('%') {
+                    Some(percent) => percent.trim().parse::<f32>().map(|p| p / 100.0),
+                    None => channel.parse::<f32>().map(|c| c / 255.0),
+                }
+                .map(|c| c.clamp(0.0, 1.0))
+            })
+            .collect::<Result<Vec<_>, _>>();
+
+        match channels.as_deref() {
+            Ok(&[r, g, b]) => Some(Color::new(r, g, b, 1.0)),
+            _ => None,
+        }
+    } else {
+        match value {
+            "black" => Some(rgb(0, 0, 0)),
+            "white" => Some(rgb(255, 255, 255)),
+            "red" => Some(rgb(255, 0, 0)),
+            "lime" => Some(rgb(0, 255, 0)),
+            "green" => Some(rgb(0, 128, 0)),
+            "blue" => Some(rgb(0, 0, 255)),
+            "yellow" => Some(rgb(255, 255, 0)),
+            "cyan" | "aqua" => Some(rgb(0, 255, 255)),
+            "magenta" | "fuchsia" => Some(rgb(255, 0, 255)),
+            "gray" | "grey" => Some(rgb(128, 128, 128)),
+            "silver" => Some(rgb(192, 192, 192)),
+            "maroon" => Some(rgb(128, 0, 0)),
+            "olive" => Some(rgb(128, 128, 0)),
+            "navy" => Some(rgb(0, 0, 128)),
+            "purple" => Some(rgb(128, 0, 128)),
+            "teal" => Some(rgb(0, 128, 128)),
+            "orange" => Some(rgb(255, 165, 0)),
+            "transparent" => Some(Color::new(0.0, 0.0, 0.0, 0.0)),
+            _ => None,
+        }
+    }
+}
+
+/// Parses the digits of a `#rgb` or `#rrggbb` color.
+#[allow(clippy::cast_possible_truncation)]
+fn parse_hex_color(hex: &str) -> Option<Color> {
+    let digits = u32::from_str_radix(hex, 16)
+        .ok()
+        .filter(|_| hex.is_ascii())?;
+    let [_, r, g, b] = match hex.len() {
+        // Each digit is repeated: #abc is #aabbcc.
+        3 => [0, (digits >> 8) & 0xF, (digits >> 4) & 0xF, digits & 0xF].map(|d| d * 0x11),
+        6 => digits.to_be_bytes().map(u32::from),
+        _ => return None,
+    };
+
+    Some(Color::new(
+        r as f32 / 255.0,
+        g as f32 / 255.0,
+        b as f32 / 255.0,
+        1.0,
+    ))
+}
+
+/// Parses a transform list. Transforms are applied right to left, so
+/// `translate(10) scale(2)` scales before translating.
+fn parse_transform(value: &str) -> Result<Transform<f32>, SvgError> {
+    let mut transform = Transform::identity();
+
+    for item in value.split_terminator(')') {
+        let error = || invalid("transform", Some(value));
+
+        let (name, args) = item.split_once('(').ok_or_else(error)?;
+        let name = name.trim_matches(|c: char| c == ',' || c.is_ascii_whitespace());
+        let args = parse_numbers(args, "transform")?;
+
+        let item = match (name, args.as_slice()) {
+            ("matrix", &[a, b, c, d, e, f]) => Transform::new(a, b, c, d, e, f),
+            ("translate", &[x]) => Transform::translate(Offset::new(x, 0.0)),
+            ("translate", &[x, y]) => Transform::translate(Offset::new(x, y)),
+            ("scale", &[s]) => Transform::scale(Scale::new(s, s)),
+            ("scale", &[x, y]) => Transform::scale(Scale::new(x, y)),
+            ("rotate", &[angle]) => Transform::rotate(Degrees(angle)),
+            ("rotate", &[angle, cx, cy]) => Transform::translate(Offset::new(-cx, -cy))
+                .then_rotate(Degrees(angle))
+                .then_translate(Offset::new(cx, cy)),
+            ("skewX", &[angle]) => {
+                Transform::new(1.0, 0.0, angle.to_radians().tan(), 1.0, 0.0, 0.0)
+            }
+            ("skewY", &[angle]) => {
+                Transform::new(1.0, angle.to_radians().tan(), 0.0, 1.0, 0.0, 0.0)
+            }
+            _ => return Err(error()),
+        };
+
+        transform = item.then(&transform);
+    }
+
+    Ok(transform)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_color(actual: Color, expected: [u8; 4]) {
+        let actual = [actual.r, actual.g, actual.b, actual.a].map(|c| (c * 255.0).round() as u8);
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn colors() {
+        assert_color(parse_color("#f80").unwrap(), [255, 136, 0, 255]);
+        assert_color(parse_color("#1e90ff").unwrap(), [30, 144, 255, 255]);
+        assert_color(
+            parse_color("rgb(10, 20%, 300)").unwrap(),
+            [10, 51, 255, 255],
+        );
+        assert_color(parse_color("green").unwrap(), [0, 128, 0, 255]);
+
+        for invalid in ["#12", "#ggg", "rgb(1, 2)", "hsl(0, 0%, 0%)", "chartreuse"] {
+            assert!(parse_color(invalid).is_none(), "{invalid}");
+        }
+    }
+
+    #[test]
+    fn transforms() {
+        let point = Point::new(1.0, 2.0);
+        let apply = |value| parse_transform(value).unwrap().transform_point(&point);
+
+        assert_eq!(apply("translate(10)"), Point::new(11.0, 2.0));
+        assert_eq!(apply("translate(10, 20) scale(2)"), Point::new(12.0, 24.0));
+        assert_eq!(apply("scale(2),translate(10 20)"), Point::new(22.0, 44.0));
+        assert_eq!(apply("matrix(1 2 3 4 5 6)"), Point::new(12.0, 16.0));
+
+        let rotated = apply("rotate(90 1 1)");
+        assert!((rotated - Point::new(0.0, 1.0)).length() < 1e-6);
+
+        assert!(parse_transform("rotate(1 2)").is_err());
+        assert!(parse_transform("spin(1)").is_err());
+    }
+
+    #[test]
+    fn document() {
+        let document = SvgDocument::parse(
+            r##"
+            <svg xmlns="http://www.w3.org/2000/svg" width="32" height="16" viewBox="0 0 16 16">
+                <title>Ignored</title>
+                <defs><rect width="4" height="4"/></defs>
+                <g fill="#f00" transform="translate(1 1)" opacity="0.5">
+                    <rect width="4" height="2" style="fill: currentColor; fill-opacity: 0.5"/>
+                    <circle cx="8" cy="8" r="2" fill-rule="evenodd"/>
+                    <path d="M0 0 h1 v1 z" fill="none"/>
+                </g>
+            </svg>
+            "##,
+        )
+        .unwrap();
+
+        assert_eq!(document.size(), Extent::new(32.0, 16.0));
+        assert_eq!(document.shapes.len(), 2);
+
+        // The 16x16 view box is centered in the 32x16 viewport.
+        let rect = &document.shapes[0];
+        assert_color(rect.color, [0, 0, 0, 64]);
+        assert_eq!(
+            rect.path.bounds().unwrap(),
+            geometry::Rect::new(Point::new(9.0, 1.0), Point::new(13.0, 3.0))
+        );
+
+        let circle = &document.shapes[1];
+        assert_color(circle.color, [255, 0, 0, 128]);
+        assert_eq!(circle.fill_rule, FillRule::EvenOdd);
+    }
+
+    #[test]
+    fn root_style() {
+        let document = SvgDocument::parse(
+            r##"
+            <svg fill="#00f" stroke="#f00" stroke-width="2" style="fill-opacity: 0.5">
+                <rect width="4" height="4"/>
+                <circle cx="8" cy="8" r="2" fill="#0f0"/>
+            </svg>
+            "##,
+        )
+        .unwrap();
+
+        // Strokes are not supported, so only the fills are kept.
+        assert_eq!(document.shapes.len(), 2);
+        assert_color(document.shapes[0].color, [0, 0, 255, 128]);
+        assert_color(document.shapes[1].color, [0, 255, 0, 128]);
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(
+            SvgDocument::parse("<svg><g></svg>").err(),
+            Some(SvgError::InvalidXml { line: 1, column: 9 })
+        );
+        assert_eq!(SvgDocument::parse("<html/>").err(), Some(SvgError::NotSvg));
+        assert_eq!(
+            SvgDocument::parse(r#"<svg><path d="L 1 1"/></svg>"#).err(),
+            Some(SvgError::InvalidPathData(SvgPathError::MissingMoveTo))
+        );
+        assert_eq!(
+            SvgDocument::parse(r#"<svg><rect width="1em"/></svg>"#).err(),
+            Some(SvgError::InvalidAttribute {
+                name: "width".to_string(),
+                value: "1em".to_string()
+            })
+        );
+    }
+}
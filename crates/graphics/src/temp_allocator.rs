@@ -0,0 +1,390 @@
+use std::collections::VecDeque;
+
+use crate::UploadStatistics;
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum Error {
+    /// The heap is full, and a new chunk of memory could not be created to
+    /// satisfy the allocation request.
+    OutOfMemory,
+}
+
+pub struct FrameAllocator<'a, T> {
+    bytes_allocated_at_start: u64,
+    allocator: &'a mut Allocator<T>,
+}
+
+impl<'a, T> FrameAllocator<'a, T> {
+    pub fn new(allocator: &'a mut Allocator<T>) -> Self {
+        Self {
+            bytes_allocated_at_start: allocator.bytes_allocated,
+            allocator,
+        }
+    }
+
+    /// Allocates a block of memory from the heap, returning it along with the
+    /// chunk that it was allocated from. If the newest chunk is full, a larger
+    /// chunk is added.
+    ///
+    /// If the frame allocator is dropped without calling `finish`, its
+    /// allocations are freed along with the next frame that is finished.
+    pub fn allocate(&mut self, size: u64, alignment: u64) -> Result<(Allocation, &T), Error> {
+        let allocator = &mut *self.allocator;
+
+        let (padding, heap_offset) = match allocator.place(size, alignment) {
+            Some(placement) => placement,
+            None => {
+                allocator.add_chunk(size)?;
+                allocator
+                    .place(size, alignment)
+                    .expect("new chunks can hold the allocation")
+            }
+        };
+
+        let offset = allocator.bytes_allocated + padding;
+        allocator.bytes_allocated = offset + size;
+        allocator.peak_bytes_in_use = allocator
+            .peak_bytes_in_use
+            .max(allocator.bytes_allocated - allocator.bytes_freed);
+
+        let allocation = Allocation {
+            size,
+            offset,
+            heap_offset,
+        };
+
+        Ok((allocation, &allocator.chunks.back().unwrap().memory))
+    }
+
+    pub fn finish(self) -> FrameMarker {
+        FrameMarker {
+            end: self.allocator.bytes_allocated,
+            start: self.bytes_allocated_at_start,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct FrameMarker {
+    end: u64,
+    start: u64,
+}
+
+impl PartialEq for FrameMarker {
+    fn eq(&self, other: &Self) -> bool {
+        self.start == other.start
+    }
+}
+
+impl PartialOrd for FrameMarker {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.start.cmp(&other.start))
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct Allocation {
+    pub size: u64,
+    pub offset: u64,
+    pub heap_offset: u64,
+}
+
+/// A block of memory that is used as a ring buffer.
+struct Chunk<T> {
+    memory: T,
+    capacity: u64,
+    /// The offset at which the chunk started being allocated from. Offsets
+    /// count every byte that was ever allocated, across all chunks.
+    start: u64,
+}
+
+/// A ring allocator for memory that is used by one frame at a time, and freed
+/// in order once frames complete.
+///
+/// Memory is allocated from the newest of a list of chunks. When it is full,
+/// a chunk of twice its size is created and allocation continues from there.
+/// Older chunks are released once every frame that allocated from them has
+/// been freed, so the allocator settles on a single chunk that is large
+/// enough for the frames in flight.
+pub struct Allocator<T> {
+    chunks: VecDeque<Chunk<T>>,
+    create_chunk: Box<dyn FnMut(u64) -> Option<T>>,
+    initial_capacity: u64,
+    bytes_freed: u64,
+    bytes_allocated: u64,
+    peak_bytes_in_use: u64,
+    num_grows: u64,
+}
+
+impl<T> Allocator<T> {
+    /// Creates an allocator whose first chunk holds `initial_capacity` bytes.
+    /// `create_chunk` creates the memory for a chunk of the given size, or
+    /// returns `None` if it cannot.
+    pub fn new(
+        initial_capacity: u64,
+        create_chunk: impl FnMut(u64) -> Option<T> + 'static,
+    ) -> Self {
+        Self {
+            chunks: VecDeque::new(),
+            create_chunk: Box::new(create_chunk),
+            initial_capacity,
+            bytes_freed: 0,
+            bytes_allocated: 0,
+            peak_bytes_in_use: 0,
+            num_grows: 0,
+        }
+    }
+
+    pub fn begin_frame(&mut self) -> FrameAllocator<'_, T> {
+        FrameAllocator::new(self)
+    }
+
+    /// Frees the allocations of a finished frame. Frames must be freed in the
+    /// order in which they were finished.
+    pub fn free_frame(&mut self, marker: FrameMarker) {
+        assert!(marker.start >= self.bytes_freed);
+        self.bytes_freed = marker.end;
+
+        while self.chunks.len() > 1 && self.chunks[1].start <= self.bytes_freed {
+            self.chunks.pop_front();
+        }
+    }
+
+    pub fn statistics(&self) -> UploadStatistics {
+        UploadStatistics {
+            capacity: self.chunks.iter().map(|chunk| chunk.capacity).sum(),
+            bytes_in_use: self.bytes_allocated - self.bytes_freed,
+            peak_bytes_in_use: self.peak_bytes_in_use,
+            num_grows: self.num_grows,
+        }
+    }
+
+    /// Finds room for an allocation in the newest chunk, returning the padding
+    /// needed to reach it and its offset within the chunk. Allocations that
+    /// would cross the end of the chunk are placed at its start instead.
+    fn place(&self, size: u64, alignment: u64) -> Option<(u64, u64)> {
+        let chunk = self.chunks.back()?;
+        let bytes_in_use = self.bytes_allocated - self.bytes_freed.max(chunk.start);
+        let position = (self.bytes_allocated - chunk.start) % chunk.capacity;
+        let aligned = next_multiple_of(position, alignment);
+
+        let (padding, heap_offset) = if aligned + size <= chunk.capacity {
+            (aligned - position, aligned)
+        } else {
+            (chunk.capacity - position, 0)
+        };
+
+        (bytes_in_use + padding + size <= chunk.capacity).then_some((padding, heap_offset))
+    }
+
+    fn add_chunk(&mut self, min_capacity: u64) -> Result<(), Error> {
+        let capacity = match self.chunks.back() {
+            Some(chunk) => chunk.capacity * 2,
+            None => self.initial_capacity,
+        }
+        .max(min_capacity);
+
+        let memory = (self.create_chunk)(capacity).ok_or(Error::OutOfMemory)?;
+        if !self.chunks.is_empty() {
+            self.num_grows += 1;
+        }
+
+        self.chunks.push_back(Chunk {
+            memory,
+            capacity,
+            start: self.bytes_allocated,
+        });
+        Ok(())
+    }
+}
+
+fn next_multiple_of(a: u64, b: u64) -> u64 {
+    match a % b {
+        0 => a,
+        r => a + b - r,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// An allocator whose chunks are numbered in the order they were created.
+    fn allocator(initial_capacity: u64) -> Allocator<u32> {
+        let mut num_chunks = 0;
+        Allocator::new(initial_capacity, move |_| {
+            num_chunks += 1;
+            Some(num_chunks - 1)
+        })
+    }
+
+    fn allocate<T>(allocator: &mut Allocator<T>, sizes: &[(u64, u64)]) -> FrameMarker {
+        let mut frame = allocator.begin_frame();
+        for &(size, alignment) in sizes {
+            frame.allocate(size, alignment).unwrap();
+        }
+        frame.finish()
+    }
+
+    #[test]
+    fn ring() {
+        let mut allocator = allocator(100);
+
+        let m0 = {
+            let mut frame = allocator.begin_frame();
+            assert_eq!(
+                frame.allocate(20, 4),
+                Ok((
+                    Allocation {
+                        size: 20,
+                        offset: 0,
+                        heap_offset: 0,
+                    },
+                    &0
+                ))
+            );
+            frame.finish()
+        };
+        assert_eq!(m0, FrameMarker { end: 20, start: 0 });
+
+        let m1 = allocate(&mut allocator, &[(70, 4)]);
+        allocator.free_frame(m0);
+
+        // Allocations that do not fit before the end of the chunk wrap around
+        // to its start.
+        let m2 = {
+            let mut frame = allocator.begin_frame();
+            assert_eq!(
+                frame.allocate(20, 8).unwrap().0,
+                Allocation {
+                    size: 20,
+                    offset: 100,
+                    heap_offset: 0,
+                }
+            );
+            frame.finish()
+        };
+        allocator.free_frame(m1);
+
+        // Allocations are aligned within the chunk.
+        let m3 = {
+            let mut frame = allocator.begin_frame();
+            assert_eq!(
+                frame.allocate(15, 64).unwrap().0,
+                Allocation {
+                    size: 15,
+                    offset: 164,
+                    heap_offset: 64,
+                }
+            );
+            frame.finish()
+        };
+        allocator.free_frame(m2);
+        allocator.free_frame(m3);
+
+        assert_eq!(
+            allocator.statistics(),
+            UploadStatistics {
+                capacity: 100,
+                bytes_in_use: 0,
+                peak_bytes_in_use: 100,
+                num_grows: 0,
+            }
+        );
+    }
+
+    #[test]
+    fn full_ring() {
+        // Filling the chunk exactly must not make it look empty.
+        let mut allocator = allocator(100);
+        let m0 = allocate(&mut allocator, &[(100, 4)]);
+        let mut frame = allocator.begin_frame();
+        assert_eq!(frame.allocate(4, 4).unwrap().1, &1);
+        let m1 = frame.finish();
+
+        allocator.free_frame(m0);
+        allocator.free_frame(m1);
+        assert_eq!(allocator.statistics().capacity, 200);
+    }
+
+    #[test]
+    fn grow() {
+        let mut allocator = allocator(100);
+
+        let m0 = allocate(&mut allocator, &[(60, 4)]);
+        let m1 = {
+            let mut frame = allocator.begin_frame();
+            // The first chunk only has 40 bytes free, so a chunk of twice the
+            // size is added. Later allocations come from the new chunk.
+            assert_eq!(
+                frame.allocate(50, 4),
+                Ok((
+                    Allocation {
+                        size: 50,
+                        offset: 60,
+                        heap_offset: 0,
+                    },
+                    &1
+                ))
+            );
+            assert_eq!(frame.allocate(10, 4).unwrap().1, &1);
+            frame.finish()
+        };
+        assert_eq!(allocator.statistics().capacity, 300);
+
+        // The first chunk is released once the frames that used it are freed.
+        allocator.free_frame(m0);
+        assert_eq!(allocator.statistics().capacity, 200);
+        allocator.free_frame(m1);
+
+        // Allocations larger than twice the chunk get a chunk of their own.
+        let m2 = {
+            let mut frame = allocator.begin_frame();
+            assert_eq!(frame.allocate(500, 4).unwrap().1, &2);
+            frame.finish()
+        };
+        allocator.free_frame(m2);
+
+        assert_eq!(
+            allocator.statistics(),
+            UploadStatistics {
+                capacity: 500,
+                bytes_in_use: 0,
+                peak_bytes_in_use: 500,
+                num_grows: 2,
+            }
+        );
+    }
+
+    #[test]
+    fn abandoned_frames() {
+        let mut allocator = allocator(100);
+
+        // A frame that is never finished is freed with the next one.
+        {
+            let mut frame = allocator.begin_frame();
+            frame.allocate(60, 4).unwrap();
+        }
+
+        let m0 = allocate(&mut allocator, &[(20, 4)]);
+        assert_eq!(allocator.statistics().bytes_in_use, 80);
+        allocator.free_frame(m0);
+        assert_eq!(allocator.statistics().bytes_in_use, 0);
+    }
+
+    #[test]
+    fn out_of_memory() {
+        let mut allocator = Allocator::new(100, |capacity| (capacity <= 100).then_some(()));
+        let m0 = allocate(&mut allocator, &[(80, 4)]);
+
+        assert_eq!(
+            allocator.begin_frame().allocate(40, 4),
+            Err(Error::OutOfMemory)
+        );
+
+        // Nothing was lost, so the allocation fits once the first frame is
+        // freed.
+        allocator.free_frame(m0);
+        assert!(allocator.begin_frame().allocate(40, 4).is_ok());
+    }
+}
@@ -0,0 +1,457 @@
+use std::{cmp::Ordering, collections::HashMap};
+
+use geometry::{Path, Point, Polyline};
+
+use crate::{Color, Vertex};
+
+/// Decides which regions of a self-overlapping shape are filled.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum FillRule {
+    /// A point is inside the shape if the outline winds around it at least
+    /// once, in either direction. Holes must be wound opposite to their
+    /// outline.
+    #[default]
+    NonZero,
+    /// A point is inside the shape if a ray from it crosses the outline an odd
+    /// number of times. Holes may be wound in either direction.
+    EvenOdd,
+}
+
+impl FillRule {
+    fn is_inside(self, winding: i32) -> bool {
+        match self {
+            Self::NonZero => winding != 0,
+            Self::EvenOdd => winding % 2 != 0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FillOptions {
+    pub fill_rule: FillRule,
+    /// The maximum distance between a curve and the line segments used to
+    /// approximate it.
+    pub tolerance: f32,
+}
+
+impl Default for FillOptions {
+    fn default() -> Self {
+        Self {
+            fill_rule: FillRule::NonZero,
+            tolerance: 0.1,
+        }
+    }
+}
+
+/// A triangle list, ready to be passed to
+/// [`RenderGraph::try_draw_immediate`](crate::RenderGraph::try_draw_immediate).
+///
+/// Triangles are wound clockwise on screen.
+#[derive(Clone, Default)]
+pub struct Tessellation {
+    pub vertices: Vec<Vertex>,
+    pub indices: Vec<u32>,
+}
+
+/// Triangulates the interior of a path. Every subpath is implicitly closed.
+///
+/// ## Panics
+///
+/// Panics if the result needs more than `u32::MAX` vertices.
+#[must_use]
+pub fn fill_path<Unit>(path: &Path<Unit>, options: &FillOptions, color: Color) -> Tessellation {
+    fill_polylines(&path.flatten(options.tolerance), options.fill_rule, color)
+}
+
+/// Triangulates the interior of a set of polygons, which may be concave,
+/// overlap each other, or intersect themselves. Every polyline is treated as
+/// closed.
+///
+/// ## Panics
+///
+/// Panics if the result needs more than `u32::MAX` vertices.
+#[must_use]
+pub fn fill_polylines<Unit>(
+    polylines: &[Polyline<Unit>],
+    fill_rule: FillRule,
+    color: Color,
+) -> Tessellation {
+    // The plane is cut into horizontal slabs at every vertex and every edge
+    // intersection. Within a slab no two edges cross, so sorting the edges by
+    // X and counting windings from the left yields a set of trapezoids.
+    let mut edges = Vec::new();
+    for polyline in polylines {
+        let points = &polyline.points;
+        for (i, from) in points.iter().enumerate() {
+            let to = &points[(i + 1) % points.len()];
+            if let Some(edge) = Edge::new(from.x, from.y, to.x, to.y) {
+                edges.push(edge);
+            }
+        }
+    }
+
+    edges.sort_by(|a, b| a.top.y.total_cmp(&b.top.y));
+
+    let mut cuts = Vec::with_capacity(edges.len() * 2);
+    for (i, a) in edges.iter().enumerate() {
+        cuts.push(a.top.y);
+        cuts.push(a.bottom.y);
+
+        for b in edges[i + 1..].iter().take_while(|b| b.top.y < a.bottom.y) {
+            if let Some(y) = a.intersection(b) {
+                cuts.push(y);
+            }
+        }
+    }
+
+    cuts.retain(|y| y.is_finite());
+    cuts.sort_by(f32::total_cmp);
+    cuts.dedup();
+
+    let mut builder = Builder::new(color);
+    let mut active: Vec<Span> = Vec::new();
+    let mut next_edge = 0;
+
+    for slab in cuts.windows(2) {
+        let (y0, y1) = (slab[0], slab[1]);
+
+        active.retain(|span| edges[span.edge].bottom.y > y0);
+        while next_edge < edges.len() && edges[next_edge].top.y <= y0 {
+            if edges[next_edge].bottom.y > y0 {
+                active.push(Span::new(next_edge));
+            }
+            next_edge += 1;
+        }
+
+        let mid = (y0 + y1) / 2.0;
+        for span in &mut active {
+            let edge = &edges[span.edge];
+            span.x0 = edge.x_at(y0);
+            span.x1 = edge.x_at(y1);
+            span.mid = edge.x_at(mid);
+        }
+        active.sort_by(|a, b| a.mid.partial_cmp(&b.mid).unwrap_or(Ordering::Equal));
+
+        let mut winding = 0;
+        let mut left = None;
+        for span in &active {
+            let was_inside = fill_rule.is_inside(winding);
+            winding += edges[span.edge].winding;
+            let is_inside = fill_rule.is_inside(winding);
+
+            match (was_inside, is_inside) {
+                (false, true) => left = Some(*span),
+                (true, false) => {
+                    if let Some(left) = left.take() {
+                        builder.trapezoid(y0, y1, &left, span);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    builder.finish()
+}
+
+/// A polygon edge, oriented so that it points down.
+struct Edge {
+    top: Point<f32>,
+    bottom: Point<f32>,
+    /// +1 if the original edge pointed down, -1 if it pointed up.
+    winding: i32,
+}
+
+impl Edge {
+    /// Returns `None` for horizontal edges, which never affect the winding
+    /// number.
+    fn new(x0: f32, y0: f32, x1: f32, y1: f32) -> Option<Self> {
+        match y0.partial_cmp(&y1)? {
+            Ordering::Less => Some(Self {
+                top: Point::new(x0, y0),
+                bottom: Point::new(x1, y1),
+                winding: 1,
+            }),
+            Ordering::Greater => Some(Self {
+                top: Point::new(x1, y1),
+                bottom: Point::new(x0, y0),
+                winding: -1,
+            }),
+            Ordering::Equal => None,
+        }
+    }
+
+    fn x_at(&self, y: f32) -> f32 {
+        if y <= self.top.y {
+            self.top.x
+        } else if y >= self.bottom.y {
+            self.bottom.x
+        } else {
+            let t = (y - self.top.y) / (self.bottom.y - self.top.y);
+            self.top.x + (self.bottom.x - self.top.x) * t
+        }
+    }
+
+    /// The Y coordinate where the two edges cross, if they do so anywhere but
+    /// at their end points.
+    fn intersection(&self, other: &Self) -> Option<f32> {
+        let d0 = self.bottom - self.top;
+        let d1 = other.bottom - other.top;
+
+        let denominator = d0.cross(&d1);
+        if denominator == 0.0 {
+            return None;
+        }
+
+        let offset = other.top - self.top;
+        let t = offset.cross(&d1) / denominator;
+        let u = offset.cross(&d0) / denominator;
+
+        if t > 0.0 && t < 1.0 && u > 0.0 && u < 1.0 {
+            Some(self.top.y + d0.y * t)
+        } else {
+            None
+        }
+    }
+}
+
+/// An edge's position within the current slab.
+#[derive(Clone, Copy)]
+struct Span {
+    edge: usize,
+    x0: f32,
+    x1: f32,
+    mid: f32,
+}
+
+impl Span {
+    fn new(edge: usize) -> Self {
+        Self {
+            edge,
+            x0: 0.0,
+            x1: 0.0,
+            mid: 0.0,
+        }
+    }
+}
+
+struct Builder {
+    color: Color,
+    output: Tessellation,
+    /// Trapezoids in adjacent slabs share corners, which are only emitted
+    /// once.
+    vertex_ids: HashMap<(u32, u32), u32>,
+}
+
+impl Builder {
+    fn new(color: Color) -> Self {
+        Self {
+            color,
+            output: Tessellation::default(),
+            vertex_ids: HashMap::new(),
+        }
+    }
+
+    fn trapezoid(&mut self, y0: f32, y1: f32, left: &Span, right: &Span) {
+        let top_width = right.x0 - left.x0;
+        let bottom_width = right.x1 - left.x1;
+
+        if top_width <= 0.0 && bottom_width <= 0.0 {
+            return;
+        }
+
+        let top_left = self.vertex(left.x0, y0);
+        let top_right = self.vertex(right.x0, y0);
+        let bottom_right = self.vertex(right.x1, y1);
+        let bottom_left = self.vertex(left.x1, y1);
+
+        // Clockwise on screen, skipping triangles that collapse to a line.
+        if top_width > 0.0 {
+            self.output
+                .indices
+                .extend_from_slice(&[top_left, top_right, bottom_right]);
+        }
+        if bottom_width > 0.0 {
+            self.output
+                .indices
+                .extend_from_slice(&[top_left, bottom_right, bottom_left]);
+        }
+    }
+
+    fn vertex(&mut self, x: f32, y: f32) -> u32 {
+        let vertices = &mut self.output.vertices;
+        let color = self.color;
+
+        *self
+            .vertex_ids
+            .entry((x.to_bits(), y.to_bits()))
+            .or_insert_with(|| {
+                let id =
+                    u32::try_from(vertices.len()).expect("tessellation exceeds u32::MAX vertices");
+                vertices.push(Vertex {
+                    position: Point::new(x, y),
+                    color,
+                });
+                id
+            })
+    }
+
+    fn finish(self) -> Tessellation {
+        self.output
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use geometry::{Offset, PathBuilder, Radians};
+
+    use super::*;
+
+    fn rect(builder: &mut PathBuilder, x0: f32, y0: f32, x1: f32, y1: f32) {
+        builder
+            .move_to(Point::new(x0, y0))
+            .line_to(Point::new(x1, y0))
+            .line_to(Point::new(x1, y1))
+            .line_to(Point::new(x0, y1))
+            .close();
+    }
+
+    /// The total area of the triangles, asserting that each one is clockwise.
+    fn area(tessellation: &Tessellation) -> f32 {
+        tessellation
+            .indices
+            .chunks_exact(3)
+            .map(|triangle| {
+                let [a, b, c] = [0, 1, 2].map(|i| {
+                    let position = tessellation.vertices[triangle[i] as usize].position;
+                    Offset::<f32>::from(position)
+                });
+                let doubled = (b - a).cross(&(c - a));
+                assert!(doubled > 0.0, "triangle is not clockwise");
+                doubled / 2.0
+            })
+            .sum()
+    }
+
+    fn fill(path: &Path, fill_rule: FillRule) -> Tessellation {
+        let options = FillOptions {
+            fill_rule,
+            ..FillOptions::default()
+        };
+        fill_path(path, &options, Color::RED)
+    }
+
+    #[test]
+    fn concave_polygon() {
+        // An L shape.
+        let mut builder = Path::builder();
+        builder
+            .move_to(Point::new(0.0, 0.0))
+            .line_to(Point::new(2.0, 0.0))
+            .line_to(Point::new(2.0, 4.0))
+            .line_to(Point::new(6.0, 4.0))
+            .line_to(Point::new(6.0, 6.0))
+            .line_to(Point::new(0.0, 6.0));
+        let path = builder.build();
+
+        assert_eq!(area(&fill(&path, FillRule::NonZero)), 20.0);
+        assert_eq!(area(&fill(&path, FillRule::EvenOdd)), 20.0);
+    }
+
+    #[test]
+    fn holes() {
+        let mut builder = Path::builder();
+        rect(&mut builder, 0.0, 0.0, 10.0, 10.0);
+        rect(&mut builder, 2.0, 2.0, 8.0, 8.0);
+        let same_direction = builder.build();
+
+        let mut builder = Path::builder();
+        rect(&mut builder, 0.0, 0.0, 10.0, 10.0);
+        rect(&mut builder, 2.0, 8.0, 8.0, 2.0);
+        let opposite_direction = builder.build();
+
+        assert_eq!(area(&fill(&same_direction, FillRule::NonZero)), 100.0);
+        assert_eq!(area(&fill(&same_direction, FillRule::EvenOdd)), 64.0);
+        assert_eq!(area(&fill(&opposite_direction, FillRule::NonZero)), 64.0);
+        assert_eq!(area(&fill(&opposite_direction, FillRule::EvenOdd)), 64.0);
+    }
+
+    #[test]
+    fn self_intersecting() {
+        // A bow tie, whose edges cross at (2, 2).
+        let mut builder = Path::builder();
+        builder
+            .move_to(Point::new(0.0, 0.0))
+            .line_to(Point::new(4.0, 4.0))
+            .line_to(Point::new(4.0, 0.0))
+            .line_to(Point::new(0.0, 4.0));
+        let bow_tie = builder.build();
+
+        assert_eq!(area(&fill(&bow_tie, FillRule::NonZero)), 8.0);
+        assert_eq!(area(&fill(&bow_tie, FillRule::EvenOdd)), 8.0);
+
+        // A pentagram, whose center is wound twice.
+        let mut builder = Path::builder();
+        for i in 0..5 {
+            let angle = std::f32::consts::TAU * (i * 2) as f32 / 5.0;
+            let point = Point::new(angle.sin() * 10.0, -angle.cos() * 10.0);
+            if i == 0 {
+                builder.move_to(point);
+            } else {
+                builder.line_to(point);
+            }
+        }
+        let star = builder.build();
+
+        let non_zero = area(&fill(&star, FillRule::NonZero));
+        let even_odd = area(&fill(&star, FillRule::EvenOdd));
+
+        // The inner pentagon has a circumradius of R * sin(18°) / sin(126°).
+        let r = 10.0 * 18f32.to_radians().sin() / 126f32.to_radians().sin();
+        let pentagon = 2.5 * r * r * 72f32.to_radians().sin();
+        assert!((non_zero - even_odd - pentagon).abs() < 1e-3);
+    }
+
+    #[test]
+    fn curves_are_flattened() {
+        let mut builder: PathBuilder = Path::builder();
+        builder
+            .move_to(Point::new(0.0, 5.0))
+            .arc_to(
+                Offset::new(5.0, 5.0),
+                Radians(0.0),
+                false,
+                true,
+                Point::new(10.0, 5.0),
+            )
+            .arc_to(
+                Offset::new(5.0, 5.0),
+                Radians(0.0),
+                false,
+                true,
+                Point::new(0.0, 5.0),
+            );
+        let circle = builder.build();
+
+        let options = FillOptions {
+            tolerance: 0.01,
+            ..FillOptions::default()
+        };
+        let tessellation = fill_path(&circle, &options, Color::RED);
+        // Flattening cuts off at most `tolerance` along the circumference.
+        let area = area(&tessellation);
+        let circle_area = 25.0 * std::f32::consts::PI;
+        let max_error = 10.0 * std::f32::consts::PI * options.tolerance;
+        assert!(
+            area <= circle_area && area > circle_area - max_error,
+            "{area}"
+        );
+    }
+
+    #[test]
+    fn empty() {
+        let tessellation = fill(&Path::builder().build(), FillRule::NonZero);
+        assert!(tessellation.vertices.is_empty());
+        assert!(tessellation.indices.is_empty());
+    }
+}
@@ -0,0 +1,534 @@
+use std::{
+    cell::RefCell,
+    collections::{HashMap, HashSet},
+    rc::Rc,
+};
+
+use geometry::{Extent, ObjectSpace, Offset, Point, Rect, ScreenSpace};
+
+use crate::{GraphicsContext, Texture};
+
+use super::{packer::ShelfPacker, Font, GlyphId};
+
+/// Glyphs are rasterized at this many horizontal offsets within a pixel, so
+/// that text can be positioned more precisely than whole pixels. Vertical
+/// positions are rounded to whole pixels, as text is usually laid out on
+/// horizontal lines.
+const SUBPIXEL_BINS: u32 = 4;
+
+/// Glyphs are separated by a row and a column of transparent texels, so that
+/// linear filtering never blends a glyph with its neighbors.
+const PADDING: u32 = 1;
+
+/// A glyph in a run, placed with its pen position on the baseline.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PositionedGlyph {
+    pub glyph: GlyphId,
+    pub position: Point<f32, ObjectSpace>,
+}
+
+/// A cache of rasterized glyphs, packed into a texture that is shared by all
+/// of the glyph runs drawn with it. Caches are reference counted, so cloning
+/// one is cheap.
+///
+/// Glyphs are rasterized when they are first drawn with
+/// [`RenderGraph::draw_glyph_run`], and the regions of the atlas that changed
+/// are uploaded when a graph that uses the cache is drawn. When the atlas is
+/// full, it grows until it reaches its maximum size, and then the least
+/// recently used glyphs are evicted. Glyphs are never evicted while a render
+/// graph that draws them is alive, so retained graphs can be redrawn at any
+/// time, but they also keep the atlas from making room for other glyphs.
+///
+/// [`RenderGraph::draw_glyph_run`]: crate::RenderGraph::draw_glyph_run
+#[derive(Clone)]
+pub struct GlyphCache {
+    atlas: Rc<RefCell<Atlas>>,
+}
+
+impl Default for GlyphCache {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl GlyphCache {
+    /// Creates a cache with a 256x256 atlas that may grow to 4096x4096.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::with_limits(256, 4096)
+    }
+
+    /// Creates a cache whose atlas starts at `initial_size` texels square,
+    /// and doubles in size as needed until it reaches `max_size`.
+    #[must_use]
+    pub fn with_limits(initial_size: u32, max_size: u32) -> Self {
+        let extent = Extent::new(initial_size, initial_size);
+        Self {
+            atlas: Rc::new(RefCell::new(Atlas {
+                packer: ShelfPacker::new(extent),
+                glyphs: HashMap::new(),
+                pixels: vec![0; 4 * initial_size as usize * initial_size as usize],
+                max_size: max_size.max(initial_size),
+                dirty: None,
+                texture: None,
+                frame: 0,
+            })),
+        }
+    }
+
+    /// The current size of the atlas, in texels.
+    #[must_use]
+    pub fn extent(&self) -> Extent<u32, ScreenSpace> {
+        self.atlas.borrow().packer.extent()
+    }
+
+    /// The number of glyphs in the cache.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.atlas.borrow().glyphs.len()
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub(crate) fn ptr_eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.atlas, &other.atlas)
+    }
+
+    /// The atlas texture, as of the last upload.
+    pub(crate) fn texture(&self) -> Texture {
+        let atlas = self.atlas.borrow();
+        atlas
+            .texture
+            .clone()
+            .expect("glyph cache was not uploaded before drawing")
+    }
+
+    /// Uploads the parts of the atlas that changed since the last upload,
+    /// recreating the texture if the atlas grew, and starts a new frame for
+    /// the purpose of eviction.
+    pub(crate) fn upload(&self, context: &GraphicsContext) {
+        let mut atlas = self.atlas.borrow_mut();
+        let atlas = &mut *atlas;
+        let extent = atlas.packer.extent();
+
+        match &atlas.texture {
+            Some(texture) if texture.extent() == extent => {
+                if let Some(dirty) = atlas.dirty {
+                    let region = dirty.extent();
+                    let row_size = 4 * region.width as usize;
+                    let mut pixels = Vec::with_capacity(row_size * region.height as usize);
+                    for y in dirty.p0.y..dirty.p1.y {
+                        let start = 4 * (y * extent.width + dirty.p0.x) as usize;
+                        pixels.extend_from_slice(&atlas.pixels[start..start + row_size]);
+                    }
+                    context.update_texture(texture, dirty.p0, region, &pixels);
+                }
+            }
+            _ => atlas.texture = Some(context.create_texture(extent, &atlas.pixels)),
+        }
+
+        atlas.dirty = None;
+        atlas.frame += 1;
+    }
+}
+
+/// The glyphs of a cache that a render graph draws. They are pinned in the
+/// atlas so that the graph's texture coordinates stay valid for as long as it
+/// can be drawn, and are unpinned when the graph is dropped.
+pub(crate) struct PinnedGlyphs {
+    cache: GlyphCache,
+    glyphs: HashSet<GlyphKey>,
+}
+
+impl PinnedGlyphs {
+    pub(crate) fn new(cache: &GlyphCache) -> Self {
+        Self {
+            cache: cache.clone(),
+            glyphs: HashSet::new(),
+        }
+    }
+
+    pub(crate) fn cache(&self) -> &GlyphCache {
+        &self.cache
+    }
+
+    /// The rectangle covered by a glyph drawn at `position`, and the texels
+    /// of the atlas that it is drawn from, which stay valid until `self` is
+    /// dropped. Returns `None` if the glyph covers no pixels, or if it does
+    /// not fit in the atlas.
+    pub(crate) fn quad(
+        &mut self,
+        font: &Font,
+        glyph: GlyphId,
+        size: f32,
+        position: Point<f32, ObjectSpace>,
+    ) -> Option<(Rect<f32, ObjectSpace>, Rect<u32, ScreenSpace>)> {
+        let x = position.x.floor();
+        let subpixel = ((position.x - x) * SUBPIXEL_BINS as f32) as u32;
+        let y = position.y.round();
+
+        let key = GlyphKey {
+            font: font.id(),
+            glyph,
+            size: size.to_bits(),
+            subpixel: subpixel.min(SUBPIXEL_BINS - 1),
+        };
+
+        let mut atlas = self.cache.atlas.borrow_mut();
+        let cached = atlas.get_or_insert(key, font)?;
+        if self.glyphs.insert(key) {
+            atlas.glyphs.get_mut(&key).unwrap().pins += 1;
+        }
+
+        let texels = cached.texels?;
+        let origin = Point::new(x + cached.offset.x as f32, y + cached.offset.y as f32);
+        let extent = texels.extent();
+        Some((
+            Rect::new(
+                origin,
+                Point::new(
+                    origin.x + extent.width as f32,
+                    origin.y + extent.height as f32,
+                ),
+            ),
+            texels,
+        ))
+    }
+}
+
+impl Drop for PinnedGlyphs {
+    fn drop(&mut self) {
+        let mut atlas = self.cache.atlas.borrow_mut();
+        for key in &self.glyphs {
+            atlas
+                .glyphs
+                .get_mut(key)
+                .expect("pinned glyphs are not evicted")
+                .pins -= 1;
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+struct GlyphKey {
+    font: u32,
+    glyph: GlyphId,
+    /// The bits of the size in pixels, as floats cannot be hashed.
+    size: u32,
+    subpixel: u32,
+}
+
+#[derive(Clone, Copy)]
+struct CachedGlyph {
+    /// The glyph's texels in the atlas, or `None` if the glyph covers no
+    /// pixels or is too large for the atlas.
+    texels: Option<Rect<u32, ScreenSpace>>,
+    /// The position of the glyph's top-left corner relative to the pen.
+    offset: Offset<i32, ScreenSpace>,
+    /// The frame in which the glyph was last drawn.
+    last_used: u64,
+    /// The number of render graphs that draw the glyph.
+    pins: u32,
+}
+
+struct Atlas {
+    packer: ShelfPacker,
+    glyphs: HashMap<GlyphKey, CachedGlyph>,
+    /// The contents of the atlas as premultiplied RGBA, which is white with
+    /// the glyphs' coverage as alpha. Glyph runs are tinted to their color
+    /// when they are drawn.
+    pixels: Vec<u8>,
+    max_size: u32,
+    /// The texels that changed since the last upload.
+    dirty: Option<Rect<u32, ScreenSpace>>,
+    texture: Option<Texture>,
+    frame: u64,
+}
+
+impl Atlas {
+    fn get_or_insert(&mut self, key: GlyphKey, font: &Font) -> Option<CachedGlyph> {
+        if let Some(cached) = self.glyphs.get_mut(&key) {
+            cached.last_used = self.frame;
+            return Some(*cached);
+        }
+
+        let subpixel = Offset::new(key.subpixel as f32 / SUBPIXEL_BINS as f32, 0.0);
+        let size = f32::from_bits(key.size);
+
+        // Glyphs that could never fit are not rasterized, as their coverage
+        // buffers can be hundreds of megabytes.
+        let fits = font
+            .glyph_metrics(key.glyph, size)
+            .bounds
+            .is_none_or(|bounds| {
+                let extent = bounds.extent();
+                extent.width.max(extent.height) + 2.0 + PADDING as f32 <= self.max_size as f32
+            });
+        let bitmap = if fits {
+            font.rasterize(key.glyph, size, subpixel)
+        } else {
+            None
+        };
+
+        let cached = match bitmap {
+            Some(bitmap) => {
+                let allocation = self.allocate(Extent::new(
+                    bitmap.extent.width + PADDING,
+                    bitmap.extent.height + PADDING,
+                ))?;
+                self.write(&allocation, bitmap.extent, &bitmap.coverage);
+
+                CachedGlyph {
+                    texels: Some(Rect::new(
+                        allocation.p0,
+                        Point::new(
+                            allocation.p0.x + bitmap.extent.width,
+                            allocation.p0.y + bitmap.extent.height,
+                        ),
+                    )),
+                    offset: bitmap.offset,
+                    last_used: self.frame,
+                    pins: 0,
+                }
+            }
+            None => CachedGlyph {
+                texels: None,
+                offset: Offset::zero(),
+                last_used: self.frame,
+                pins: 0,
+            },
+        };
+
+        self.glyphs.insert(key, cached);
+        Some(cached)
+    }
+
+    /// Finds space in the atlas, growing it or evicting glyphs as needed.
+    fn allocate(&mut self, extent: Extent<u32, ScreenSpace>) -> Option<Rect<u32, ScreenSpace>> {
+        loop {
+            if let Some(rect) = self.packer.allocate(extent) {
+                return Some(rect);
+            }
+
+            if !self.grow() && !self.evict() {
+                return None;
+            }
+        }
+    }
+
+    /// Doubles the size of the atlas, up to its maximum size. Glyphs keep
+    /// their positions, and the texture is recreated on the next upload.
+    fn grow(&mut self) -> bool {
+        let old = self.packer.extent();
+        let size = (old.width * 2).min(self.max_size);
+        if size <= old.width {
+            return false;
+        }
+
+        let mut pixels = vec![0; 4 * size as usize * size as usize];
+        let row_size = 4 * old.width as usize;
+        for (src, dst) in self
+            .pixels
+            .chunks_exact(row_size)
+            .zip(pixels.chunks_exact_mut(4 * size as usize))
+        {
+            dst[..row_size].copy_from_slice(src);
+        }
+
+        self.pixels = pixels;
+        self.packer.grow(Extent::new(size, size));
+        true
+    }
+
+    /// Evicts the least recently used glyph that no render graph draws.
+    fn evict(&mut self) -> bool {
+        let Some((&key, &CachedGlyph { texels, .. })) = self
+            .glyphs
+            .iter()
+            .filter(|(_, cached)| cached.texels.is_some() && cached.pins == 0)
+            .min_by_key(|(_, cached)| cached.last_used)
+        else {
+            return false;
+        };
+
+        self.glyphs.remove(&key);
+        let texels = texels.unwrap();
+        self.packer.deallocate(&Rect::new(
+            texels.p0,
+            Point::new(texels.p1.x + PADDING, texels.p1.y + PADDING),
+        ));
+        true
+    }
+
+    /// Copies coverage into the top-left corner of `allocation`, and clears
+    /// the rest of it.
+    fn write(
+        &mut self,
+        allocation: &Rect<u32, ScreenSpace>,
+        extent: Extent<u32, ScreenSpace>,
+        coverage: &[u8],
+    ) {
+        let stride = self.packer.extent().width as usize;
+        let (width, height) = (extent.width as usize, extent.height as usize);
+
+        for y in 0..allocation.extent().height as usize {
+            for x in 0..allocation.extent().width as usize {
+                let texel = (allocation.p0.y as usize + y) * stride + allocation.p0.x as usize + x;
+                let value = if x < width && y < height {
+                    coverage[y * width + x]
+                } else {
+                    0
+                };
+                self.pixels[4 * texel..4 * texel + 4].fill(value);
+            }
+        }
+
+        self.dirty = Some(match self.dirty {
+            Some(dirty) => dirty.union(allocation),
+            None => *allocation,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{text::test_font::test_font, GraphicsConfig};
+
+    fn quad(
+        glyphs: &mut PinnedGlyphs,
+        font: &Font,
+        c: char,
+        x: f32,
+    ) -> Option<(Rect<f32, ObjectSpace>, Rect<u32, ScreenSpace>)> {
+        glyphs.quad(
+            font,
+            font.glyph_index(c).unwrap(),
+            10.0,
+            Point::new(x, 20.0),
+        )
+    }
+
+    #[test]
+    fn reuse_glyphs() {
+        let font = test_font();
+        let cache = GlyphCache::new();
+        let mut glyphs = PinnedGlyphs::new(&cache);
+
+        // `I` is 2 pixels wide at 10px, starting one pixel after the pen.
+        let (rect, texels) = quad(&mut glyphs, &font, 'I', 5.0).unwrap();
+        assert_eq!(
+            rect,
+            Rect::new(Point::new(6.0, 13.0), Point::new(8.0, 20.0))
+        );
+        assert_eq!(texels.extent(), Extent::new(2, 7));
+
+        // The same glyph at another whole pixel position is reused.
+        let (rect, same) = quad(&mut glyphs, &font, 'I', 7.0).unwrap();
+        assert_eq!(rect.p0, Point::new(8.0, 13.0));
+        assert_eq!(same, texels);
+        assert_eq!(cache.len(), 1);
+
+        // Subpixel positions are rasterized separately.
+        let (rect, shifted) = quad(&mut glyphs, &font, 'I', 5.5).unwrap();
+        assert_eq!(rect.p0, Point::new(6.0, 13.0));
+        assert_eq!(shifted.extent(), Extent::new(3, 7));
+        assert_eq!(cache.len(), 2);
+
+        // Spaces are cached, but have nothing to draw.
+        assert!(quad(&mut glyphs, &font, ' ', 0.0).is_none());
+        assert_eq!(cache.len(), 3);
+    }
+
+    #[test]
+    fn upload_dirty_regions() {
+        let context = GraphicsContext::new(&GraphicsConfig::default());
+        let font = test_font();
+        let cache = GlyphCache::new();
+        let mut glyphs = PinnedGlyphs::new(&cache);
+
+        let (_, i) = quad(&mut glyphs, &font, 'I', 0.0).unwrap();
+        cache.upload(&context);
+        assert_eq!(cache.texture().extent(), cache.extent());
+        assert!(cache.atlas.borrow().dirty.is_none());
+
+        // Only the new glyph and its padding need to be uploaded.
+        let (_, o) = quad(&mut glyphs, &font, 'O', 0.0).unwrap();
+        let dirty = cache.atlas.borrow().dirty.unwrap();
+        assert_eq!(dirty.p0, o.p0);
+        assert_eq!(dirty.extent(), Extent::new(7, 8));
+
+        let atlas = cache.atlas.borrow();
+        let texel = |x: u32, y: u32| {
+            let index = 4 * (y * cache.extent().width + x) as usize;
+            &atlas.pixels[index..index + 4]
+        };
+        assert_eq!(texel(i.p0.x, i.p0.y), [255; 4]);
+        assert_eq!(texel(i.p1.x, i.p0.y), [0; 4]);
+        // The `O` covers its middle, but not its corners.
+        assert_eq!(texel(o.p0.x + 3, o.p0.y + 3), [255; 4]);
+        assert_eq!(texel(o.p0.x, o.p0.y), [0; 4]);
+    }
+
+    #[test]
+    fn grow_then_evict() {
+        let context = GraphicsContext::new(&GraphicsConfig::default());
+        let font = test_font();
+        let cache = GlyphCache::with_limits(8, 16);
+
+        // Away from whole pixels, `I` takes 4x8 texels with its padding. Each
+        // glyph is used in a different frame, so that they are evicted in a
+        // predictable order, and by a graph that is then dropped, so that
+        // they can be evicted at all.
+        for x in [0.25, 0.5] {
+            quad(&mut PinnedGlyphs::new(&cache), &font, 'I', x).unwrap();
+            cache.upload(&context);
+        }
+        assert_eq!(cache.extent(), Extent::new(8, 8));
+
+        // The atlas grows before anything is evicted.
+        let mut glyphs = PinnedGlyphs::new(&cache);
+        let (_, i) = quad(&mut glyphs, &font, 'I', 0.75).unwrap();
+        assert_eq!(cache.extent(), Extent::new(16, 16));
+        quad(&mut glyphs, &font, 'O', 0.0).unwrap();
+        cache.upload(&context);
+        quad(&mut glyphs, &font, 'A', 0.0).unwrap();
+        assert_eq!(cache.len(), 5);
+
+        // Once the atlas is at its maximum size, the least recently used
+        // glyphs are evicted until there is room.
+        quad(&mut glyphs, &font, 'V', 0.0).unwrap();
+        assert_eq!(cache.extent(), Extent::new(16, 16));
+        assert_eq!(cache.len(), 4);
+        assert_eq!(quad(&mut glyphs, &font, 'I', 0.75).unwrap().1, i);
+        assert_eq!(cache.len(), 4);
+
+        // Glyphs drawn by a live graph are never evicted, so there is no room
+        // for more until the graph is dropped.
+        assert!(quad(&mut PinnedGlyphs::new(&cache), &font, 'O', 0.5).is_none());
+        assert_eq!(cache.len(), 4);
+        drop(glyphs);
+        assert!(quad(&mut PinnedGlyphs::new(&cache), &font, 'O', 0.5).is_some());
+    }
+
+    #[test]
+    fn too_large_glyphs() {
+        let font = test_font();
+        let cache = GlyphCache::with_limits(8, 16);
+        let mut glyphs = PinnedGlyphs::new(&cache);
+
+        // Glyphs larger than the atlas can grow are remembered as having
+        // nothing to draw, without rasterizing them each time.
+        let glyph = font.glyph_index('O').unwrap();
+        for _ in 0..2 {
+            assert!(glyphs
+                .quad(&font, glyph, 10_000.0, Point::new(0.0, 0.0))
+                .is_none());
+            assert_eq!(cache.len(), 1);
+        }
+        assert_eq!(cache.extent(), Extent::new(8, 8));
+    }
+}
@@ -0,0 +1,349 @@
+use std::{
+    rc::Rc,
+    sync::atomic::{AtomicU32, Ordering},
+};
+
+use geometry::{Offset, Path, PathBuilder, Point, Rect};
+use owned_ttf_parser::{AsFaceRef, Face, OutlineBuilder, OwnedFace};
+
+use super::rasterizer::{rasterize, GlyphBitmap};
+
+/// The index of a glyph within a font.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct GlyphId(pub u16);
+
+/// An error encountered while loading a font.
+#[derive(Debug)]
+pub enum FontError {
+    /// The file could not be read.
+    Io(std::io::Error),
+    /// The data is not a valid TrueType or OpenType font, or the collection
+    /// does not contain a font at the requested index.
+    Invalid { message: String },
+}
+
+impl From<std::io::Error> for FontError {
+    fn from(error: std::io::Error) -> Self {
+        Self::Io(error)
+    }
+}
+
+/// Vertical metrics of a font at a given size, in pixels.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FontMetrics {
+    /// The distance from the baseline to the top of the tallest glyphs.
+    pub ascent: f32,
+    /// The distance from the baseline to the bottom of the deepest glyphs,
+    /// as a positive number.
+    pub descent: f32,
+    /// The recommended gap between the descent of one line and the ascent of
+    /// the next.
+    pub line_gap: f32,
+}
+
+impl FontMetrics {
+    /// The distance between the baselines of consecutive lines.
+    #[must_use]
+    pub fn line_height(&self) -> f32 {
+        self.ascent + self.descent + self.line_gap
+    }
+}
+
+/// Horizontal metrics of a glyph at a given size, in pixels.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GlyphMetrics {
+    /// How far the pen moves after drawing the glyph.
+    pub advance: f32,
+    /// The bounds of the glyph's outline relative to the pen position on the
+    /// baseline, with y pointing down. `None` if the glyph has no outline.
+    pub bounds: Option<Rect<f32>>,
+}
+
+/// A parsed TrueType or OpenType font. Fonts are cheap to clone, and clones
+/// share the same font data.
+///
+/// All sizes are in pixels per em, and all returned coordinates have their
+/// origin at the pen position on the baseline with y pointing down.
+#[derive(Clone)]
+pub struct Font {
+    face: Rc<OwnedFace>,
+    /// Identifies the font in glyph caches. Unlike the face's address, it is
+    /// never reused by another font.
+    id: u32,
+}
+
+impl Font {
+    /// Parses a font file. If the data is a font collection, the first font
+    /// is used.
+    pub fn parse(data: Vec<u8>) -> Result<Self, FontError> {
+        Self::parse_collection(data, 0)
+    }
+
+    /// Parses the font at `index` within a font collection (`.ttc` or `.otc`
+    /// file). An index of 0 also accepts a single font file.
+    pub fn parse_collection(data: Vec<u8>, index: u32) -> Result<Self, FontError> {
+        let face = OwnedFace::from_vec(data, index).map_err(|error| FontError::Invalid {
+            message: error.to_string(),
+        })?;
+
+        static NEXT_ID: AtomicU32 = AtomicU32::new(0);
+
+        Ok(Self {
+            face: Rc::new(face),
+            id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
+        })
+    }
+
+    /// Reads and parses a font file. See [`Font::parse`].
+    pub fn load(path: impl AsRef<std::path::Path>) -> Result<Self, FontError> {
+        Self::parse(std::fs::read(path)?)
+    }
+
+    pub(crate) fn id(&self) -> u32 {
+        self.id
+    }
+
+    fn face(&self) -> &Face<'_> {
+        self.face.as_face_ref()
+    }
+
+    /// The face with its layout tables parsed for shaping. Parsing is not
+    /// free, so one face should be used to shape a whole run.
+    pub(crate) fn shaping_face(&self) -> rustybuzz::Face<'_> {
+        rustybuzz::Face::from_face(self.face().clone())
+    }
+
+    /// The number of pixels per font unit at `size`.
+    fn scale(&self, size: f32) -> f32 {
+        size / f32::from(self.face().units_per_em())
+    }
+
+    #[must_use]
+    pub fn units_per_em(&self) -> u16 {
+        self.face().units_per_em()
+    }
+
+    #[must_use]
+    pub fn num_glyphs(&self) -> u16 {
+        self.face().number_of_glyphs()
+    }
+
+    #[must_use]
+    pub fn metrics(&self, size: f32) -> FontMetrics {
+        let face = self.face();
+        let scale = self.scale(size);
+        FontMetrics {
+            ascent: f32::from(face.ascender()) * scale,
+            descent: -f32::from(face.descender()) * scale,
+            line_gap: f32::from(face.line_gap()) * scale,
+        }
+    }
+
+    /// The glyph that the font's character map assigns to `c`, if any.
+    #[must_use]
+    pub fn glyph_index(&self, c: char) -> Option<GlyphId> {
+        self.face().glyph_index(c).map(|glyph| GlyphId(glyph.0))
+    }
+
+    #[must_use]
+    pub fn glyph_metrics(&self, glyph: GlyphId, size: f32) -> GlyphMetrics {
+        let face = self.face();
+        let scale = self.scale(size);
+        let id = owned_ttf_parser::GlyphId(glyph.0);
+
+        let advance = face.glyph_hor_advance(id).unwrap_or(0);
+        let bounds = face.glyph_bounding_box(id).map(|rect| {
+            Rect::new(
+                Point::new(
+                    f32::from(rect.x_min) * scale,
+                    -f32::from(rect.y_max) * scale,
+                ),
+                Point::new(
+                    f32::from(rect.x_max) * scale,
+                    -f32::from(rect.y_min) * scale,
+                ),
+            )
+        });
+
+        GlyphMetrics {
+            advance: f32::from(advance) * scale,
+            bounds,
+        }
+    }
+
+    /// The adjustment to the advance of `left` when it is followed by
+    /// `right`, from the font's `kern` table. Variable and cross-stream
+    /// kerning are not supported.
+    #[must_use]
+    pub fn kerning(&self, left: GlyphId, right: GlyphId, size: f32) -> f32 {
+        let Some(kern) = self.face().tables().kern else {
+            return 0.0;
+        };
+
+        let left = owned_ttf_parser::GlyphId(left.0);
+        let right = owned_ttf_parser::GlyphId(right.0);
+
+        let kerning: i32 = kern
+            .subtables
+            .into_iter()
+            .filter(|table| table.horizontal && !table.variable && !table.has_cross_stream)
+            .filter_map(|table| table.glyphs_kerning(left, right))
+            .map(i32::from)
+            .sum();
+
+        kerning as f32 * self.scale(size)
+    }
+
+    /// The outline of a glyph, or `None` if the glyph has no outline (such as
+    /// a space).
+    #[must_use]
+    pub fn outline<Unit>(&self, glyph: GlyphId, size: f32) -> Option<Path<Unit>> {
+        self.outline_at(glyph, size, Offset::zero())
+    }
+
+    fn outline_at<Unit>(
+        &self,
+        glyph: GlyphId,
+        size: f32,
+        origin: Offset<f32>,
+    ) -> Option<Path<Unit>> {
+        let mut builder = Outline {
+            builder: PathBuilder::new(),
+            scale: self.scale(size),
+            origin,
+        };
+
+        self.face()
+            .outline_glyph(owned_ttf_parser::GlyphId(glyph.0), &mut builder)?;
+        Some(builder.builder.build())
+    }
+
+    /// Rasterizes a glyph into a coverage bitmap, with the pen placed at
+    /// `subpixel` (each coordinate usually between 0 and 1) so that glyphs can
+    /// be positioned more precisely than whole pixels. Returns `None` if the
+    /// glyph has no outline or covers no pixels.
+    #[must_use]
+    pub fn rasterize(
+        &self,
+        glyph: GlyphId,
+        size: f32,
+        subpixel: Offset<f32>,
+    ) -> Option<GlyphBitmap> {
+        rasterize(&self.outline_at::<()>(glyph, size, subpixel)?)
+    }
+}
+
+impl std::fmt::Debug for Font {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Font")
+            .field("units_per_em", &self.units_per_em())
+            .field("num_glyphs", &self.num_glyphs())
+            .finish_non_exhaustive()
+    }
+}
+
+/// Converts outlines from font units with y pointing up to pixels with y
+/// pointing down.
+struct Outline<Unit> {
+    builder: PathBuilder<Unit>,
+    scale: f32,
+    origin: Offset<f32>,
+}
+
+impl<Unit> Outline<Unit> {
+    fn point(&self, x: f32, y: f32) -> Point<f32, Unit> {
+        Point::new(
+            x * self.scale + self.origin.x,
+            -y * self.scale + self.origin.y,
+        )
+    }
+}
+
+impl<Unit> OutlineBuilder for Outline<Unit> {
+    fn move_to(&mut self, x: f32, y: f32) {
+        let to = self.point(x, y);
+        self.builder.move_to(to);
+    }
+
+    fn line_to(&mut self, x: f32, y: f32) {
+        let to = self.point(x, y);
+        self.builder.line_to(to);
+    }
+
+    fn quad_to(&mut self, x1: f32, y1: f32, x: f32, y: f32) {
+        let (ctrl, to) = (self.point(x1, y1), self.point(x, y));
+        self.builder.quad_to(ctrl, to);
+    }
+
+    fn curve_to(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, x: f32, y: f32) {
+        let (ctrl1, ctrl2, to) = (self.point(x1, y1), self.point(x2, y2), self.point(x, y));
+        self.builder.cubic_to(ctrl1, ctrl2, to);
+    }
+
+    fn close(&mut self) {
+        self.builder.close();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::text::test_font::test_font;
+
+    #[test]
+    fn metrics() {
+        let font = test_font();
+        assert_eq!(font.units_per_em(), 1000);
+
+        let metrics = font.metrics(20.0);
+        assert_eq!(metrics.ascent, 16.0);
+        assert_eq!(metrics.descent, 4.0);
+        assert_eq!(metrics.line_gap, 2.0);
+        assert_eq!(metrics.line_height(), 22.0);
+
+        let i = font.glyph_index('I').unwrap();
+        let glyph = font.glyph_metrics(i, 20.0);
+        assert_eq!(glyph.advance, 8.0);
+        assert_eq!(
+            glyph.bounds,
+            Some(Rect::new(Point::new(2.0, -14.0), Point::new(6.0, 0.0)))
+        );
+
+        let space = font.glyph_index(' ').unwrap();
+        assert_eq!(font.glyph_metrics(space, 20.0).bounds, None);
+        assert!(font.outline::<()>(space, 20.0).is_none());
+        assert!(font.glyph_index('\u{4E00}').is_none());
+    }
+
+    #[test]
+    fn kerning() {
+        let font = test_font();
+        let a = font.glyph_index('A').unwrap();
+        let v = font.glyph_index('V').unwrap();
+        assert_eq!(font.kerning(a, v, 20.0), -2.0);
+        assert_eq!(font.kerning(v, a, 20.0), 0.0);
+    }
+
+    #[test]
+    fn invalid() {
+        assert!(matches!(
+            Font::parse(b"not a font".to_vec()),
+            Err(FontError::Invalid { .. })
+        ));
+        assert!(matches!(
+            Font::load("does/not/exist.ttf"),
+            Err(FontError::Io(_))
+        ));
+    }
+
+    #[test]
+    fn quadratic_outline() {
+        let font = test_font();
+        let o = font.glyph_index('O').unwrap();
+        let outline = font.outline::<()>(o, 10.0).unwrap();
+
+        let bounds = outline.bounds().unwrap();
+        assert!((bounds.p0.x - 0.5).abs() < 1e-4 && (bounds.p1.x - 5.5).abs() < 1e-4);
+        assert!((bounds.p0.y + 7.0).abs() < 1e-4 && bounds.p1.y.abs() < 1e-4);
+    }
+}
@@ -0,0 +1,891 @@
+use std::collections::HashMap;
+use std::ops::Range;
+
+use geometry::{ObjectSpace, Point, Rect};
+use unicode_linebreak::{linebreaks, BreakOpportunity};
+use unicode_segmentation::UnicodeSegmentation;
+
+use super::{Font, GlyphId, PositionedGlyph, ShapedText};
+
+/// How lines are placed horizontally within the layout.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TextAlign {
+    #[default]
+    Start,
+    Center,
+    End,
+    /// Stretches the spaces between words so that lines fill the layout's
+    /// width. The last line of each paragraph is aligned to the start.
+    Justify,
+}
+
+/// How a string is laid out by [`TextLayout::new`].
+#[derive(Clone, Debug)]
+pub struct TextStyle {
+    pub font: Font,
+    /// Fonts that text is drawn with, in order of preference, where `font`
+    /// has no glyphs for it. Line heights come from `font` alone.
+    pub fallback_fonts: Vec<Font>,
+    /// The font size, in pixels per em.
+    pub size: f32,
+    /// The distance between baselines, as a multiple of the font's line
+    /// height.
+    pub line_height: f32,
+    pub align: TextAlign,
+    /// Lines are wrapped so that they are no wider than this. Text is laid
+    /// out on as few lines as possible if this is `None`.
+    pub max_width: Option<f32>,
+    /// Text that does not fit on this many lines is cut off, and the last line
+    /// ends with an ellipsis. At least one line is always laid out.
+    pub max_lines: Option<usize>,
+}
+
+impl TextStyle {
+    #[must_use]
+    pub fn new(font: Font, size: f32) -> Self {
+        Self {
+            font,
+            fallback_fonts: Vec::new(),
+            size,
+            line_height: 1.0,
+            align: TextAlign::Start,
+            max_width: None,
+            max_lines: None,
+        }
+    }
+}
+
+/// A line of a [`TextLayout`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct TextLine {
+    /// The bytes of the text on the line, including trailing whitespace but
+    /// not the line break.
+    pub range: Range<usize>,
+    /// The area of the line's visible text, spanning the full line height.
+    pub rect: Rect<f32, ObjectSpace>,
+    /// The vertical position of the line's baseline.
+    pub baseline: f32,
+    /// The byte index and horizontal position of each caret position on the
+    /// line, in order.
+    carets: Vec<(usize, f32)>,
+}
+
+/// A string broken into lines and converted into glyphs, ready to be drawn
+/// with [`RenderGraph::draw_glyph_run`], one [`TextLayout::glyph_runs`] run at
+/// a time.
+///
+/// Text is shaped with [`ShapedText`], so it gets the font's ligatures,
+/// kerning and mark positioning, and characters that the font does not cover
+/// fall back to the style's fallback fonts. Right-to-left runs are reordered
+/// on each line.
+///
+/// Lines are broken at the opportunities defined by the Unicode line breaking
+/// algorithm, or between grapheme clusters if a word is wider than the
+/// maximum width. Whitespace at the end of a line hangs past the maximum
+/// width, and is not part of the line's visible area.
+///
+/// The layout's origin is the top-left corner of its first line, with y
+/// pointing down.
+///
+/// [`RenderGraph::draw_glyph_run`]: crate::RenderGraph::draw_glyph_run
+#[derive(Clone, Debug)]
+pub struct TextLayout {
+    /// The style's font followed by its fallback fonts.
+    fonts: Vec<Font>,
+    size: f32,
+    line_height: f32,
+    glyphs: Vec<PositionedGlyph>,
+    /// The index in `fonts` and the range in `glyphs` of each run of glyphs
+    /// that are drawn with the same font.
+    runs: Vec<(usize, Range<usize>)>,
+    lines: Vec<TextLine>,
+    is_truncated: bool,
+}
+
+/// A glyph placed relative to the left edge of its cluster on the baseline.
+struct ClusterGlyph {
+    /// The index of the glyph's font in the style's fonts.
+    font: usize,
+    glyph: GlyphId,
+    offset: Point<f32, ObjectSpace>,
+}
+
+/// One or more grapheme clusters that were shaped together, the smallest unit
+/// of text that lines are broken between.
+struct Cluster {
+    range: Range<usize>,
+    glyphs: Range<usize>,
+    advance: f32,
+    /// The embedding level from the bidirectional algorithm. Clusters with
+    /// odd levels are right-to-left.
+    level: u8,
+    is_whitespace: bool,
+    is_line_break: bool,
+}
+
+/// A line being broken, as a range of clusters.
+struct Line {
+    clusters: Range<usize>,
+    /// The end of the clusters that are not trailing whitespace.
+    content_end: usize,
+    /// The width of the clusters up to `content_end`, and of the ellipsis if
+    /// the line has one.
+    content_width: f32,
+    width: f32,
+    /// Whether the line ends its paragraph, or is the last line of truncated
+    /// text. Such lines are not justified.
+    is_last: bool,
+    has_ellipsis: bool,
+}
+
+impl Line {
+    fn new(start: usize) -> Self {
+        Self {
+            clusters: start..start,
+            content_end: start,
+            content_width: 0.0,
+            width: 0.0,
+            is_last: false,
+            has_ellipsis: false,
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.clusters.is_empty()
+    }
+
+    fn push(&mut self, index: usize, cluster: &Cluster) {
+        self.clusters.end = index + 1;
+        self.width += cluster.advance;
+        if !cluster.is_whitespace {
+            self.content_end = index + 1;
+            self.content_width = self.width;
+        }
+    }
+}
+
+impl TextLayout {
+    #[must_use]
+    pub fn new(text: &str, style: &TextStyle) -> Self {
+        let font = &style.font;
+        let size = style.size;
+        let fonts: Vec<Font> = std::iter::once(font)
+            .chain(&style.fallback_fonts)
+            .cloned()
+            .collect();
+
+        let (glyphs, clusters) = shape(&fonts, size, text);
+        let mut lines = break_lines(text, &clusters, style.max_width);
+
+        let ellipsis = ellipsis(font);
+        let ellipsis_width: f32 = ellipsis
+            .iter()
+            .map(|&glyph| font.glyph_metrics(glyph, size).advance)
+            .sum();
+
+        let max_lines = style.max_lines.unwrap_or(usize::MAX).max(1);
+        let is_truncated = lines.len() > max_lines;
+        if is_truncated {
+            // The last line takes the rest of its paragraph, cut short to make
+            // room for the ellipsis.
+            let start = lines[max_lines - 1].clusters.start;
+            let end = lines[max_lines - 1..]
+                .iter()
+                .find(|line| line.is_last)
+                .map_or(clusters.len(), |line| line.clusters.end);
+
+            let max_width = style.max_width.unwrap_or(f32::INFINITY);
+            let mut line = Line::new(start);
+            for (index, cluster) in clusters.iter().enumerate().take(end).skip(start) {
+                if !cluster.is_whitespace
+                    && line.width + cluster.advance + ellipsis_width > max_width
+                {
+                    break;
+                }
+                line.push(index, cluster);
+            }
+
+            line.is_last = true;
+            line.has_ellipsis = true;
+            line.content_width += ellipsis_width;
+            lines.truncate(max_lines - 1);
+            lines.push(line);
+        }
+
+        let available_width = style.max_width.unwrap_or_else(|| {
+            lines
+                .iter()
+                .map(|line| line.content_width)
+                .fold(0.0, f32::max)
+        });
+
+        let metrics = font.metrics(size);
+        let line_height = metrics.line_height() * style.line_height;
+        // Extra space is split evenly above and below the glyphs.
+        let baseline_offset =
+            0.5 * (line_height - metrics.ascent - metrics.descent) + metrics.ascent;
+
+        let mut layout = Self {
+            fonts,
+            size,
+            line_height,
+            glyphs: Vec::new(),
+            runs: Vec::new(),
+            lines: Vec::with_capacity(lines.len()),
+            is_truncated,
+        };
+
+        for (line_index, line) in lines.iter().enumerate() {
+            let free = available_width - line.content_width;
+            let spaces = clusters[line.clusters.start..line.content_end]
+                .iter()
+                .filter(|cluster| cluster.is_whitespace)
+                .count();
+            let (offset, spacing) = match style.align {
+                TextAlign::Start => (0.0, 0.0),
+                TextAlign::Center => (0.5 * free, 0.0),
+                TextAlign::End => (free, 0.0),
+                TextAlign::Justify if line.is_last || spaces == 0 => (0.0, 0.0),
+                TextAlign::Justify => (0.0, free.max(0.0) / spaces as f32),
+            };
+
+            let top = line_index as f32 * line_height;
+            let baseline = top + baseline_offset;
+
+            // Visible clusters are placed in visual order, followed by trailing
+            // whitespace in logical order.
+            let visible = line.clusters.start..line.content_end;
+            let levels: Vec<u8> = clusters[visible.clone()]
+                .iter()
+                .map(|cluster| cluster.level)
+                .collect();
+            let order = visual_order(&levels)
+                .into_iter()
+                .map(|index| visible.start + index)
+                .chain(line.content_end..line.clusters.end);
+
+            let mut x = offset;
+            let mut visible_end = offset;
+            let mut edges = vec![(offset, offset); line.clusters.len()];
+            for index in order {
+                let cluster = &clusters[index];
+                if cluster.is_line_break {
+                    break;
+                }
+
+                let left = x;
+                x += cluster.advance;
+                if index < line.content_end {
+                    for glyph in &glyphs[cluster.glyphs.clone()] {
+                        layout.push_glyph(
+                            glyph.font,
+                            PositionedGlyph {
+                                glyph: glyph.glyph,
+                                position: Point::new(
+                                    left + glyph.offset.x,
+                                    baseline + glyph.offset.y,
+                                ),
+                            },
+                        );
+                    }
+                    if cluster.is_whitespace {
+                        x += spacing;
+                    }
+                    visible_end = x;
+                }
+                edges[index - line.clusters.start] = (left, x);
+            }
+
+            // Carets are placed at the leading edge of each cluster, and at the
+            // trailing edge of the last one.
+            let mut carets = Vec::new();
+            let mut end = clusters
+                .get(line.clusters.start)
+                .map_or(text.len(), |cluster| cluster.range.start);
+            let start = end;
+            let mut end_x = offset;
+
+            for (cluster, &(left, right)) in clusters[line.clusters.clone()].iter().zip(&edges) {
+                if cluster.is_line_break {
+                    break;
+                }
+
+                let (leading, trailing) = if cluster.level % 2 == 1 {
+                    (right, left)
+                } else {
+                    (left, right)
+                };
+                carets.push((cluster.range.start, leading));
+                end = cluster.range.end;
+                end_x = trailing;
+            }
+            carets.push((end, end_x));
+
+            if line.has_ellipsis {
+                for &glyph in &ellipsis {
+                    layout.push_glyph(
+                        0,
+                        PositionedGlyph {
+                            glyph,
+                            position: Point::new(visible_end, baseline),
+                        },
+                    );
+                    visible_end += font.glyph_metrics(glyph, size).advance;
+                }
+            }
+
+            layout.lines.push(TextLine {
+                range: start..end,
+                rect: Rect::new(
+                    Point::new(offset, top),
+                    Point::new(visible_end, top + line_height),
+                ),
+                baseline,
+                carets,
+            });
+        }
+
+        layout
+    }
+
+    /// The style's primary font.
+    #[must_use]
+    pub fn font(&self) -> &Font {
+        &self.fonts[0]
+    }
+
+    #[must_use]
+    pub fn size(&self) -> f32 {
+        self.size
+    }
+
+    /// Every glyph of the layout, whichever font it is drawn with.
+    #[must_use]
+    pub fn glyphs(&self) -> &[PositionedGlyph] {
+        &self.glyphs
+    }
+
+    /// The glyphs in runs that are drawn with the same font, each of which can
+    /// be drawn with [`RenderGraph::draw_glyph_run`].
+    ///
+    /// [`RenderGraph::draw_glyph_run`]: crate::RenderGraph::draw_glyph_run
+    pub fn glyph_runs(&self) -> impl Iterator<Item = (&Font, &[PositionedGlyph])> + '_ {
+        self.runs
+            .iter()
+            .map(|(font, range)| (&self.fonts[*font], &self.glyphs[range.clone()]))
+    }
+
+    fn push_glyph(&mut self, font: usize, glyph: PositionedGlyph) {
+        let index = self.glyphs.len();
+        self.glyphs.push(glyph);
+        match self.runs.last_mut() {
+            Some((last, run)) if *last == font => run.end = index + 1,
+            _ => self.runs.push((font, index..index + 1)),
+        }
+    }
+
+    /// The laid out lines, from top to bottom. There is always at least one
+    /// line, even if the text is empty.
+    #[must_use]
+    pub fn lines(&self) -> &[TextLine] {
+        &self.lines
+    }
+
+    /// Whether text was cut off because it did not fit within the maximum
+    /// number of lines.
+    #[must_use]
+    pub fn is_truncated(&self) -> bool {
+        self.is_truncated
+    }
+
+    /// The area covered by the lines of the layout.
+    #[must_use]
+    pub fn bounds(&self) -> Rect<f32, ObjectSpace> {
+        self.lines
+            .iter()
+            .map(|line| line.rect)
+            .reduce(|a, b| a.union(&b))
+            .unwrap_or_default()
+    }
+
+    /// The byte index of the caret position closest to a point. Points above
+    /// or below the text select the first or last line.
+    #[must_use]
+    pub fn hit_test(&self, point: Point<f32, ObjectSpace>) -> usize {
+        let line =
+            ((point.y / self.line_height).floor().max(0.0) as usize).min(self.lines.len() - 1);
+        self.lines[line]
+            .carets
+            .iter()
+            .min_by(|a, b| (a.1 - point.x).abs().total_cmp(&(b.1 - point.x).abs()))
+            .map_or(0, |&(index, _)| index)
+    }
+
+    /// A zero-width rectangle spanning the height of the line at which the
+    /// caret is drawn before the byte at `index`.
+    ///
+    /// Indices at the boundary between two wrapped lines are placed at the
+    /// start of the second line. Indices within a grapheme cluster, or within
+    /// text that was cut off, are placed before the cluster or at the end of
+    /// the line.
+    #[must_use]
+    pub fn caret_rect(&self, index: usize) -> Rect<f32, ObjectSpace> {
+        let line_index = self
+            .lines
+            .iter()
+            .rposition(|line| line.range.start <= index)
+            .unwrap_or(0);
+        let line = &self.lines[line_index];
+
+        let x = line
+            .carets
+            .iter()
+            .rev()
+            .find(|&&(caret, _)| caret <= index)
+            .or(line.carets.first())
+            .map_or(0.0, |&(_, x)| x);
+
+        let top = line_index as f32 * self.line_height;
+        Rect::new(Point::new(x, top), Point::new(x, top + self.line_height))
+    }
+}
+
+/// Shapes the text and groups its glyphs into clusters in logical order.
+/// Grapheme clusters that were shaped together, such as those of a ligature,
+/// become a single cluster, and control characters get clusters without
+/// glyphs.
+fn shape(fonts: &[Font], size: f32, text: &str) -> (Vec<ClusterGlyph>, Vec<Cluster>) {
+    struct Shaped {
+        glyphs: Vec<ClusterGlyph>,
+        advance: f32,
+        level: u8,
+    }
+
+    // The glyphs of each shaped cluster by the byte index it starts at, in
+    // visual order.
+    let mut shaped: HashMap<usize, Shaped> = HashMap::new();
+    for run in ShapedText::new(text, fonts, size).runs() {
+        let font = fonts
+            .iter()
+            .position(|font| font.id() == run.font.id())
+            .unwrap_or(0);
+        let mut pen = run.x;
+        let mut left = pen;
+        for ((glyph, &advance), &start) in run.glyphs.iter().zip(&run.advances).zip(&run.clusters) {
+            let cluster = shaped.entry(start).or_insert_with(|| {
+                left = pen;
+                Shaped {
+                    glyphs: Vec::new(),
+                    advance: 0.0,
+                    level: run.level,
+                }
+            });
+            cluster.glyphs.push(ClusterGlyph {
+                font,
+                glyph: glyph.glyph,
+                offset: Point::new(glyph.position.x - left, glyph.position.y),
+            });
+            cluster.advance += advance;
+            pen += advance;
+        }
+    }
+
+    let mut glyphs = Vec::with_capacity(text.len());
+    let mut clusters: Vec<Cluster> = Vec::new();
+    for (start, grapheme) in text.grapheme_indices(true) {
+        let end = start + grapheme.len();
+        let is_whitespace = grapheme.chars().all(char::is_whitespace);
+        let is_line_break = grapheme.chars().all(|c| {
+            matches!(
+                c,
+                '\n' | '\r' | '\u{0B}' | '\u{0C}' | '\u{85}' | '\u{2028}' | '\u{2029}'
+            )
+        });
+
+        match shaped.remove(&start) {
+            Some(cluster) => {
+                let first_glyph = glyphs.len();
+                glyphs.extend(cluster.glyphs);
+                clusters.push(Cluster {
+                    range: start..end,
+                    glyphs: first_glyph..glyphs.len(),
+                    advance: cluster.advance,
+                    level: cluster.level,
+                    is_whitespace,
+                    is_line_break,
+                });
+            }
+            // Grapheme clusters that were shaped with the one before them.
+            None if !grapheme.chars().all(char::is_control)
+                && clusters
+                    .last()
+                    .is_some_and(|cluster| !cluster.is_line_break) =>
+            {
+                let cluster = clusters.last_mut().unwrap();
+                cluster.range.end = end;
+                cluster.is_whitespace &= is_whitespace;
+            }
+            None => clusters.push(Cluster {
+                range: start..end,
+                glyphs: glyphs.len()..glyphs.len(),
+                advance: 0.0,
+                level: clusters.last().map_or(0, |cluster| cluster.level),
+                is_whitespace,
+                is_line_break,
+            }),
+        }
+    }
+
+    (glyphs, clusters)
+}
+
+/// The visual order of the clusters on a line, from left to right, given
+/// their embedding levels. From the highest level down to the lowest odd
+/// level, each sequence of clusters at that level or higher is reversed.
+fn visual_order(levels: &[u8]) -> Vec<usize> {
+    let mut order: Vec<usize> = (0..levels.len()).collect();
+    let highest = levels.iter().copied().max().unwrap_or(0);
+    let lowest_odd = levels
+        .iter()
+        .copied()
+        .filter(|level| level % 2 == 1)
+        .min()
+        .unwrap_or(highest + 1);
+
+    for level in (lowest_odd..=highest).rev() {
+        let mut start = 0;
+        while start < order.len() {
+            let len = order[start..]
+                .iter()
+                .take_while(|&&index| levels[index] >= level)
+                .count();
+            order[start..start + len].reverse();
+            start += len.max(1);
+        }
+    }
+
+    order
+}
+
+/// Breaks clusters into lines no wider than `max_width`, greedily fitting as
+/// many words onto each line as possible.
+fn break_lines(text: &str, clusters: &[Cluster], max_width: Option<f32>) -> Vec<Line> {
+    let mut lines = Vec::new();
+    let mut line = Line::new(0);
+
+    // The text between consecutive break opportunities cannot be broken,
+    // unless it is too wide to fit on a line by itself.
+    let mut start = 0;
+    for (offset, opportunity) in linebreaks(text) {
+        let end = start + clusters[start..].partition_point(|cluster| cluster.range.start < offset);
+        if end == start {
+            continue;
+        }
+
+        let mut word = Line::new(start);
+        for (index, cluster) in clusters.iter().enumerate().take(end).skip(start) {
+            word.push(index, cluster);
+        }
+
+        let fits = |line: &Line, width: f32| max_width.is_none_or(|max| line.width + width <= max);
+        if !line.is_empty() && !fits(&line, word.content_width) {
+            lines.push(std::mem::replace(&mut line, Line::new(start)));
+        }
+
+        if line.is_empty() && !fits(&line, word.content_width) {
+            for (index, cluster) in clusters.iter().enumerate().take(end).skip(start) {
+                if !line.is_empty() && !cluster.is_whitespace && !fits(&line, cluster.advance) {
+                    lines.push(std::mem::replace(&mut line, Line::new(index)));
+                }
+                line.push(index, cluster);
+            }
+        } else {
+            for (index, cluster) in clusters.iter().enumerate().take(end).skip(start) {
+                line.push(index, cluster);
+            }
+        }
+
+        if opportunity == BreakOpportunity::Mandatory {
+            line.is_last = true;
+            lines.push(std::mem::replace(&mut line, Line::new(end)));
+        }
+        start = end;
+    }
+
+    // Text that ends with a line break has an empty line after it, where the
+    // caret can be placed.
+    if clusters.last().is_none_or(|cluster| cluster.is_line_break) {
+        line.is_last = true;
+        lines.push(line);
+    }
+
+    lines
+}
+
+/// The glyphs used to show that text was cut off.
+fn ellipsis(font: &Font) -> Vec<GlyphId> {
+    if let Some(glyph) = font.glyph_index('\u{2026}') {
+        vec![glyph]
+    } else if let Some(glyph) = font.glyph_index('.') {
+        vec![glyph; 3]
+    } else {
+        Vec::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::text::test_font::{test_font, Glyph, TestFont};
+
+    // At 10px, letters are 5 wide, spaces 2.5 and periods 2. Lines are 11
+    // tall, with the baseline 8.5 below their top.
+    fn style(max_width: Option<f32>) -> TextStyle {
+        TextStyle {
+            max_width,
+            ..TextStyle::new(test_font(), 10.0)
+        }
+    }
+
+    /// A font with only a space and the Hebrew letters alef and bet, which
+    /// are 300 and 500 units wide.
+    fn hebrew_font() -> Font {
+        let font = TestFont {
+            units_per_em: 1000,
+            ascender: 800,
+            descender: -200,
+            line_gap: 0,
+            glyphs: vec![
+                TestFont::rect(600, 100, 0, 500, 700),
+                Glyph {
+                    advance: 250,
+                    contours: Vec::new(),
+                },
+                TestFont::rect(300, 50, 0, 250, 500),
+                TestFont::rect(500, 50, 0, 450, 500),
+            ],
+            cmap: vec![(' ', 1), ('\u{5D0}', 2), ('\u{5D1}', 3)],
+            kerning: Vec::new(),
+        };
+        Font::parse(font.build()).unwrap()
+    }
+
+    fn ranges(layout: &TextLayout) -> Vec<(usize, usize)> {
+        layout
+            .lines()
+            .iter()
+            .map(|line| (line.range.start, line.range.end))
+            .collect()
+    }
+
+    fn xs(layout: &TextLayout) -> Vec<f32> {
+        layout
+            .glyphs()
+            .iter()
+            .map(|glyph| glyph.position.x)
+            .collect()
+    }
+
+    #[test]
+    fn wrap_words() {
+        let layout = TextLayout::new("aa bb cc", &style(Some(12.0)));
+        assert_eq!(ranges(&layout), [(0, 3), (3, 6), (6, 8)]);
+        assert!(!layout.is_truncated());
+
+        let line = &layout.lines()[1];
+        assert_eq!(line.baseline, 19.5);
+        // Trailing whitespace is not part of the visible line.
+        assert_eq!(
+            line.rect,
+            Rect::new(Point::new(0.0, 11.0), Point::new(10.0, 22.0))
+        );
+        assert_eq!(
+            layout.bounds(),
+            Rect::new(Point::new(0.0, 0.0), Point::new(10.0, 33.0))
+        );
+
+        // Spaces have glyphs, but only between words.
+        assert_eq!(layout.glyphs().len(), 6);
+        assert_eq!(layout.glyphs()[2].position, Point::new(0.0, 19.5));
+
+        let layout = TextLayout::new("aa bb cc", &style(None));
+        assert_eq!(ranges(&layout), [(0, 8)]);
+    }
+
+    #[test]
+    fn line_breaks() {
+        let layout = TextLayout::new("a\nb\r\n\nc", &style(None));
+        assert_eq!(ranges(&layout), [(0, 1), (2, 3), (5, 5), (6, 7)]);
+
+        let layout = TextLayout::new("a\n", &style(None));
+        assert_eq!(ranges(&layout), [(0, 1), (2, 2)]);
+        assert_eq!(layout.caret_rect(2).p0, Point::new(0.0, 11.0));
+
+        let layout = TextLayout::new("", &style(None));
+        assert_eq!(ranges(&layout), [(0, 0)]);
+        assert_eq!(layout.bounds().p1.y, 11.0);
+    }
+
+    #[test]
+    fn break_long_words() {
+        let layout = TextLayout::new("aaaaa b", &style(Some(12.0)));
+        assert_eq!(ranges(&layout), [(0, 2), (2, 4), (4, 6), (6, 7)]);
+    }
+
+    #[test]
+    fn align() {
+        let mut style = style(Some(12.0));
+
+        style.align = TextAlign::Center;
+        let layout = TextLayout::new("aa bb c", &style);
+        assert_eq!(xs(&layout), [1.0, 6.0, 1.0, 6.0, 3.5]);
+
+        style.align = TextAlign::End;
+        let layout = TextLayout::new("aa bb c", &style);
+        assert_eq!(xs(&layout), [2.0, 7.0, 2.0, 7.0, 7.0]);
+        assert_eq!(layout.lines()[2].rect.p0.x, 7.0);
+
+        // Spaces within lines are stretched, except on the last line of each
+        // paragraph.
+        style.max_width = Some(13.0);
+        style.align = TextAlign::Justify;
+        let layout = TextLayout::new("a a aa\na a", &style);
+        assert_eq!(ranges(&layout), [(0, 4), (4, 6), (7, 10)]);
+        assert_eq!(xs(&layout), [0.0, 5.0, 8.0, 0.0, 5.0, 0.0, 5.0, 7.5]);
+        assert_eq!(layout.lines()[0].rect.p1.x, 13.0);
+    }
+
+    #[test]
+    fn line_height() {
+        let layout = TextLayout::new(
+            "a\nb",
+            &TextStyle {
+                line_height: 2.0,
+                ..style(None)
+            },
+        );
+        assert_eq!(layout.lines()[0].baseline, 14.0);
+        assert_eq!(layout.lines()[1].baseline, 36.0);
+        assert_eq!(layout.bounds().p1.y, 44.0);
+    }
+
+    #[test]
+    fn ellipsis() {
+        let style = TextStyle {
+            max_lines: Some(1),
+            ..style(Some(12.0))
+        };
+
+        // The last line keeps as much of its paragraph as fits beside the
+        // ellipsis.
+        let layout = TextLayout::new("aaa bbb", &style);
+        assert!(layout.is_truncated());
+        assert_eq!(ranges(&layout), [(0, 1)]);
+        assert_eq!(xs(&layout), [0.0, 5.0, 7.0, 9.0]);
+        assert_eq!(layout.lines()[0].rect.p1.x, 11.0);
+
+        // Whitespace before the ellipsis is dropped.
+        let layout = TextLayout::new("a bbb", &style);
+        assert_eq!(ranges(&layout), [(0, 2)]);
+        assert_eq!(xs(&layout), [0.0, 5.0, 7.0, 9.0]);
+
+        let layout = TextLayout::new("aa", &style);
+        assert!(!layout.is_truncated());
+        assert_eq!(xs(&layout), [0.0, 5.0]);
+
+        // Later paragraphs are cut off, even if the last line fits.
+        let layout = TextLayout::new("a\nb", &style);
+        assert!(layout.is_truncated());
+        assert_eq!(ranges(&layout), [(0, 1)]);
+        assert_eq!(xs(&layout), [0.0, 5.0, 7.0, 9.0]);
+    }
+
+    #[test]
+    fn hit_test_and_carets() {
+        let layout = TextLayout::new("aa bb cc", &style(Some(12.0)));
+
+        assert_eq!(layout.hit_test(Point::new(2.0, 5.0)), 0);
+        assert_eq!(layout.hit_test(Point::new(3.0, 5.0)), 1);
+        assert_eq!(layout.hit_test(Point::new(7.0, 15.0)), 4);
+        assert_eq!(layout.hit_test(Point::new(100.0, 15.0)), 6);
+        assert_eq!(layout.hit_test(Point::new(-5.0, -5.0)), 0);
+        assert_eq!(layout.hit_test(Point::new(100.0, 100.0)), 8);
+
+        assert_eq!(
+            layout.caret_rect(1),
+            Rect::new(Point::new(5.0, 0.0), Point::new(5.0, 11.0))
+        );
+        // The boundary between wrapped lines is at the start of the second.
+        assert_eq!(
+            layout.caret_rect(3),
+            Rect::new(Point::new(0.0, 11.0), Point::new(0.0, 22.0))
+        );
+        assert_eq!(layout.caret_rect(8).p0, Point::new(10.0, 22.0));
+
+        // Carets are placed between grapheme clusters. The combining accent
+        // is missing from the font, so it is drawn as `.notdef`, which takes
+        // no space because it is a mark.
+        let layout = TextLayout::new("ae\u{301}b", &style(None));
+        assert_eq!(layout.glyphs().len(), 4);
+        assert_eq!(layout.caret_rect(1).p0.x, 5.0);
+        assert_eq!(layout.caret_rect(3).p0.x, 5.0);
+        assert_eq!(layout.caret_rect(4).p0.x, 10.0);
+        assert_eq!(layout.hit_test(Point::new(12.0, 0.0)), 4);
+    }
+
+    #[test]
+    fn bidi() {
+        let style = TextStyle {
+            max_width: Some(15.0),
+            ..TextStyle::new(hebrew_font(), 10.0)
+        };
+
+        // Right-to-left runs are reversed on each line, and carets are placed
+        // at the right edge of right-to-left clusters.
+        let layout = TextLayout::new("\u{5D0}\u{5D1} \u{5D0}\u{5D1}", &style);
+        assert_eq!(ranges(&layout), [(0, 5), (5, 9)]);
+        assert_eq!(xs(&layout), [0.0, 5.0, 0.0, 5.0]);
+        assert_eq!(layout.caret_rect(0).p0.x, 8.0);
+        assert_eq!(layout.caret_rect(2).p0.x, 5.0);
+        assert_eq!(layout.caret_rect(9).p0, Point::new(0.0, 10.0));
+        assert_eq!(layout.hit_test(Point::new(1.0, 15.0)), 9);
+    }
+
+    #[test]
+    fn fallback_fonts() {
+        let style = TextStyle {
+            fallback_fonts: vec![hebrew_font()],
+            ..style(None)
+        };
+
+        // The Hebrew letters fall back to the second font, and are drawn as a
+        // separate run.
+        let layout = TextLayout::new("ab \u{5D0}\u{5D1}", &style);
+        assert_eq!(xs(&layout), [0.0, 5.0, 10.0, 12.5, 17.5]);
+
+        let runs: Vec<_> = layout
+            .glyph_runs()
+            .map(|(font, glyphs)| {
+                let ids: Vec<u16> = glyphs.iter().map(|glyph| glyph.glyph.0).collect();
+                (font.id(), ids)
+            })
+            .collect();
+        assert_eq!(
+            runs,
+            [
+                (style.font.id(), vec![6, 6, 1]),
+                (style.fallback_fonts[0].id(), vec![3, 2])
+            ]
+        );
+    }
+
+    #[test]
+    fn kerning() {
+        let layout = TextLayout::new("AVA", &style(None));
+        assert_eq!(xs(&layout), [0.0, 5.0, 11.0]);
+        // Kerning is split between the pair, so the caret is in the middle of
+        // the gap it removes.
+        assert_eq!(layout.caret_rect(1).p0.x, 5.5);
+        assert_eq!(layout.bounds().p1.x, 17.0);
+    }
+}
@@ -0,0 +1,19 @@
+//! Font loading, glyph rasterization and caching, text shaping and text
+//! layout.
+
+mod cache;
+mod font;
+mod layout;
+mod packer;
+mod rasterizer;
+mod shaper;
+
+#[cfg(test)]
+pub(crate) mod test_font;
+
+pub(crate) use cache::PinnedGlyphs;
+pub use cache::{GlyphCache, PositionedGlyph};
+pub use font::{Font, FontError, FontMetrics, GlyphId, GlyphMetrics};
+pub use layout::{TextAlign, TextLayout, TextLine, TextStyle};
+pub use rasterizer::GlyphBitmap;
+pub use shaper::{ShapedRun, ShapedText};
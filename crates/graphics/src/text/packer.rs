@@ -0,0 +1,280 @@
+use geometry::{Extent, Point, Rect, ScreenSpace};
+
+/// Shelf heights are rounded up to a multiple of this, so that rectangles of
+/// similar heights share shelves.
+const SHELF_ALIGNMENT: u32 = 4;
+
+/// Packs rectangles into a larger rectangle by placing them side by side on
+/// horizontal shelves. Rectangles can be freed, and the space they occupied
+/// is reused by later allocations.
+///
+/// Shelf packing wastes some space when rectangles vary in height, but it is
+/// fast and suits glyphs, which are mostly of similar sizes.
+pub(crate) struct ShelfPacker {
+    extent: Extent<u32, ScreenSpace>,
+    /// Shelves from top to bottom. The last shelf is never empty, so the
+    /// space below it is free.
+    shelves: Vec<Shelf>,
+}
+
+struct Shelf {
+    y: u32,
+    height: u32,
+    /// Unoccupied spans as `(x, width)`, sorted by `x` and never adjacent.
+    free: Vec<(u32, u32)>,
+    /// The number of rectangles on the shelf.
+    count: u32,
+}
+
+impl Shelf {
+    fn new(y: u32, height: u32, width: u32) -> Self {
+        Self {
+            y,
+            height,
+            free: vec![(0, width)],
+            count: 0,
+        }
+    }
+
+    fn allocate(&mut self, width: u32) -> Option<u32> {
+        let span = self.free.iter().position(|&(_, free)| free >= width)?;
+        let (x, free) = self.free[span];
+        if free == width {
+            self.free.remove(span);
+        } else {
+            self.free[span] = (x + width, free - width);
+        }
+        self.count += 1;
+        Some(x)
+    }
+
+    fn free(&mut self, x: u32, width: u32) {
+        let index = self.free.partition_point(|&(free, _)| free < x);
+        self.free.insert(index, (x, width));
+
+        // Merge with the following span, then the preceding one.
+        if let Some(&(next, next_width)) = self.free.get(index + 1) {
+            if x + width == next {
+                self.free[index].1 += next_width;
+                self.free.remove(index + 1);
+            }
+        }
+        if index > 0 {
+            let (previous, previous_width) = self.free[index - 1];
+            if previous + previous_width == x {
+                self.free[index - 1].1 += self.free[index].1;
+                self.free.remove(index);
+            }
+        }
+    }
+}
+
+impl ShelfPacker {
+    pub fn new(extent: Extent<u32, ScreenSpace>) -> Self {
+        Self {
+            extent,
+            shelves: Vec::new(),
+        }
+    }
+
+    pub fn extent(&self) -> Extent<u32, ScreenSpace> {
+        self.extent
+    }
+
+    /// Finds space for a rectangle, or returns `None` if there is none.
+    pub fn allocate(&mut self, extent: Extent<u32, ScreenSpace>) -> Option<Rect<u32, ScreenSpace>> {
+        if extent.width == 0
+            || extent.height == 0
+            || extent.width > self.extent.width
+            || extent.height > self.extent.height
+        {
+            return None;
+        }
+
+        let height = extent
+            .height
+            .next_multiple_of(SHELF_ALIGNMENT)
+            .min(self.extent.height);
+
+        let shelf = self
+            .find_shelf(extent.width, height)
+            .or_else(|| self.reuse_empty_shelf(height))
+            .or_else(|| self.add_shelf(height))?;
+
+        let shelf = &mut self.shelves[shelf];
+        let x = shelf.allocate(extent.width)?;
+        Some(Rect::new(
+            Point::new(x, shelf.y),
+            Point::new(x + extent.width, shelf.y + extent.height),
+        ))
+    }
+
+    /// Frees a rectangle returned by [`ShelfPacker::allocate`].
+    pub fn deallocate(&mut self, rect: &Rect<u32, ScreenSpace>) {
+        let index = self
+            .shelves
+            .iter()
+            .position(|shelf| shelf.y == rect.p0.y && shelf.count > 0)
+            .expect("rectangle was not allocated by this packer");
+
+        let shelf = &mut self.shelves[index];
+        shelf.free(rect.p0.x, rect.p1.x - rect.p0.x);
+        shelf.count -= 1;
+
+        if shelf.count == 0 {
+            shelf.free = vec![(0, self.extent.width)];
+            self.merge_empty_shelves(index);
+        }
+    }
+
+    /// Enlarges the area that rectangles are packed into. Allocated rectangles
+    /// keep their positions.
+    pub fn grow(&mut self, extent: Extent<u32, ScreenSpace>) {
+        assert!(extent.width >= self.extent.width && extent.height >= self.extent.height);
+
+        let old_width = self.extent.width;
+        if extent.width > old_width {
+            for shelf in &mut self.shelves {
+                shelf.free(old_width, extent.width - old_width);
+            }
+        }
+
+        self.extent = extent;
+    }
+
+    /// The first shelf of exactly `height` with room for `width`.
+    fn find_shelf(&self, width: u32, height: u32) -> Option<usize> {
+        self.shelves.iter().position(|shelf| {
+            shelf.count > 0
+                && shelf.height == height
+                && shelf.free.iter().any(|&(_, free)| free >= width)
+        })
+    }
+
+    /// Takes the smallest empty shelf that is at least `height` tall, and
+    /// splits off what is left of it as another empty shelf.
+    fn reuse_empty_shelf(&mut self, height: u32) -> Option<usize> {
+        let index = self
+            .shelves
+            .iter()
+            .enumerate()
+            .filter(|(_, shelf)| shelf.count == 0 && shelf.height >= height)
+            .min_by_key(|(_, shelf)| shelf.height)
+            .map(|(index, _)| index)?;
+
+        let shelf = &mut self.shelves[index];
+        if shelf.height > height {
+            let rest = Shelf::new(shelf.y + height, shelf.height - height, self.extent.width);
+            shelf.height = height;
+            self.shelves.insert(index + 1, rest);
+        }
+
+        Some(index)
+    }
+
+    /// Adds a shelf below the last one, if there is room.
+    fn add_shelf(&mut self, height: u32) -> Option<usize> {
+        let y = self
+            .shelves
+            .last()
+            .map_or(0, |shelf| shelf.y + shelf.height);
+        if y + height > self.extent.height {
+            return None;
+        }
+
+        self.shelves.push(Shelf::new(y, height, self.extent.width));
+        Some(self.shelves.len() - 1)
+    }
+
+    /// Merges the empty shelf at `index` with its empty neighbors, and removes
+    /// empty shelves from the bottom.
+    fn merge_empty_shelves(&mut self, mut index: usize) {
+        if index + 1 < self.shelves.len() && self.shelves[index + 1].count == 0 {
+            let next = self.shelves.remove(index + 1);
+            self.shelves[index].height += next.height;
+        }
+
+        if index > 0 && self.shelves[index - 1].count == 0 {
+            let shelf = self.shelves.remove(index);
+            index -= 1;
+            self.shelves[index].height += shelf.height;
+        }
+
+        if index == self.shelves.len() - 1 {
+            self.shelves.pop();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rect(x0: u32, y0: u32, x1: u32, y1: u32) -> Rect<u32, ScreenSpace> {
+        Rect::new(Point::new(x0, y0), Point::new(x1, y1))
+    }
+
+    fn overlaps(a: &Rect<u32, ScreenSpace>, b: &Rect<u32, ScreenSpace>) -> bool {
+        a.p0.x < b.p1.x && b.p0.x < a.p1.x && a.p0.y < b.p1.y && b.p0.y < a.p1.y
+    }
+
+    #[test]
+    fn pack_without_overlap() {
+        let mut packer = ShelfPacker::new(Extent::new(64, 64));
+
+        let mut rects = Vec::new();
+        for i in 0..40 {
+            let extent = Extent::new(3 + i % 7, 2 + i % 5);
+            let rect = packer.allocate(extent).unwrap();
+            assert_eq!(rect.p1.x - rect.p0.x, extent.width);
+            assert_eq!(rect.p1.y - rect.p0.y, extent.height);
+            assert!(rect.p1.x <= 64 && rect.p1.y <= 64);
+            rects.push(rect);
+        }
+
+        for (i, a) in rects.iter().enumerate() {
+            for b in &rects[i + 1..] {
+                assert!(!overlaps(a, b), "{a:?} overlaps {b:?}");
+            }
+        }
+
+        assert!(packer.allocate(Extent::new(65, 1)).is_none());
+        assert!(packer.allocate(Extent::new(0, 1)).is_none());
+    }
+
+    #[test]
+    fn reuse_freed_space() {
+        let mut packer = ShelfPacker::new(Extent::new(8, 8));
+
+        let a = packer.allocate(Extent::new(8, 4)).unwrap();
+        let b = packer.allocate(Extent::new(4, 4)).unwrap();
+        let c = packer.allocate(Extent::new(4, 4)).unwrap();
+        assert_eq!(b, rect(0, 4, 4, 8));
+        assert!(packer.allocate(Extent::new(1, 1)).is_none());
+
+        // Space within a shelf is reused by rectangles of a similar height.
+        packer.deallocate(&b);
+        let d = packer.allocate(Extent::new(3, 3)).unwrap();
+        assert_eq!(d, rect(0, 4, 3, 7));
+
+        // Empty shelves are merged, and can be split into different heights.
+        packer.deallocate(&a);
+        packer.deallocate(&c);
+        packer.deallocate(&d);
+        assert_eq!(packer.allocate(Extent::new(8, 8)), Some(rect(0, 0, 8, 8)));
+    }
+
+    #[test]
+    fn grow() {
+        let mut packer = ShelfPacker::new(Extent::new(8, 8));
+        let a = packer.allocate(Extent::new(8, 8)).unwrap();
+        assert!(packer.allocate(Extent::new(4, 4)).is_none());
+
+        packer.grow(Extent::new(16, 16));
+        let b = packer.allocate(Extent::new(8, 8)).unwrap();
+        let c = packer.allocate(Extent::new(16, 8)).unwrap();
+        assert_eq!(b.p0, Point::new(8, 0));
+        assert_eq!(c.p0, Point::new(0, 8));
+        assert!(!overlaps(&a, &b) && !overlaps(&a, &c));
+    }
+}
@@ -0,0 +1,273 @@
+use geometry::{Extent, Offset, Path, Point, ScreenSpace};
+
+/// How far flattened curves may stray from the outline, in pixels. This
+/// changes the coverage of edge pixels by at most a few levels.
+const TOLERANCE: f32 = 0.01;
+
+/// The coverage of a rasterized glyph, one byte per pixel from 0 (empty) to
+/// 255 (fully covered), in rows starting from the top-left corner.
+#[derive(Clone, Debug, PartialEq)]
+pub struct GlyphBitmap {
+    /// The position of the bitmap's top-left corner relative to the pen
+    /// position on the baseline.
+    pub offset: Offset<i32, ScreenSpace>,
+    pub extent: Extent<u32, ScreenSpace>,
+    pub coverage: Vec<u8>,
+}
+
+/// Rasterizes the area enclosed by a path into a bitmap that tightly fits it.
+/// Every subpath is treated as closed, and overlapping subpaths are combined
+/// with the nonzero fill rule.
+///
+/// Coverage is computed analytically: each edge adds the signed area it
+/// covers to an accumulation buffer, and a running sum along each row yields
+/// the area of each pixel that is inside the path.
+pub(crate) fn rasterize<Unit>(path: &Path<Unit>) -> Option<GlyphBitmap> {
+    let polylines = path.flatten(TOLERANCE);
+
+    let bounds = path.bounds()?;
+    let x0 = bounds.p0.x.floor();
+    let y0 = bounds.p0.y.floor();
+    let width = (bounds.p1.x.ceil() - x0) as usize;
+    let height = (bounds.p1.y.ceil() - y0) as usize;
+    if width == 0 || height == 0 {
+        return None;
+    }
+
+    let mut rasterizer = Rasterizer::new(width, height);
+    for polyline in &polylines {
+        let points = &polyline.points;
+        let closing = points.last().zip(points.first());
+        for (from, to) in points
+            .windows(2)
+            .map(|pair| (&pair[0], &pair[1]))
+            .chain(closing)
+        {
+            rasterizer.line(
+                Point::new(from.x - x0, from.y - y0),
+                Point::new(to.x - x0, to.y - y0),
+            );
+        }
+    }
+
+    Some(GlyphBitmap {
+        offset: Offset::new(x0 as i32, y0 as i32),
+        extent: Extent::new(width as u32, height as u32),
+        coverage: rasterizer.coverage(),
+    })
+}
+
+struct Rasterizer {
+    width: usize,
+    height: usize,
+    /// Rows have two extra cells, since an edge on the right border of the
+    /// bitmap adds area to the cells past it.
+    stride: usize,
+    accumulation: Vec<f32>,
+}
+
+impl Rasterizer {
+    fn new(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            stride: width + 2,
+            accumulation: vec![0.0; (width + 2) * height],
+        }
+    }
+
+    /// Adds a line to the outline. Points must lie within the bitmap.
+    fn line(&mut self, from: Point<f32>, to: Point<f32>) {
+        if from.y == to.y {
+            return;
+        }
+
+        // Walk the line downwards, remembering its direction for the winding.
+        let (direction, from, to) = if from.y < to.y {
+            (1.0, from, to)
+        } else {
+            (-1.0, to, from)
+        };
+
+        let dxdy = (to.x - from.x) / (to.y - from.y);
+        let mut x = from.x;
+        let max_x = self.width as f32;
+
+        let first_row = from.y.max(0.0) as usize;
+        let last_row = (to.y.ceil() as usize).min(self.height);
+        for row in first_row..last_row {
+            let cells = &mut self.accumulation[row * self.stride..(row + 1) * self.stride];
+
+            // The part of the line within this row.
+            let dy = to.y.min(row as f32 + 1.0) - from.y.max(row as f32);
+            let next_x = x + dxdy * dy;
+            let d = dy * direction;
+
+            let (left, right) = if x < next_x { (x, next_x) } else { (next_x, x) };
+            let (left, right) = (left.clamp(0.0, max_x), right.clamp(0.0, max_x));
+            let left_floor = left.floor();
+            let right_ceil = right.ceil();
+            let (left_cell, right_cell) = (left_floor as usize, right_ceil as usize);
+
+            if right_cell <= left_cell + 1 {
+                // The line stays within one cell. Area to the right of the
+                // line's midpoint belongs to this cell, and the remainder
+                // carries over to the next one.
+                let mid = 0.5 * (left + right) - left_floor;
+                cells[left_cell] += d - d * mid;
+                cells[left_cell + 1] += d * mid;
+            } else {
+                // The line crosses several cells. Each cell receives the area
+                // of the trapezoid under the line within it.
+                let slope = 1.0 / (right - left);
+                let left_fraction = left - left_floor;
+                let first = 0.5 * slope * (1.0 - left_fraction).powi(2);
+                let right_fraction = right - right_ceil + 1.0;
+                let last = 0.5 * slope * right_fraction.powi(2);
+
+                cells[left_cell] += d * first;
+                if right_cell == left_cell + 2 {
+                    cells[left_cell + 1] += d * (1.0 - first - last);
+                } else {
+                    let second = slope * (1.5 - left_fraction);
+                    cells[left_cell + 1] += d * (second - first);
+                    for cell in &mut cells[left_cell + 2..right_cell - 1] {
+                        *cell += d * slope;
+                    }
+                    let before_last = second + (right_cell - left_cell - 3) as f32 * slope;
+                    cells[right_cell - 1] += d * (1.0 - before_last - last);
+                }
+                cells[right_cell] += d * last;
+            }
+
+            x = next_x;
+        }
+    }
+
+    fn coverage(&self) -> Vec<u8> {
+        let mut coverage = Vec::with_capacity(self.width * self.height);
+        for row in self.accumulation.chunks_exact(self.stride) {
+            let mut area = 0.0;
+            for cell in &row[..self.width] {
+                area += cell;
+                coverage.push((area.abs().min(1.0) * 255.0).round() as u8);
+            }
+        }
+        coverage
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::text::test_font::test_font;
+
+    fn rows(bitmap: &GlyphBitmap) -> Vec<&[u8]> {
+        bitmap
+            .coverage
+            .chunks_exact(bitmap.extent.width as usize)
+            .collect()
+    }
+
+    #[test]
+    fn rectangle() {
+        let font = test_font();
+        let i = font.glyph_index('I').unwrap();
+
+        // At 10px, `I` covers x = 1..3 and y = -7..0 exactly.
+        let bitmap = font.rasterize(i, 10.0, Offset::zero()).unwrap();
+        assert_eq!(bitmap.offset, Offset::new(1, -7));
+        assert_eq!(bitmap.extent, Extent::new(2, 7));
+        assert!(bitmap.coverage.iter().all(|&c| c == 255));
+
+        // Shifting by half a pixel splits the edge columns.
+        let bitmap = font.rasterize(i, 10.0, Offset::new(0.5, 0.0)).unwrap();
+        assert_eq!(bitmap.offset, Offset::new(1, -7));
+        assert_eq!(bitmap.extent, Extent::new(3, 7));
+        for row in rows(&bitmap) {
+            assert_eq!(row, [128, 255, 128]);
+        }
+    }
+
+    #[test]
+    fn triangle() {
+        let font = test_font();
+        let a = font.glyph_index('A').unwrap();
+        let bitmap = font.rasterize(a, 20.0, Offset::zero()).unwrap();
+        assert_eq!(bitmap.offset, Offset::new(0, -14));
+        assert_eq!(bitmap.extent, Extent::new(12, 14));
+
+        // The triangle covers half of its bounding box, and is symmetric.
+        let total: u32 = bitmap.coverage.iter().map(|&c| u32::from(c)).sum();
+        let expected = 255.0 * 12.0 * 14.0 / 2.0;
+        assert!((total as f32 - expected).abs() / expected < 0.01);
+        for row in rows(&bitmap) {
+            for (left, right) in row.iter().zip(row.iter().rev()) {
+                assert!(left.abs_diff(*right) <= 1);
+            }
+        }
+        assert_eq!(rows(&bitmap)[13][6], 255);
+        assert_eq!(rows(&bitmap)[0][0], 0);
+    }
+
+    #[test]
+    fn cubic() {
+        // A circle of radius 4 made of cubic curves.
+        let k = 4.0 * 0.552_284_8;
+        let mut builder = Path::<()>::builder();
+        builder
+            .move_to(Point::new(4.0, 0.0))
+            .cubic_to(
+                Point::new(4.0 + k, 0.0),
+                Point::new(8.0, 4.0 - k),
+                Point::new(8.0, 4.0),
+            )
+            .cubic_to(
+                Point::new(8.0, 4.0 + k),
+                Point::new(4.0 + k, 8.0),
+                Point::new(4.0, 8.0),
+            )
+            .cubic_to(
+                Point::new(4.0 - k, 8.0),
+                Point::new(0.0, 4.0 + k),
+                Point::new(0.0, 4.0),
+            )
+            .cubic_to(
+                Point::new(0.0, 4.0 - k),
+                Point::new(4.0 - k, 0.0),
+                Point::new(4.0, 0.0),
+            )
+            .close();
+
+        let bitmap = rasterize(&builder.build()).unwrap();
+        assert_eq!(bitmap.extent, Extent::new(8, 8));
+
+        let total: f32 = bitmap.coverage.iter().map(|&c| f32::from(c) / 255.0).sum();
+        // Flattening cuts slightly into the circle.
+        let area = std::f32::consts::PI * 16.0;
+        assert!(total < area && total > area - 0.25, "{total}");
+        assert_eq!(rows(&bitmap)[4][4], 255);
+        assert_eq!(rows(&bitmap)[0][0], 0);
+    }
+
+    #[test]
+    fn overlapping_contours() {
+        // Two overlapping squares with the same winding do not exceed full
+        // coverage.
+        let mut builder = Path::<()>::builder();
+        for origin in [0.0, 1.0] {
+            builder
+                .move_to(Point::new(origin, origin))
+                .line_to(Point::new(origin + 2.0, origin))
+                .line_to(Point::new(origin + 2.0, origin + 2.0))
+                .line_to(Point::new(origin, origin + 2.0))
+                .close();
+        }
+
+        let bitmap = rasterize(&builder.build()).unwrap();
+        assert_eq!(
+            rows(&bitmap),
+            [[255, 255, 0], [255, 255, 255], [0, 255, 255]]
+        );
+    }
+}
@@ -0,0 +1,303 @@
+use std::ops::Range;
+
+use geometry::Point;
+use rustybuzz::{Direction, UnicodeBuffer};
+use unicode_bidi::{BidiInfo, Level};
+use unicode_segmentation::UnicodeSegmentation;
+
+use super::{Font, GlyphId, PositionedGlyph};
+
+/// Glyphs shaped from a run of text with a single font and direction.
+#[derive(Clone, Debug)]
+pub struct ShapedRun {
+    pub font: Font,
+    /// The bytes of the source text that the run was shaped from.
+    pub range: Range<usize>,
+    /// The run's embedding level from the bidirectional algorithm. Runs with
+    /// odd levels are right-to-left.
+    pub level: u8,
+    /// The glyphs in visual order, from left to right, with their pen
+    /// positions on the baseline.
+    pub glyphs: Vec<PositionedGlyph>,
+    /// The advance of each glyph. Glyph positions also include offsets from
+    /// the pen, such as those of marks, so they are not always the sum of
+    /// the advances before them.
+    pub advances: Vec<f32>,
+    /// For each glyph, the byte index in the source text of the start of the
+    /// cluster it was shaped from. All glyphs of a ligature or of a base
+    /// character and its marks share a cluster.
+    pub clusters: Vec<usize>,
+    /// The horizontal position at which the run starts.
+    pub x: f32,
+    /// The horizontal distance covered by the run's glyphs.
+    pub advance: f32,
+}
+
+impl ShapedRun {
+    #[must_use]
+    pub fn is_rtl(&self) -> bool {
+        self.level % 2 == 1
+    }
+}
+
+/// A line of text converted into glyphs by applying the fonts' substitution
+/// and positioning features, such as ligatures, contextual forms, kerning and
+/// mark positioning.
+///
+/// Text is split into runs with the Unicode Bidirectional Algorithm, and runs
+/// are placed in visual order. The direction of each paragraph comes from its
+/// first strong character. Each run is shaped with the first font that has
+/// glyphs for all of its characters, so that text in scripts that the primary
+/// font does not cover can fall back to other fonts. Characters that no font
+/// covers are drawn with the primary font's `.notdef` glyph.
+///
+/// Glyph positions start at the origin on the baseline, with y pointing down.
+/// Each paragraph starts again at the origin, so text with more than one
+/// paragraph must be placed on separate lines by the caller, as
+/// [`TextLayout`] does. Control characters, such as line breaks, are not
+/// drawn.
+///
+/// [`TextLayout`]: super::TextLayout
+#[derive(Clone, Debug)]
+pub struct ShapedText {
+    runs: Vec<ShapedRun>,
+    advance: f32,
+}
+
+impl ShapedText {
+    /// Shapes `text` at `size` pixels per em, with fonts in order of
+    /// preference.
+    ///
+    /// ## Panics
+    ///
+    /// Panics if `fonts` is empty.
+    #[must_use]
+    pub fn new(text: &str, fonts: &[Font], size: f32) -> Self {
+        assert!(!fonts.is_empty(), "at least one font is required");
+
+        let bidi = BidiInfo::new(text, None);
+        let mut runs = Vec::new();
+        let mut advance: f32 = 0.0;
+
+        for paragraph in &bidi.paragraphs {
+            let mut x = 0.0;
+            let (levels, visual_runs) = bidi.visual_runs(paragraph, paragraph.range.clone());
+            for range in visual_runs {
+                let level = levels[range.start];
+                let mut font_runs = split_by_font(text, range, fonts);
+                if level.is_rtl() {
+                    font_runs.reverse();
+                }
+
+                for (font, range) in font_runs {
+                    let run = shape_run(text, range, &fonts[font], size, level, x);
+                    x += run.advance;
+                    runs.push(run);
+                }
+            }
+            advance = advance.max(x);
+        }
+
+        Self { runs, advance }
+    }
+
+    /// The shaped runs of each paragraph in turn, in visual order from left
+    /// to right.
+    #[must_use]
+    pub fn runs(&self) -> &[ShapedRun] {
+        &self.runs
+    }
+
+    /// The horizontal distance covered by the widest paragraph.
+    #[must_use]
+    pub fn advance(&self) -> f32 {
+        self.advance
+    }
+}
+
+/// Splits a run of text into runs of grapheme clusters that one font has
+/// glyphs for, in logical order. Clusters stay with the preceding run when
+/// its font covers them, so that spaces and punctuation do not split runs.
+fn split_by_font(text: &str, range: Range<usize>, fonts: &[Font]) -> Vec<(usize, Range<usize>)> {
+    let mut runs: Vec<(usize, Range<usize>)> = Vec::new();
+
+    for (offset, grapheme) in text[range.clone()].grapheme_indices(true) {
+        if grapheme.chars().all(char::is_control) {
+            continue;
+        }
+
+        let start = range.start + offset;
+        let end = start + grapheme.len();
+        let covers = |font: &Font| {
+            grapheme
+                .chars()
+                .all(|c| c.is_control() || font.glyph_index(c).is_some())
+        };
+
+        match runs.last_mut() {
+            Some((font, run)) if run.end == start && covers(&fonts[*font]) => run.end = end,
+            _ => {
+                let font = fonts.iter().position(covers).unwrap_or(0);
+                match runs.last_mut() {
+                    Some((last, run)) if *last == font && run.end == start => run.end = end,
+                    _ => runs.push((font, start..end)),
+                }
+            }
+        }
+    }
+
+    runs
+}
+
+fn shape_run(
+    text: &str,
+    range: Range<usize>,
+    font: &Font,
+    size: f32,
+    level: Level,
+    x: f32,
+) -> ShapedRun {
+    let mut buffer = UnicodeBuffer::new();
+    buffer.push_str(&text[range.clone()]);
+    buffer.set_direction(if level.is_rtl() {
+        Direction::RightToLeft
+    } else {
+        Direction::LeftToRight
+    });
+    buffer.guess_segment_properties();
+
+    let output = rustybuzz::shape(&font.shaping_face(), &[], buffer);
+    let scale = size / f32::from(font.units_per_em());
+
+    let mut glyphs = Vec::with_capacity(output.len());
+    let mut advances = Vec::with_capacity(output.len());
+    let mut clusters = Vec::with_capacity(output.len());
+    let mut advance = 0.0;
+    for (info, position) in output.glyph_infos().iter().zip(output.glyph_positions()) {
+        glyphs.push(PositionedGlyph {
+            glyph: GlyphId(info.glyph_id as u16),
+            position: Point::new(
+                x + advance + position.x_offset as f32 * scale,
+                -position.y_offset as f32 * scale,
+            ),
+        });
+        clusters.push(range.start + info.cluster as usize);
+        advances.push(position.x_advance as f32 * scale);
+        advance += position.x_advance as f32 * scale;
+    }
+
+    ShapedRun {
+        font: font.clone(),
+        range,
+        level: level.number(),
+        glyphs,
+        advances,
+        clusters,
+        x,
+        advance,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::text::test_font::{test_font, Glyph, TestFont};
+
+    /// A font with only a space and the Hebrew letters alef and bet, which
+    /// are 300 and 500 units wide.
+    fn hebrew_font() -> Font {
+        let font = TestFont {
+            units_per_em: 1000,
+            ascender: 800,
+            descender: -200,
+            line_gap: 0,
+            glyphs: vec![
+                TestFont::rect(600, 100, 0, 500, 700),
+                Glyph {
+                    advance: 250,
+                    contours: Vec::new(),
+                },
+                TestFont::rect(300, 50, 0, 250, 500),
+                TestFont::rect(500, 50, 0, 450, 500),
+            ],
+            cmap: vec![(' ', 1), ('\u{5D0}', 2), ('\u{5D1}', 3)],
+            kerning: Vec::new(),
+        };
+        Font::parse(font.build()).unwrap()
+    }
+
+    fn glyphs(run: &ShapedRun) -> Vec<(u16, f32)> {
+        run.glyphs
+            .iter()
+            .map(|glyph| (glyph.glyph.0, glyph.position.x))
+            .collect()
+    }
+
+    #[test]
+    fn kerning() {
+        let shaped = ShapedText::new("AVA", &[test_font()], 10.0);
+        let [run] = shaped.runs() else {
+            panic!("expected one run");
+        };
+        assert!(!run.is_rtl());
+        assert_eq!(glyphs(run), [(4, 0.0), (5, 5.0), (4, 11.0)]);
+        assert_eq!(run.clusters, [0, 1, 2]);
+        assert_eq!(shaped.advance(), 17.0);
+    }
+
+    #[test]
+    fn fallback() {
+        // Bet and alef fall back to the Hebrew font, and are reversed.
+        let text = "ab \u{5D0}\u{5D1}";
+        let shaped = ShapedText::new(text, &[test_font(), hebrew_font()], 10.0);
+        let [latin, hebrew] = shaped.runs() else {
+            panic!("expected two runs");
+        };
+
+        assert_eq!(latin.range, 0..3);
+        assert_eq!(latin.level, 0);
+        assert_eq!(glyphs(latin), [(6, 0.0), (6, 5.0), (1, 10.0)]);
+
+        assert_eq!(hebrew.range, 3..7);
+        assert!(hebrew.is_rtl());
+        assert_eq!(glyphs(hebrew), [(3, 12.5), (2, 17.5)]);
+        assert_eq!(hebrew.clusters, [5, 3]);
+        assert_eq!(shaped.advance(), 20.5);
+
+        // Characters that no font covers use the first font.
+        let shaped = ShapedText::new("\u{20AC}", &[test_font(), hebrew_font()], 10.0);
+        assert_eq!(glyphs(&shaped.runs()[0]), [(0, 0.0)]);
+    }
+
+    #[test]
+    fn bidi() {
+        // The paragraph is right-to-left, so the embedded Latin text is placed
+        // to the left of the Hebrew text before it.
+        let text = "\u{5D0}\u{5D1} ab\nc";
+        let shaped = ShapedText::new(text, &[test_font(), hebrew_font()], 10.0);
+        let runs: Vec<_> = shaped
+            .runs()
+            .iter()
+            .map(|run| (run.range.clone(), run.level))
+            .collect();
+        assert_eq!(runs, [(5..7, 2), (0..5, 1), (8..9, 0)]);
+
+        let hebrew = &shaped.runs()[1];
+        assert_eq!(glyphs(hebrew), [(1, 10.0), (3, 12.5), (2, 17.5)]);
+        assert_eq!(hebrew.clusters, [4, 2, 0]);
+
+        // The line break is not drawn, and the next paragraph is left-to-right
+        // and starts again at the origin.
+        assert_eq!(glyphs(&shaped.runs()[2]), [(6, 0.0)]);
+        assert_eq!(shaped.advance(), 20.5);
+    }
+
+    #[test]
+    fn marks_share_clusters() {
+        // The combining accent is missing from both fonts, so it is drawn
+        // with the first font's `.notdef` glyph in the same cluster.
+        let shaped = ShapedText::new("e\u{301}a", &[test_font()], 10.0);
+        let run = &shaped.runs()[0];
+        assert_eq!(run.clusters, [0, 0, 3]);
+    }
+}
@@ -0,0 +1,261 @@
+//! A builder for minimal TrueType fonts, so that tests do not depend on font
+//! files installed on the system.
+
+/// A point on a glyph's outline, in font units with y pointing up, and
+/// whether it is on the curve or a quadratic control point.
+pub type OutlinePoint = (i16, i16, bool);
+
+pub struct Glyph {
+    pub advance: u16,
+    pub contours: Vec<Vec<OutlinePoint>>,
+}
+
+pub struct TestFont {
+    pub units_per_em: u16,
+    pub ascender: i16,
+    pub descender: i16,
+    pub line_gap: i16,
+    /// Glyph 0 is `.notdef`.
+    pub glyphs: Vec<Glyph>,
+    /// Maps characters to glyph indices.
+    pub cmap: Vec<(char, u16)>,
+    /// Horizontal kerning between pairs of glyph indices, in font units.
+    pub kerning: Vec<(u16, u16, i16)>,
+}
+
+impl TestFont {
+    /// A rectangular glyph covering `x0..x1` and `y0..y1`.
+    pub fn rect(advance: u16, x0: i16, y0: i16, x1: i16, y1: i16) -> Glyph {
+        Glyph {
+            advance,
+            contours: vec![vec![
+                (x0, y0, true),
+                (x0, y1, true),
+                (x1, y1, true),
+                (x1, y0, true),
+            ]],
+        }
+    }
+
+    /// Serializes the font with just the tables needed to load and draw it.
+    pub fn build(&self) -> Vec<u8> {
+        let mut glyf = Vec::new();
+        let mut loca = Vec::new();
+        for glyph in &self.glyphs {
+            loca.extend_from_slice(&(glyf.len() as u32).to_be_bytes());
+            write_glyph(&mut glyf, glyph);
+        }
+        loca.extend_from_slice(&(glyf.len() as u32).to_be_bytes());
+
+        let mut head = Vec::new();
+        head.extend_from_slice(&0x0001_0000u32.to_be_bytes()); // version
+        head.extend_from_slice(&0x0001_0000u32.to_be_bytes()); // revision
+        head.extend_from_slice(&0u32.to_be_bytes()); // checksum adjustment
+        head.extend_from_slice(&0x5F0F_3CF5u32.to_be_bytes()); // magic
+        head.extend_from_slice(&0u16.to_be_bytes()); // flags
+        head.extend_from_slice(&self.units_per_em.to_be_bytes());
+        head.extend_from_slice(&[0; 16]); // created, modified
+        head.extend_from_slice(&[0; 8]); // bounds
+        head.extend_from_slice(&[0; 6]); // style, smallest size, direction
+        head.extend_from_slice(&1i16.to_be_bytes()); // long loca offsets
+        head.extend_from_slice(&0i16.to_be_bytes()); // glyph data format
+
+        let num_glyphs = self.glyphs.len() as u16;
+
+        let mut hhea = Vec::new();
+        hhea.extend_from_slice(&0x0001_0000u32.to_be_bytes());
+        hhea.extend_from_slice(&self.ascender.to_be_bytes());
+        hhea.extend_from_slice(&self.descender.to_be_bytes());
+        hhea.extend_from_slice(&self.line_gap.to_be_bytes());
+        hhea.extend_from_slice(&[0; 24]);
+        hhea.extend_from_slice(&num_glyphs.to_be_bytes());
+
+        let mut maxp = Vec::new();
+        maxp.extend_from_slice(&0x0000_5000u32.to_be_bytes());
+        maxp.extend_from_slice(&num_glyphs.to_be_bytes());
+
+        let mut hmtx = Vec::new();
+        for glyph in &self.glyphs {
+            let left = glyph
+                .contours
+                .iter()
+                .flatten()
+                .map(|point| point.0)
+                .min()
+                .unwrap_or(0);
+            hmtx.extend_from_slice(&glyph.advance.to_be_bytes());
+            hmtx.extend_from_slice(&left.to_be_bytes());
+        }
+
+        // A format 12 subtable, with one group per character.
+        let mut cmap = Vec::new();
+        let mut mappings = self.cmap.clone();
+        mappings.sort_unstable();
+        cmap.extend_from_slice(&0u16.to_be_bytes());
+        cmap.extend_from_slice(&1u16.to_be_bytes());
+        cmap.extend_from_slice(&3u16.to_be_bytes());
+        cmap.extend_from_slice(&10u16.to_be_bytes());
+        cmap.extend_from_slice(&12u32.to_be_bytes());
+        cmap.extend_from_slice(&12u16.to_be_bytes());
+        cmap.extend_from_slice(&0u16.to_be_bytes());
+        cmap.extend_from_slice(&(16 + 12 * mappings.len() as u32).to_be_bytes());
+        cmap.extend_from_slice(&0u32.to_be_bytes());
+        cmap.extend_from_slice(&(mappings.len() as u32).to_be_bytes());
+        for (c, glyph) in mappings {
+            cmap.extend_from_slice(&u32::from(c).to_be_bytes());
+            cmap.extend_from_slice(&u32::from(c).to_be_bytes());
+            cmap.extend_from_slice(&u32::from(glyph).to_be_bytes());
+        }
+
+        // A format 0 subtable. Binary search parameters are not used.
+        let mut kern = Vec::new();
+        let mut pairs = self.kerning.clone();
+        pairs.sort_unstable();
+        kern.extend_from_slice(&0u16.to_be_bytes());
+        kern.extend_from_slice(&1u16.to_be_bytes());
+        kern.extend_from_slice(&0u16.to_be_bytes());
+        kern.extend_from_slice(&(14 + 6 * pairs.len() as u16).to_be_bytes());
+        kern.extend_from_slice(&1u16.to_be_bytes());
+        kern.extend_from_slice(&(pairs.len() as u16).to_be_bytes());
+        kern.extend_from_slice(&[0; 6]);
+        for (left, right, value) in pairs {
+            kern.extend_from_slice(&left.to_be_bytes());
+            kern.extend_from_slice(&right.to_be_bytes());
+            kern.extend_from_slice(&value.to_be_bytes());
+        }
+
+        let tables: [(&[u8; 4], Vec<u8>); 8] = [
+            (b"cmap", cmap),
+            (b"glyf", glyf),
+            (b"head", head),
+            (b"hhea", hhea),
+            (b"hmtx", hmtx),
+            (b"kern", kern),
+            (b"loca", loca),
+            (b"maxp", maxp),
+        ];
+
+        let mut font = Vec::new();
+        font.extend_from_slice(&0x0001_0000u32.to_be_bytes());
+        font.extend_from_slice(&(tables.len() as u16).to_be_bytes());
+        font.extend_from_slice(&[0; 6]);
+
+        let mut offset = 12 + 16 * tables.len();
+        for (tag, data) in &tables {
+            font.extend_from_slice(*tag);
+            font.extend_from_slice(&0u32.to_be_bytes());
+            font.extend_from_slice(&(offset as u32).to_be_bytes());
+            font.extend_from_slice(&(data.len() as u32).to_be_bytes());
+            offset += data.len().next_multiple_of(4);
+        }
+        for (_, data) in &tables {
+            font.extend_from_slice(data);
+            font.resize(font.len().next_multiple_of(4), 0);
+        }
+
+        font
+    }
+}
+
+fn write_glyph(glyf: &mut Vec<u8>, glyph: &Glyph) {
+    if glyph.contours.is_empty() {
+        return;
+    }
+
+    let points = || glyph.contours.iter().flatten();
+    let x_min = points().map(|p| p.0).min().unwrap();
+    let y_min = points().map(|p| p.1).min().unwrap();
+    let x_max = points().map(|p| p.0).max().unwrap();
+    let y_max = points().map(|p| p.1).max().unwrap();
+
+    glyf.extend_from_slice(&(glyph.contours.len() as i16).to_be_bytes());
+    for value in [x_min, y_min, x_max, y_max] {
+        glyf.extend_from_slice(&value.to_be_bytes());
+    }
+
+    let mut end = 0;
+    for contour in &glyph.contours {
+        end += contour.len() as u16;
+        glyf.extend_from_slice(&(end - 1).to_be_bytes());
+    }
+    glyf.extend_from_slice(&0u16.to_be_bytes()); // instructions
+
+    // Coordinates are written as 16-bit deltas from the previous point.
+    for point in points() {
+        glyf.push(u8::from(point.2));
+    }
+    let mut previous = 0;
+    for point in points() {
+        glyf.extend_from_slice(&(point.0 - previous).to_be_bytes());
+        previous = point.0;
+    }
+    let mut previous = 0;
+    for point in points() {
+        glyf.extend_from_slice(&(point.1 - previous).to_be_bytes());
+        previous = point.1;
+    }
+
+    glyf.resize(glyf.len().next_multiple_of(4), 0);
+}
+
+/// A font with 1000 units per em and a few simple glyphs:
+///
+/// - Latin letters and digits are 500 units wide boxes, 400 units tall,
+///   except for `I`, `O`, `A` and `V`.
+/// - `I` is a 200 by 700 unit bar, 100 units from the origin.
+/// - `O` is a 500 by 700 unit oval made of quadratic curves.
+/// - `A` and `V` are triangles, kerned by -100 units.
+/// - `.` is a 100 unit square, 200 units wide.
+/// - Space is 250 units wide.
+pub fn test_font() -> crate::Font {
+    let glyphs = vec![
+        TestFont::rect(600, 100, 0, 500, 700),
+        Glyph {
+            advance: 250,
+            contours: Vec::new(),
+        },
+        TestFont::rect(400, 100, 0, 300, 700),
+        Glyph {
+            advance: 600,
+            contours: vec![vec![
+                (300, 0, true),
+                (550, 0, false),
+                (550, 350, true),
+                (550, 700, false),
+                (300, 700, true),
+                (50, 700, false),
+                (50, 350, true),
+                (50, 0, false),
+            ]],
+        },
+        Glyph {
+            advance: 600,
+            contours: vec![vec![(0, 0, true), (300, 700, true), (600, 0, true)]],
+        },
+        Glyph {
+            advance: 600,
+            contours: vec![vec![(0, 700, true), (600, 700, true), (300, 0, true)]],
+        },
+        TestFont::rect(500, 50, 0, 450, 400),
+        TestFont::rect(200, 50, 0, 150, 100),
+    ];
+
+    let mut cmap = vec![(' ', 1), ('I', 2), ('O', 3), ('A', 4), ('V', 5), ('.', 7)];
+    for c in ('a'..='z').chain('B'..='Z').chain('0'..='9') {
+        if !matches!(c, 'I' | 'O' | 'V') {
+            cmap.push((c, 6));
+        }
+    }
+
+    let font = TestFont {
+        units_per_em: 1000,
+        ascender: 800,
+        descender: -200,
+        line_gap: 100,
+        glyphs,
+        cmap,
+        kerning: vec![(4, 5, -100)],
+    };
+
+    crate::Font::parse(font.build()).unwrap()
+}
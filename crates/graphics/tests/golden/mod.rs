@@ -0,0 +1,252 @@
+//! Golden image tests for render graph output.
+//!
+//! Each test renders a [`RenderGraph`] into an offscreen image and compares the
+//! result against a reference PNG checked in under `tests/references`. When
+//! the images differ, the rendered image and a diff image are written to
+//! `<target>/tmp/golden` for inspection. In the diff image, mismatched pixels
+//! are highlighted in magenta over a faded copy of the reference.
+//!
+//! To regenerate the references, run the tests with `GLIMMER_UPDATE_GOLDEN=1`
+//! set and review the new images before committing them.
+
+// Not every test binary uses every helper.
+#![allow(dead_code)]
+
+use std::{
+    fs::File,
+    io::BufWriter,
+    path::{Path, PathBuf},
+};
+
+use geometry::{Extent, ScreenSpace};
+use graphics::{GraphicsConfig, GraphicsContext, ImageFormat, RenderGraph};
+
+/// How far a rendered image may deviate from its reference and still pass.
+#[derive(Debug, Clone, Copy)]
+pub struct Tolerance {
+    /// The largest per-channel difference, in 8-bit units, for two pixels to
+    /// be considered equal.
+    pub max_channel_difference: u8,
+    /// The number of pixels that may exceed `max_channel_difference`.
+    pub max_mismatched_pixels: usize,
+}
+
+impl Default for Tolerance {
+    fn default() -> Self {
+        Self {
+            max_channel_difference: 1,
+            max_mismatched_pixels: 0,
+        }
+    }
+}
+
+/// An 8-bit RGBA image.
+pub struct Rgba8Image {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<u8>,
+}
+
+/// Renders `graph` into an image of the given size and compares it against the
+/// reference image `name` using the default tolerance.
+///
+/// ## Panics
+///
+/// Panics if the rendered image does not match the reference, or if the
+/// reference does not exist.
+pub fn assert_golden(name: &str, extent: Extent<u32, ScreenSpace>, graph: &RenderGraph) {
+    assert_golden_with(name, extent, graph, Tolerance::default());
+}
+
+/// Like [`assert_golden`], but with a custom tolerance.
+pub fn assert_golden_with(
+    name: &str,
+    extent: Extent<u32, ScreenSpace>,
+    graph: &RenderGraph,
+    tolerance: Tolerance,
+) {
+    check(name, &render(extent, graph), tolerance);
+}
+
+/// Like [`assert_golden`], but draws with an existing context. Use this for
+/// graphs that refer to resources created by the context, such as textures.
+pub fn assert_golden_with_context(
+    context: &GraphicsContext,
+    name: &str,
+    extent: Extent<u32, ScreenSpace>,
+    graph: &RenderGraph,
+) {
+    check(
+        name,
+        &render_with_context(context, extent, graph),
+        Tolerance::default(),
+    );
+}
+
+fn check(name: &str, actual: &Rgba8Image, tolerance: Tolerance) {
+    let reference_path = reference_dir().join(format!("{name}.png"));
+
+    if std::env::var_os("GLIMMER_UPDATE_GOLDEN").is_some() {
+        write_png(&reference_path, actual);
+        return;
+    }
+
+    let Some(expected) = read_png(&reference_path) else {
+        panic!(
+            "missing reference image {}; rerun with GLIMMER_UPDATE_GOLDEN=1 to create it",
+            reference_path.display()
+        );
+    };
+
+    assert!(
+        expected.width == actual.width && expected.height == actual.height,
+        "{name}: reference is {}x{} but the rendered image is {}x{}",
+        expected.width,
+        expected.height,
+        actual.width,
+        actual.height
+    );
+
+    let (diff, num_mismatched) = compare(&expected, actual, tolerance.max_channel_difference);
+
+    if num_mismatched > tolerance.max_mismatched_pixels {
+        let output_dir = output_dir();
+        std::fs::create_dir_all(&output_dir).unwrap();
+
+        let actual_path = output_dir.join(format!("{name}.actual.png"));
+        let diff_path = output_dir.join(format!("{name}.diff.png"));
+        write_png(&actual_path, actual);
+        write_png(&diff_path, &diff);
+
+        panic!(
+            "{name}: {num_mismatched} pixels differ from the reference (at most {} allowed)\n  \
+             actual: {}\n  diff: {}",
+            tolerance.max_mismatched_pixels,
+            actual_path.display(),
+            diff_path.display()
+        );
+    }
+}
+
+/// Renders `graph` into a new offscreen image and reads it back.
+pub fn render(extent: Extent<u32, ScreenSpace>, graph: &RenderGraph) -> Rgba8Image {
+    render_with_context(
+        &GraphicsContext::new(&GraphicsConfig::default()),
+        extent,
+        graph,
+    )
+}
+
+/// Renders `graph` with an existing context into a new offscreen image and
+/// reads it back.
+pub fn render_with_context(
+    context: &GraphicsContext,
+    extent: Extent<u32, ScreenSpace>,
+    graph: &RenderGraph,
+) -> Rgba8Image {
+    let image = context.create_image(Extent::new(extent.width, extent.height), ImageFormat::Rgba8);
+
+    context.draw(&image, graph).unwrap();
+
+    let pixels = context
+        .read_pixels(&image)
+        .iter()
+        .flat_map(|color| [color.r, color.g, color.b, color.a])
+        .map(to_unorm8)
+        .collect();
+
+    Rgba8Image {
+        width: extent.width,
+        height: extent.height,
+        pixels,
+    }
+}
+
+/// Compares two images of the same size, returning a diff image and the
+/// number of pixels that differ by more than `max_channel_difference`.
+fn compare(
+    expected: &Rgba8Image,
+    actual: &Rgba8Image,
+    max_channel_difference: u8,
+) -> (Rgba8Image, usize) {
+    let mut num_mismatched = 0;
+    let mut diff = Vec::with_capacity(expected.pixels.len());
+
+    for (e, a) in expected
+        .pixels
+        .chunks_exact(4)
+        .zip(actual.pixels.chunks_exact(4))
+    {
+        let is_match = e
+            .iter()
+            .zip(a)
+            .all(|(e, a)| e.abs_diff(*a) <= max_channel_difference);
+
+        if is_match {
+            diff.extend_from_slice(&[e[0] / 4, e[1] / 4, e[2] / 4, 255]);
+        } else {
+            num_mismatched += 1;
+            diff.extend_from_slice(&[255, 0, 255, 255]);
+        }
+    }
+
+    (
+        Rgba8Image {
+            width: expected.width,
+            height: expected.height,
+            pixels: diff,
+        },
+        num_mismatched,
+    )
+}
+
+fn reference_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("references")
+}
+
+fn output_dir() -> PathBuf {
+    Path::new(env!("CARGO_TARGET_TMPDIR")).join("golden")
+}
+
+fn read_png(path: &Path) -> Option<Rgba8Image> {
+    let file = File::open(path).ok()?;
+
+    let mut decoder = png::Decoder::new(file);
+    decoder.set_transformations(png::Transformations::normalize_to_color8());
+    let mut reader = decoder.read_info().unwrap();
+
+    let mut buffer = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut buffer).unwrap();
+    buffer.truncate(info.buffer_size());
+
+    assert_eq!(
+        info.color_type,
+        png::ColorType::Rgba,
+        "{}: reference images must be RGBA",
+        path.display()
+    );
+
+    Some(Rgba8Image {
+        width: info.width,
+        height: info.height,
+        pixels: buffer,
+    })
+}
+
+fn write_png(path: &Path, image: &Rgba8Image) {
+    let file = BufWriter::new(File::create(path).unwrap());
+
+    let mut encoder = png::Encoder::new(file, image.width, image.height);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+
+    let mut writer = encoder.write_header().unwrap();
+    writer.write_image_data(&image.pixels).unwrap();
+}
+
+#[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+fn to_unorm8(value: f32) -> u8 {
+    (value.clamp(0.0, 1.0) * 255.0).round() as u8
+}
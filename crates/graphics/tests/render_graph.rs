@@ -0,0 +1,502 @@
+mod golden;
+
+use geometry::{
+    Extent, Insets, ObjectSpace, Offset, Path, Point, Radians, Rect, Scale, ScreenSpace, Transform,
+    WorldSpace,
+};
+use graphics::{
+    fill_path, stroke_path, BatchStatistics, BlendMode, Color, FillOptions, FillRule, Filter,
+    GraphicsConfig, GraphicsContext, ImageOptions, LineCap, LineJoin, RenderGraph,
+    RenderGraphNodeId, StrokeOptions, SvgDocument, Vertex,
+};
+
+use golden::{assert_golden, assert_golden_with_context};
+
+fn vertex(x: f32, y: f32, color: Color) -> Vertex {
+    Vertex {
+        position: Point::new(x, y),
+        color,
+    }
+}
+
+#[test]
+fn empty() {
+    assert_golden("empty", Extent::new(16, 16), &RenderGraph::new());
+}
+
+#[test]
+fn triangle() {
+    let mut graph = RenderGraph::new();
+    graph.draw_immediate(
+        RenderGraphNodeId::root(),
+        &[
+            vertex(4.0, 4.0, Color::RED),
+            vertex(28.0, 16.0, Color::GREEN),
+            vertex(8.0, 28.0, Color::BLUE),
+        ],
+        &[0, 1, 2],
+    );
+
+    assert_golden("triangle", Extent::new(32, 32), &graph);
+}
+
+#[test]
+fn quad_gradient() {
+    let mut graph = RenderGraph::new();
+    graph.draw_immediate(
+        RenderGraphNodeId::root(),
+        &[
+            vertex(0.0, 0.0, Color::RED),
+            vertex(32.0, 0.0, Color::GREEN),
+            vertex(32.0, 32.0, Color::BLUE),
+            vertex(0.0, 32.0, Color::new(1.0, 1.0, 1.0, 1.0)),
+        ],
+        &[0, 1, 2, 0, 2, 3],
+    );
+
+    assert_golden("quad_gradient", Extent::new(32, 32), &graph);
+}
+
+#[test]
+fn draw_order() {
+    let mut graph = RenderGraph::new();
+
+    // Later siblings are drawn over earlier ones.
+    for (offset, color) in [(4.0, Color::RED), (10.0, Color::GREEN), (16.0, Color::BLUE)] {
+        graph.draw_immediate(
+            RenderGraphNodeId::root(),
+            &[
+                vertex(offset, offset, color),
+                vertex(offset + 12.0, offset, color),
+                vertex(offset + 12.0, offset + 12.0, color),
+                vertex(offset, offset + 12.0, color),
+            ],
+            &[0, 1, 2, 0, 2, 3],
+        );
+    }
+
+    assert_golden("draw_order", Extent::new(32, 32), &graph);
+}
+
+#[test]
+fn fill_rules() {
+    // A pentagram with a small square at its center, filled with each rule
+    // side by side. The center is wound twice by the pentagram and once more
+    // by the square.
+    let mut builder = Path::<ObjectSpace>::builder();
+    for i in 0..5 {
+        let angle = std::f32::consts::TAU * (i * 2) as f32 / 5.0;
+        let point = Point::new(16.0 + angle.sin() * 14.0, 17.0 - angle.cos() * 14.0);
+        if i == 0 {
+            builder.move_to(point);
+        } else {
+            builder.line_to(point);
+        }
+    }
+    builder
+        .close()
+        .move_to(Point::new(14.0, 15.0))
+        .line_to(Point::new(18.0, 15.0))
+        .line_to(Point::new(18.0, 19.0))
+        .line_to(Point::new(14.0, 19.0))
+        .close();
+    let path = builder.build();
+
+    let mut graph = RenderGraph::new();
+    for (fill_rule, offset) in [(FillRule::NonZero, 0.0), (FillRule::EvenOdd, 32.0)] {
+        let options = FillOptions {
+            fill_rule,
+            ..FillOptions::default()
+        };
+        let mut tessellation = fill_path(&path, &options, Color::BLUE);
+        for vertex in &mut tessellation.vertices {
+            vertex.position.x += offset;
+        }
+
+        graph
+            .try_draw_immediate(
+                RenderGraphNodeId::root(),
+                &tessellation.vertices,
+                &tessellation.indices,
+            )
+            .unwrap();
+    }
+
+    assert_golden("fill_rules", Extent::new(64, 32), &graph);
+}
+
+#[test]
+fn strokes() {
+    let mut graph = RenderGraph::new();
+
+    // One zigzag per join style, each with a different cap.
+    let styles = [
+        (LineJoin::Miter, LineCap::Butt, Color::RED),
+        (LineJoin::Round, LineCap::Round, Color::GREEN),
+        (LineJoin::Bevel, LineCap::Square, Color::BLUE),
+    ];
+    for (i, (line_join, line_cap, color)) in styles.into_iter().enumerate() {
+        let x = 6.0 + i as f32 * 20.0;
+        let mut builder = Path::<ObjectSpace>::builder();
+        builder
+            .move_to(Point::new(x, 6.0))
+            .line_to(Point::new(x + 10.0, 12.0))
+            .line_to(Point::new(x, 18.0))
+            .line_to(Point::new(x + 10.0, 24.0));
+
+        let options = StrokeOptions {
+            width: 4.0,
+            line_join,
+            line_cap,
+            ..StrokeOptions::default()
+        };
+        let tessellation = stroke_path(&builder.build(), &options, color);
+        graph
+            .try_draw_immediate(
+                RenderGraphNodeId::root(),
+                &tessellation.vertices,
+                &tessellation.indices,
+            )
+            .unwrap();
+    }
+
+    // A dashed circle.
+    let mut builder = Path::<ObjectSpace>::builder();
+    builder
+        .move_to(Point::new(64.0, 16.0))
+        .arc_to(
+            Offset::new(10.0, 10.0),
+            Radians(0.0),
+            false,
+            true,
+            Point::new(84.0, 16.0),
+        )
+        .arc_to(
+            Offset::new(10.0, 10.0),
+            Radians(0.0),
+            false,
+            true,
+            Point::new(64.0, 16.0),
+        )
+        .close();
+
+    let options = StrokeOptions {
+        width: 2.0,
+        dashes: vec![6.0, 3.0],
+        dash_offset: 2.0,
+        ..StrokeOptions::default()
+    };
+    let tessellation = stroke_path(&builder.build(), &options, Color::RED);
+    graph
+        .try_draw_immediate(
+            RenderGraphNodeId::root(),
+            &tessellation.vertices,
+            &tessellation.indices,
+        )
+        .unwrap();
+
+    assert_golden("strokes", Extent::new(96, 32), &graph);
+}
+
+fn svg_document_graph() -> RenderGraph {
+    let document = SvgDocument::parse(
+        r##"
+        <svg xmlns="http://www.w3.org/2000/svg" width="16" height="16" viewBox="0 0 16 16">
+            <rect x="1" y="1" width="14" height="14" rx="3" fill="#1e90ff"/>
+            <g transform="rotate(45 8 8)" fill="white">
+                <rect x="7" y="3" width="2" height="10"/>
+                <rect x="3" y="7" width="10" height="2"/>
+            </g>
+            <path d="M8 5a3 3 0 1 0 0.01 0zm0 1.5a1.5 1.5 0 1 1-0.01 0z" fill="orange"/>
+            <circle cx="13" cy="13" r="2" fill="red"/>
+        </svg>
+        "##,
+    )
+    .unwrap();
+
+    let mut graph = RenderGraph::new();
+    document
+        .draw(
+            &mut graph,
+            RenderGraphNodeId::root(),
+            &Transform::scale(Scale::new(2.0, 2.0)),
+        )
+        .unwrap();
+    graph
+}
+
+#[test]
+fn svg_document() {
+    assert_golden("svg_document", Extent::new(32, 32), &svg_document_graph());
+}
+
+#[test]
+fn transforms() {
+    let square = |color| {
+        [
+            vertex(-4.0, -4.0, color),
+            vertex(4.0, -4.0, color),
+            vertex(4.0, 4.0, color),
+            vertex(-4.0, 4.0, color),
+        ]
+    };
+
+    // A square orbited by a smaller, rotated square. Each square is drawn
+    // around its own origin and placed by the transform nodes above it.
+    let mut graph = RenderGraph::new();
+    let planet = graph.transform(
+        RenderGraphNodeId::root(),
+        &Transform::translate(Offset::new(12.0, 12.0)),
+    );
+    graph.draw_immediate(planet, &square(Color::BLUE), &[0, 1, 2, 0, 2, 3]);
+
+    let moon = graph.transform(
+        planet,
+        &Transform::scale(Scale::new(0.5, 0.5))
+            .then_rotate(Radians(std::f32::consts::FRAC_PI_4))
+            .then_translate(Offset::new(10.0, 0.0)),
+    );
+    graph.draw_immediate(moon, &square(Color::RED), &[0, 1, 2, 0, 2, 3]);
+
+    // Zoom in and pan so that the pair is centered.
+    graph.set_camera(
+        Transform::<f32, WorldSpace, ScreenSpace>::translate(Offset::new(-17.0, -12.0))
+            .then_scale(Scale::new(1.5, 1.5))
+            .then_translate(Offset::new(24.0, 16.0)),
+    );
+
+    assert_golden("transforms", Extent::new(48, 32), &graph);
+}
+
+#[test]
+fn mirrored_transforms() {
+    let triangle = [
+        vertex(2.0, 2.0, Color::RED),
+        vertex(14.0, 8.0, Color::GREEN),
+        vertex(2.0, 14.0, Color::BLUE),
+    ];
+
+    // Mirroring reverses the winding of the triangles beneath it, which must
+    // not stop them from being drawn.
+    let mut graph = RenderGraph::new();
+    graph.draw_immediate(RenderGraphNodeId::root(), &triangle, &[0, 1, 2]);
+    let mirrored = graph.transform(
+        RenderGraphNodeId::root(),
+        &Transform::scale(Scale::new(-1.0, 1.0)).then_translate(Offset::new(32.0, 0.0)),
+    );
+    graph.draw_immediate(mirrored, &triangle, &[0, 1, 2]);
+
+    // Mirroring the camera as well flips the triangles back.
+    graph.set_camera(
+        Transform::<f32, WorldSpace, ScreenSpace>::scale(Scale::new(1.0, -1.0))
+            .then_translate(Offset::new(0.0, 16.0)),
+    );
+
+    assert_golden("mirrored_transforms", Extent::new(32, 16), &graph);
+}
+
+#[test]
+fn clips() {
+    let stripe = |y: f32, color| {
+        [
+            vertex(0.0, y, color),
+            vertex(24.0, y, color),
+            vertex(24.0, y + 6.0, color),
+            vertex(0.0, y + 6.0, color),
+        ]
+    };
+
+    // A scroll view: a list of stripes, scrolled up by 5 pixels and clipped to
+    // the view's bounds.
+    let mut graph = RenderGraph::new();
+    let view = graph.clip(
+        RenderGraphNodeId::root(),
+        &Rect::new(Point::new(4.0, 4.0), Point::new(20.0, 28.0)),
+    );
+    let content = graph.transform(view, &Transform::translate(Offset::new(0.0, -5.0)));
+    for (i, color) in [
+        Color::RED,
+        Color::GREEN,
+        Color::BLUE,
+        Color::RED,
+        Color::GREEN,
+        Color::BLUE,
+    ]
+    .into_iter()
+    .enumerate()
+    {
+        graph.draw_immediate(content, &stripe(i as f32 * 6.0, color), &[0, 1, 2, 0, 2, 3]);
+    }
+
+    // A panel inside the scroll view, only partially visible.
+    let panel = graph.clip(
+        content,
+        &Rect::new(Point::new(12.0, 20.0), Point::new(32.0, 40.0)),
+    );
+    graph.draw_immediate(
+        panel,
+        &[
+            vertex(0.0, 0.0, Color::new(1.0, 1.0, 1.0, 1.0)),
+            vertex(32.0, 0.0, Color::new(1.0, 1.0, 1.0, 1.0)),
+            vertex(32.0, 32.0, Color::new(1.0, 1.0, 1.0, 1.0)),
+            vertex(0.0, 32.0, Color::new(1.0, 1.0, 1.0, 1.0)),
+        ],
+        &[0, 1, 2, 0, 2, 3],
+    );
+
+    assert_golden("clips", Extent::new(32, 32), &graph);
+}
+
+#[test]
+fn layers() {
+    let rect = |x: f32, y: f32, width: f32, height: f32, color| {
+        [
+            vertex(x, y, color),
+            vertex(x + width, y, color),
+            vertex(x + width, y + height, color),
+            vertex(x, y + height, color),
+        ]
+    };
+    let quad = [0, 1, 2, 0, 2, 3];
+
+    let mut graph = RenderGraph::new();
+    let root = RenderGraphNodeId::root();
+
+    // A backdrop of vertical stripes, half translucent.
+    for (i, color) in [
+        Color::RED,
+        Color::GREEN,
+        Color::BLUE,
+        Color::new(1.0, 1.0, 0.0, 0.5),
+    ]
+    .into_iter()
+    .enumerate()
+    {
+        graph.draw_immediate(root, &rect(i as f32 * 16.0, 0.0, 16.0, 32.0, color), &quad);
+    }
+
+    // Overlapping squares faded as a group, with and without a layer.
+    let white = Color::new(1.0, 1.0, 1.0, 1.0);
+    let group = graph.layer(root, 0.5, BlendMode::Normal);
+    graph.draw_immediate(group, &rect(2.0, 2.0, 12.0, 12.0, white), &quad);
+    graph.draw_immediate(group, &rect(8.0, 8.0, 12.0, 12.0, Color::BLUE), &quad);
+    graph.draw_immediate(
+        root,
+        &rect(34.0, 2.0, 12.0, 12.0, Color::new(1.0, 1.0, 1.0, 0.5)),
+        &quad,
+    );
+    graph.draw_immediate(
+        root,
+        &rect(40.0, 8.0, 12.0, 12.0, Color::new(0.0, 0.0, 1.0, 0.5)),
+        &quad,
+    );
+
+    // A bar across the stripes with each separable blend mode.
+    let gray = Color::new(0.5, 0.5, 0.5, 1.0);
+    for (i, blend_mode) in [BlendMode::Multiply, BlendMode::Screen, BlendMode::Additive]
+        .into_iter()
+        .enumerate()
+    {
+        let layer = graph.layer(root, 1.0, blend_mode);
+        graph.draw_immediate(
+            layer,
+            &rect(0.0, 22.0 + i as f32 * 3.0, 64.0, 3.0, gray),
+            &quad,
+        );
+    }
+
+    assert_golden("layers", Extent::new(64, 32), &graph);
+}
+
+fn images_graph(context: &GraphicsContext) -> RenderGraph {
+    // A 2x2 checkerboard of primaries, and a frame with a translucent center.
+    let checker = context.create_texture(
+        Extent::new(2, 2),
+        &[
+            255, 0, 0, 255, 0, 255, 0, 255, //
+            0, 0, 255, 255, 255, 255, 255, 255,
+        ],
+    );
+    let mut frame = Vec::new();
+    for y in 0..6 {
+        for x in 0..6 {
+            let border = !(2..4).contains(&x) || !(2..4).contains(&y);
+            frame.extend_from_slice(if border {
+                &[255, 255, 255, 255]
+            } else {
+                &[0, 0, 0, 128]
+            });
+        }
+    }
+    let frame = context.create_texture(Extent::new(6, 6), &frame);
+
+    let mut graph = RenderGraph::new();
+    let root = RenderGraphNodeId::root();
+    let rect = |x: f32, y: f32, width: f32, height: f32| -> Rect<f32, ObjectSpace> {
+        Rect::new(Point::new(x, y), Point::new(x + width, y + height))
+    };
+
+    let nearest = ImageOptions {
+        filter: Filter::Nearest,
+        ..ImageOptions::default()
+    };
+    graph.draw_image(root, &checker, &rect(0.0, 0.0, 16.0, 16.0), &nearest);
+    graph.draw_image(
+        root,
+        &checker,
+        &rect(16.0, 0.0, 16.0, 16.0),
+        &ImageOptions::default(),
+    );
+    graph.draw_image(
+        root,
+        &checker,
+        &rect(32.0, 0.0, 16.0, 16.0),
+        &ImageOptions {
+            tint: Color::new(1.0, 1.0, 1.0, 0.5),
+            ..nearest
+        },
+    );
+
+    // The frame keeps 2px borders however it is stretched.
+    let nine_slice = ImageOptions {
+        nine_slice: Some(Insets::uniform(2.0)),
+        ..nearest
+    };
+    graph.draw_image(root, &frame, &rect(2.0, 18.0, 28.0, 12.0), &nine_slice);
+    graph.draw_image(root, &frame, &rect(34.0, 18.0, 12.0, 12.0), &nine_slice);
+    graph
+}
+
+#[test]
+fn images() {
+    let context = GraphicsContext::new(&GraphicsConfig::default());
+    let graph = images_graph(&context);
+    assert_golden_with_context(&context, "images", Extent::new(48, 32), &graph);
+}
+
+#[test]
+fn batching() {
+    // Batched graphs draw the same images with fewer draw calls.
+    let mut graph = svg_document_graph();
+    assert_eq!(
+        graph.batch(),
+        BatchStatistics {
+            draw_calls_before: 5,
+            draw_calls_after: 1,
+        }
+    );
+    assert_golden("svg_document", Extent::new(32, 32), &graph);
+
+    // The tinted checkerboard is moved beneath the linearly filtered one to
+    // join the first, and the frames are merged.
+    let context = GraphicsContext::new(&GraphicsConfig::default());
+    let mut graph = images_graph(&context);
+    assert_eq!(
+        graph.batch(),
+        BatchStatistics {
+            draw_calls_before: 5,
+            draw_calls_after: 3,
+        }
+    );
+    assert_golden_with_context(&context, "images", Extent::new(48, 32), &graph);
+}